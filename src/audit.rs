@@ -0,0 +1,256 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use gitlab::api::projects;
+use gitlab::api::AsyncQuery;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use tracing::warn;
+
+use crate::client::Client;
+use crate::endpoints::{
+    Pipelines, ProjectCommits, ProjectCredentials, ProjectIssues, ProtectedEnvironments,
+};
+use crate::environments::list_environments;
+use crate::error::{ApiError, ConfigError};
+use crate::pagination;
+use crate::types::{Environment, ProjectId, ProtectedEnvironment};
+
+/// The settings every project should have, by their name in the projects API, e.g.
+/// `merge_method: ff` or `only_allow_merge_if_pipeline_succeeds: true`.
+pub type Baseline = BTreeMap<String, Value>;
+
+/// A setting of a project that differs from the baseline.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct SettingDrift {
+    pub setting: String,
+    pub expected: Value,
+    /// `null` if the project does not have the setting, e.g. on an edition without it.
+    pub actual: Value,
+}
+
+pub fn load_baseline(path: &Path) -> Result<Baseline, ConfigError> {
+    let content = fs::read_to_string(path).map_err(|source| ConfigError::Read {
+        path: path.to_owned(),
+        source,
+    })?;
+    serde_yaml::from_str(&content).map_err(|source| ConfigError::Baseline {
+        path: path.to_owned(),
+        source,
+    })
+}
+
+/// The settings of a project as the projects API returns them.
+pub async fn project_settings(gitlab: &Client, project: ProjectId) -> Result<Value, ApiError> {
+    let endpoint = projects::Project::builder()
+        .project(project)
+        .build()
+        .map_err(ApiError::builder)?;
+    Ok(endpoint.query_async(gitlab).await?)
+}
+
+/// The settings in which `settings` differ from `baseline`.
+pub fn settings_drift(baseline: &Baseline, settings: &Value) -> Vec<SettingDrift> {
+    baseline
+        .iter()
+        .filter_map(|(setting, expected)| {
+            let actual = settings.get(setting).cloned().unwrap_or(Value::Null);
+            (actual != *expected).then(|| SettingDrift {
+                setting: setting.clone(),
+                expected: expected.clone(),
+                actual,
+            })
+        })
+        .collect()
+}
+
+/// The kinds of credentials of a project by their path in the API.
+pub const CREDENTIAL_KINDS: [&str; 4] =
+    ["deploy_tokens", "deploy_keys", "access_tokens", "triggers"];
+
+/// Reads the date of dates as well as timestamps, as the credentials APIs use both.
+fn date<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<NaiveDate>, D::Error> {
+    let date: Option<String> = Option::deserialize(deserializer)?;
+    date.map(|date| {
+        let day = date.get(..10).unwrap_or(&date);
+        NaiveDate::parse_from_str(day, "%Y-%m-%d").map_err(serde::de::Error::custom)
+    })
+    .transpose()
+}
+
+/// A token or key that gives access to a project.
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct Credential {
+    #[serde(skip_deserializing)]
+    pub kind: &'static str,
+    #[serde(alias = "title", alias = "description")]
+    pub name: String,
+    /// `None` if it never expires.
+    #[serde(default, deserialize_with = "date")]
+    pub expires_at: Option<NaiveDate>,
+    #[serde(default)]
+    pub revoked: bool,
+}
+
+impl Credential {
+    /// Whether it expires before `until`, or expired already.
+    pub fn expires_before(&self, until: NaiveDate) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at < until)
+    }
+}
+
+/// The credentials of `project` that were not revoked. Kinds that we may not list are left
+/// out with a warning.
+pub async fn project_credentials(
+    gitlab: &Client,
+    project: ProjectId,
+) -> Result<Vec<Credential>, ApiError> {
+    let mut credentials = vec![];
+    for kind in CREDENTIAL_KINDS {
+        let endpoint = ProjectCredentials { project, kind };
+        match pagination::list::<_, Credential>(gitlab, &endpoint, None, |_| ()).await {
+            Ok(listed) => credentials.extend(
+                listed
+                    .into_iter()
+                    .filter(|credential| !credential.revoked)
+                    .map(|credential| Credential { kind, ..credential }),
+            ),
+            Err(e) => warn!("could not list the {} of project {}: {}", kind, project, e),
+        }
+    }
+    Ok(credentials)
+}
+
+/// Whether `endpoint` lists anything, looking at no more than one item.
+async fn lists_any<E>(gitlab: &Client, endpoint: &E) -> Result<bool, ApiError>
+where
+    E: gitlab::api::Endpoint + gitlab::api::Pageable,
+{
+    let items: Vec<Value> = pagination::list(gitlab, endpoint, Some(1), |_| ()).await?;
+    Ok(!items.is_empty())
+}
+
+/// Whether `project` had no commits, pipelines or issue updates since `since`.
+pub async fn is_inactive(
+    gitlab: &Client,
+    project: ProjectId,
+    since: DateTime<Utc>,
+) -> Result<bool, ApiError> {
+    let active = lists_any(gitlab, &ProjectCommits { project, since }).await?
+        || lists_any(
+            gitlab,
+            &Pipelines {
+                project,
+                updated_after: since,
+                ref_: None,
+                status: None,
+            },
+        )
+        .await?
+        || lists_any(
+            gitlab,
+            &ProjectIssues {
+                project,
+                updated_after: since,
+            },
+        )
+        .await?;
+    Ok(!active)
+}
+
+/// The names of the `environments` of `tier` that are not `protected`.
+fn unprotected(
+    environments: &[Environment],
+    protected: &[ProtectedEnvironment],
+    tier: &str,
+) -> Vec<String> {
+    environments
+        .iter()
+        .filter(|env| env.tier.as_deref() == Some(tier))
+        .filter(|env| !protected.iter().any(|p| p.name == env.name))
+        .map(|env| env.name.clone())
+        .collect()
+}
+
+/// The environments of `tier` in `project` that anyone with write access may deploy to.
+pub async fn unprotected_environments(
+    gitlab: &Client,
+    project: ProjectId,
+    tier: &str,
+) -> Result<Vec<String>, ApiError> {
+    let environments = list_environments(gitlab, project, None).await?;
+    let protected: Vec<ProtectedEnvironment> =
+        pagination::list(gitlab, &ProtectedEnvironments { project }, None, |_| ()).await?;
+    Ok(unprotected(&environments, &protected, tier))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_settings_drift() {
+        let baseline: Baseline = serde_yaml::from_str(
+            "merge_method: ff\nvisibility: private\napprovals_before_merge: 1\n",
+        )
+        .unwrap();
+        let settings = json!({"merge_method": "merge", "visibility": "private"});
+        assert_eq!(
+            settings_drift(&baseline, &settings),
+            vec![
+                SettingDrift {
+                    setting: "approvals_before_merge".to_string(),
+                    expected: json!(1),
+                    actual: Value::Null,
+                },
+                SettingDrift {
+                    setting: "merge_method".to_string(),
+                    expected: json!("ff"),
+                    actual: json!("merge"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_credential() {
+        let token: Credential =
+            serde_json::from_value(json!({"name": "ci", "expires_at": "2024-05-01T00:00:00Z"}))
+                .unwrap();
+        let key: Credential =
+            serde_json::from_value(json!({"title": "deploy", "expires_at": null})).unwrap();
+        let until = NaiveDate::from_ymd_opt(2024, 5, 2).unwrap();
+        assert!(token.expires_before(until));
+        assert_eq!(key.name, "deploy");
+        assert!(!key.expires_before(until));
+    }
+
+    #[test]
+    fn test_unprotected() {
+        let environment = |name: &str, tier: &str| Environment {
+            id: 1,
+            name: name.to_string(),
+            tier: Some(tier.to_string()),
+            external_url: None,
+            state: None,
+            last_deployment: None,
+        };
+        let environments = vec![
+            environment("production", "production"),
+            environment("production-eu", "production"),
+            environment("staging", "staging"),
+        ];
+        let protected = vec![ProtectedEnvironment {
+            name: "production".to_string(),
+            deploy_access_levels: vec![],
+            required_approval_count: 0,
+        }];
+        assert_eq!(
+            unprotected(&environments, &protected, "production"),
+            vec!["production-eu".to_string()]
+        );
+    }
+}
@@ -0,0 +1,215 @@
+use bytes::Bytes;
+use chrono::{DateTime, Duration, Utc};
+use http::header::{HeaderName, HeaderValue};
+use http::Response;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::fs::{self, DirBuilder, OpenOptions};
+use std::hash::{Hash, Hasher};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::config::Context;
+use crate::types::ProjectId;
+
+/// How long the projects of a namespace are reused without asking GitLab again.
+const PROJECT_INDEX_TTL_MINUTES: i64 = 10;
+
+/// A response stored on disk together with the `ETag` it can be revalidated with.
+#[derive(Deserialize, Serialize)]
+pub struct Entry {
+    pub etag: String,
+    pub fetched_at: DateTime<Utc>,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Entry {
+    pub fn from_response(etag: &str, rsp: &Response<Bytes>) -> Option<Entry> {
+        let headers = rsp
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_owned())))
+            .collect();
+        Some(Entry {
+            etag: etag.to_owned(),
+            fetched_at: Utc::now(),
+            headers,
+            body: String::from_utf8(rsp.body().to_vec()).ok()?,
+        })
+    }
+
+    pub fn into_response(self) -> Response<Bytes> {
+        let mut rsp = Response::new(Bytes::from(self.body));
+        for (name, value) in self.headers {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(&value),
+            ) {
+                rsp.headers_mut().append(name, value);
+            }
+        }
+        rsp
+    }
+}
+
+/// The name to ID mapping of the projects of one namespace.
+#[derive(Deserialize, Serialize)]
+struct ProjectIndex {
+    fetched_at: DateTime<Utc>,
+    projects: Vec<(String, ProjectId)>,
+}
+
+impl ProjectIndex {
+    fn is_fresh(&self, now: DateTime<Utc>) -> bool {
+        now.signed_duration_since(self.fetched_at) < Duration::minutes(PROJECT_INDEX_TTL_MINUTES)
+    }
+}
+
+/// Whether the response of `url` may be stored. CI/CD variables hold secrets, so they are
+/// never written to disk.
+fn is_cacheable(url: &str) -> bool {
+    let path = url
+        .parse::<http::Uri>()
+        .map(|uri| uri.path().to_owned())
+        .unwrap_or_default();
+    !path.split('/').any(|segment| segment == "variables")
+}
+
+/// Writes `contents` to `path` in `dir`, both readable by the current user only.
+fn write_private(dir: &Path, path: &Path, contents: &str) -> io::Result<()> {
+    let mut builder = DirBuilder::new();
+    builder.recursive(true);
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+        builder.mode(0o700);
+        options.mode(0o600);
+    }
+    builder.create(dir)?;
+    options.open(path)?.write_all(contents.as_bytes())
+}
+
+fn hash(value: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// Responses are stored per server and token, so that different users never share entries.
+pub struct Cache {
+    dir: PathBuf,
+}
+
+impl Cache {
+    pub fn root() -> Option<PathBuf> {
+        dirs::cache_dir().map(|dir| dir.join("gitlabctl"))
+    }
+
+    pub fn for_context(context: &Context) -> Option<Cache> {
+        let scope = hash(&format!("{}\n{}", context.server, context.access_token));
+        Cache::root().map(|root| Cache {
+            dir: root.join(scope),
+        })
+    }
+
+    fn path(&self, url: &str) -> PathBuf {
+        self.dir.join(hash(url) + ".json")
+    }
+
+    pub fn get(&self, url: &str) -> Option<Entry> {
+        if !is_cacheable(url) {
+            return None;
+        }
+        let contents = fs::read_to_string(self.path(url)).ok()?;
+        serde_json::from_str(&contents).ok()
+    }
+
+    /// Stores `entry`. The cache is best effort, so failing to write it is not an error.
+    pub fn put(&self, url: &str, entry: &Entry) {
+        if !is_cacheable(url) {
+            return;
+        }
+        if let Ok(contents) = serde_json::to_string(entry) {
+            write_private(&self.dir, &self.path(url), &contents).unwrap_or_default();
+        }
+    }
+
+    fn project_index_path(&self, namespace: &str) -> PathBuf {
+        self.dir.join(format!("projects-{}.json", hash(namespace)))
+    }
+
+    fn project_index(&self, namespace: &str) -> Option<ProjectIndex> {
+        let contents = fs::read_to_string(self.project_index_path(namespace)).ok()?;
+        serde_json::from_str(&contents).ok()
+    }
+
+    /// Returns the projects of `namespace` if they were stored recently enough.
+    pub fn projects(&self, namespace: &str) -> Option<Vec<(String, ProjectId)>> {
+        self.project_index(namespace)
+            .filter(|index| index.is_fresh(Utc::now()))
+            .map(|index| index.projects)
+    }
+
+    /// Returns the projects of `namespace` however old they are, e.g. when offline.
+    pub fn stale_projects(&self, namespace: &str) -> Option<Vec<(String, ProjectId)>> {
+        self.project_index(namespace).map(|index| index.projects)
+    }
+
+    pub fn put_projects(&self, namespace: &str, projects: &[(String, ProjectId)]) {
+        let index = ProjectIndex {
+            fetched_at: Utc::now(),
+            projects: projects.to_vec(),
+        };
+        if let Ok(contents) = serde_json::to_string(&index) {
+            write_private(&self.dir, &self.project_index_path(namespace), &contents)
+                .unwrap_or_default();
+        }
+    }
+
+    /// Removes the cached responses of all contexts.
+    pub fn clear() -> io::Result<()> {
+        match Cache::root() {
+            Some(root) if root.exists() => fs::remove_dir_all(root),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_project_index_expires() {
+        let index = ProjectIndex {
+            fetched_at: Utc::now(),
+            projects: vec![("project".to_string(), 1)],
+        };
+        assert!(index.is_fresh(Utc::now()));
+        assert!(!index.is_fresh(Utc::now() + Duration::hours(1)));
+    }
+
+    #[test]
+    fn test_variables_are_not_cacheable() {
+        let api = "https://gitlab.com/api/v4";
+        assert!(is_cacheable(&format!(
+            "{api}/projects/1/environments?page=1"
+        )));
+        assert!(!is_cacheable(&format!("{api}/projects/1/variables?page=1")));
+        assert!(!is_cacheable(&format!("{api}/groups/2/variables/KEY")));
+    }
+
+    #[test]
+    fn test_entry_roundtrip() {
+        let mut rsp = Response::new(Bytes::from_static(b"[{\"id\": 1}]"));
+        rsp.headers_mut()
+            .insert("link", HeaderValue::from_static("<next>; rel=\"next\""));
+        let entry = Entry::from_response("W/\"abc\"", &rsp).unwrap();
+        let restored = entry.into_response();
+        assert_eq!(restored.body().as_ref(), b"[{\"id\": 1}]");
+        assert_eq!(restored.headers()["link"], "<next>; rel=\"next\"");
+    }
+}
@@ -0,0 +1,1557 @@
+use clap::builder::PossibleValuesParser;
+use clap::{ArgAction, Args, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use gitlabctl::environments::NameFilter;
+use gitlabctl::error::Error;
+use gitlabctl::import;
+use gitlabctl::selector::Selector;
+use gitlabctl::table;
+
+#[derive(Parser)]
+#[command(
+    name = "gitlabctl",
+    version = "0.1",
+    author = "Bijan Chokoufe Nejad <bijan@chokoufe.com>",
+    about = "gitlabctl controls gitlab from the command line"
+)]
+pub struct Cli {
+    #[command(flatten)]
+    pub global: GlobalArgs,
+    #[command(subcommand)]
+    pub command: Commands,
+}
+
+/// The flags that every command accepts.
+#[derive(Args)]
+pub struct GlobalArgs {
+    /// Logs more details, up to every request with -vv and more with -vvv.
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    pub verbose: u8,
+    /// Refuses to run any command that modifies data on the server.
+    #[arg(long, global = true)]
+    pub read_only: bool,
+    /// Neither uses nor updates the on-disk response cache.
+    #[arg(long, global = true)]
+    pub no_cache: bool,
+    /// Answers from the on-disk cache only, without connecting to GitLab.
+    #[arg(long, global = true, conflicts_with = "no_cache")]
+    pub offline: bool,
+    /// How often failed requests are retried, overriding the config.
+    #[arg(long, global = true, value_name = "N")]
+    pub retries: Option<u32>,
+    /// How long a single request may take, e.g. 30s. 0 disables the timeout.
+    #[arg(long, global = true, default_value = "30s", value_parser = humantime::parse_duration)]
+    pub timeout: Duration,
+    /// How long the whole command may take, e.g. 5m.
+    #[arg(long, global = true, value_parser = humantime::parse_duration)]
+    pub deadline: Option<Duration>,
+    /// Answers yes to every confirmation, for non-interactive use.
+    #[arg(short, long, global = true)]
+    pub yes: bool,
+    /// The configured context to use instead of the current one. `get` commands accept it
+    /// more than once to query several instances.
+    #[arg(long, global = true, value_name = "NAME")]
+    pub context: Vec<String>,
+    /// Only queries the projects of a namespace with this name, or that match a glob like
+    /// `svc-*` or a regex between slashes.
+    #[arg(long, global = true, value_name = "PATTERN", value_parser = NameFilter::parse)]
+    pub project_filter: Option<NameFilter>,
+    /// Only queries the projects of a namespace with this topic.
+    #[arg(long, global = true)]
+    pub topic: Option<String>,
+    /// Also queries the archived projects of a namespace.
+    #[arg(long, global = true)]
+    pub include_archived: bool,
+    /// Only queries the projects directly in a namespace, not the ones of its subgroups.
+    #[arg(long, global = true)]
+    pub no_subgroups: bool,
+    /// Only queries the projects whose topics have these labels, and shows the environments
+    /// of the tier, e.g. `team=payments,tier=production`.
+    #[arg(short = 'l', long, global = true, value_parser = Selector::parse)]
+    pub selector: Option<Selector>,
+}
+
+impl GlobalArgs {
+    /// The one context given with `--context`, for the commands that talk to one instance.
+    pub fn single_context(&self) -> Result<Option<&str>, Error> {
+        match self.context.as_slice() {
+            [] => Ok(None),
+            [name] => Ok(Some(name)),
+            _ => Err(Error::Usage(
+                "Only get commands accept more than one --context".to_string(),
+            )),
+        }
+    }
+}
+
+#[derive(Subcommand)]
+pub enum Commands {
+    /// get resources from gitlab
+    Get {
+        #[command(subcommand)]
+        resource: GetResource,
+    },
+    /// delete resources from gitlab
+    Delete {
+        #[command(subcommand)]
+        resource: DeleteResource,
+    },
+    /// create resources in gitlab
+    Create {
+        #[command(subcommand)]
+        resource: CreateResource,
+    },
+    /// update resources in gitlab
+    Update {
+        #[command(subcommand)]
+        resource: UpdateResource,
+    },
+    /// export the configuration of the projects of a group as code
+    Export {
+        #[command(subcommand)]
+        resource: ExportResource,
+    },
+    /// open a resource in the browser
+    Open {
+        #[command(subcommand)]
+        resource: OpenResource,
+    },
+    /// manage the on-disk response cache
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommand,
+    },
+    /// manage the gitlabctl configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// browse the environments in an interactive dashboard
+    Ui(UiArgs),
+    /// serve the environments as JSON over HTTP, refreshed in the background
+    Serve(ServeArgs),
+    /// export Prometheus metrics of the environments
+    Metrics(MetricsArgs),
+    /// show the past deployments of a resource
+    History {
+        #[command(subcommand)]
+        resource: HistoryResource,
+    },
+    /// check the projects of a group against what they should be
+    Audit {
+        #[command(subcommand)]
+        command: AuditCommand,
+    },
+    /// change the settings of many projects of a group at once
+    Bulk {
+        #[command(subcommand)]
+        command: BulkCommand,
+    },
+    /// delete resources that are no longer needed
+    Prune {
+        #[command(subcommand)]
+        resource: PruneResource,
+    },
+    /// show the pipelines of a project
+    Ci {
+        #[command(subcommand)]
+        command: CiCommand,
+    },
+    /// follow a resource across projects
+    Trace {
+        #[command(subcommand)]
+        resource: TraceResource,
+    },
+    /// work with the issues of a project
+    Issue {
+        #[command(subcommand)]
+        command: IssueCommand,
+    },
+    /// work with the merge requests of a project
+    Mr {
+        #[command(subcommand)]
+        command: MrCommand,
+    },
+    /// list and clear the to-do items of the current user
+    Todo {
+        #[command(subcommand)]
+        command: TodoCommand,
+    },
+    /// add time spent on an issue or merge request
+    Spend(TimeArgs),
+    /// set the time estimate of an issue or merge request
+    Estimate(TimeArgs),
+    /// run the pipeline of a ref and its deploy job to an environment
+    Deploy(DeployArgs),
+    /// add notes to resources
+    Annotate {
+        #[command(subcommand)]
+        resource: AnnotateResource,
+    },
+    /// wait until a deployment or pipeline meets a condition
+    Wait {
+        #[command(subcommand)]
+        resource: WaitResource,
+    },
+    /// compare two resources
+    Diff {
+        #[command(subcommand)]
+        resource: DiffResource,
+    },
+    /// list the commits and merge requests between two refs
+    Compare(CompareArgs),
+    /// search projects, issues, merge requests, code or commits
+    Search(SearchArgs),
+    /// show the version, the supported features and the health of the GitLab instance
+    ClusterInfo(ClusterInfoArgs),
+    /// inspect a self-hosted instance with the token of an administrator
+    Admin {
+        #[command(subcommand)]
+        command: AdminCommand,
+    },
+    /// show how many requests the token has left before GitLab rate limits it
+    #[command(name = "ratelimit", visible_alias = "rate-limit")]
+    RateLimit(RateLimitArgs),
+    /// report statistics about pipelines and merge requests
+    Stats {
+        #[command(subcommand)]
+        command: StatsCommand,
+    },
+    /// receive GitLab webhooks and print or handle their events
+    Listen(ListenArgs),
+    /// replace gitlabctl with its latest release
+    SelfUpdate(SelfUpdateArgs),
+    /// print the completion script for a shell
+    Completion { shell: Shell },
+    /// generate documentation
+    Docs {
+        #[command(subcommand)]
+        command: DocsCommand,
+    },
+    /// Any other command runs the `gitlabctl-<command>` plugin on the PATH
+    #[command(external_subcommand)]
+    Plugin(Vec<String>),
+}
+
+#[derive(Subcommand)]
+pub enum GetResource {
+    /// the last deployment of every environment
+    #[command(visible_alias = "environment", alias = "envs", alias = "env")]
+    Environments(EnvironmentsArgs),
+    /// the failed tests of a pipeline's JUnit report
+    TestReport(TestReportArgs),
+    /// the next runs of the pipeline schedules of a group on a timeline
+    #[command(alias = "schedule")]
+    Schedules(SchedulesArgs),
+    /// the review apps of a group with their URLs, optionally checking that they respond
+    #[command(alias = "review-app")]
+    ReviewApps(ReviewAppsArgs),
+    /// the recent pipelines of a group, optionally only the latest of every ref
+    #[command(alias = "pipeline")]
+    Pipelines(PipelinesArgs),
+}
+
+#[derive(Args)]
+pub struct PipelinesArgs {
+    /// Filters the resources to the given group path, including subgroups. Can be given
+    /// more than once to show several groups together.
+    #[arg(short, long)]
+    pub namespace: Vec<String>,
+    /// Only shows the pipelines of this branch or tag.
+    #[arg(long = "ref", value_name = "REF")]
+    pub ref_: Option<String>,
+    /// Only shows the pipelines with this status.
+    #[arg(long, value_enum)]
+    pub status: Option<PipelineStatus>,
+    /// Only shows the newest pipeline of every project and ref.
+    #[arg(long)]
+    pub latest: bool,
+    /// How far back to look, e.g. 30d.
+    #[arg(long, default_value = "7d", value_parser = humantime::parse_duration)]
+    pub since: Duration,
+    /// The most recent pipelines of each project to look at.
+    #[arg(long, default_value_t = 20)]
+    pub limit: usize,
+    #[arg(short, long, value_enum, default_value_t)]
+    pub output: OutputFormat,
+    /// The maximum number of requests to run at the same time.
+    #[arg(long, default_value_t = 16, value_parser = clap::value_parser!(u16).range(1..))]
+    pub concurrency: u16,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum PipelineStatus {
+    Created,
+    Pending,
+    Running,
+    Success,
+    Failed,
+    Canceled,
+    Skipped,
+    Manual,
+    Scheduled,
+}
+
+#[derive(Args)]
+pub struct ReviewAppsArgs {
+    /// Filters the resources to the given group path, including subgroups.
+    #[arg(short, long)]
+    pub namespace: Option<String>,
+    /// The names of the review apps, a glob or a regex between slashes.
+    #[arg(long, default_value = "review/*", value_parser = NameFilter::parse)]
+    pub environment: NameFilter,
+    /// Sends a HEAD request to every URL and flags the review apps that do not respond.
+    #[arg(long)]
+    pub check: bool,
+    #[arg(short, long, value_enum, default_value_t)]
+    pub output: OutputFormat,
+    /// The maximum number of requests to run at the same time.
+    #[arg(long, default_value_t = 16, value_parser = clap::value_parser!(u16).range(1..))]
+    pub concurrency: u16,
+}
+
+#[derive(Args)]
+pub struct SchedulesArgs {
+    /// Filters the resources to the given group path, including subgroups.
+    #[arg(short, long)]
+    pub namespace: Option<String>,
+    /// Only shows the schedules that run within this time, e.g. 12h.
+    #[arg(long, default_value = "24h", value_parser = humantime::parse_duration)]
+    pub within: Duration,
+    /// The maximum number of requests to run at the same time.
+    #[arg(long, default_value_t = 16, value_parser = clap::value_parser!(u16).range(1..))]
+    pub concurrency: u16,
+}
+
+#[derive(Args)]
+pub struct TestReportArgs {
+    /// The ID of the pipeline.
+    #[arg(long)]
+    pub pipeline: u64,
+    /// Only shows the tests that fail in the pipeline but did not in this other pipeline.
+    #[arg(long, value_name = "OTHER_PIPELINE")]
+    pub diff: Option<u64>,
+    /// The path of the project, by default the project of the git checkout.
+    #[arg(short, long)]
+    pub project: Option<String>,
+    #[arg(short, long, value_enum, default_value_t)]
+    pub output: OutputFormat,
+}
+
+#[derive(Subcommand)]
+pub enum DeleteResource {
+    /// stop and delete environments
+    #[command(visible_alias = "environment", alias = "envs", alias = "env")]
+    Environments(DeleteEnvironmentsArgs),
+    /// remove the protection of an environment
+    ProtectedEnvironment(DeleteProtectedEnvironmentArgs),
+}
+
+#[derive(Args)]
+pub struct DeleteProtectedEnvironmentArgs {
+    /// The name of the environment, e.g. `production`.
+    pub name: String,
+    /// The project with this path, by default the project of the git checkout.
+    #[arg(short, long)]
+    pub project: Option<String>,
+}
+
+#[derive(Subcommand)]
+pub enum CreateResource {
+    /// create a snippet from stdin and print its URL
+    Snippet(CreateSnippetArgs),
+    /// protect an environment, so that only the given roles, users and groups may deploy
+    ProtectedEnvironment(ProtectedEnvironmentArgs),
+}
+
+#[derive(Subcommand)]
+pub enum ExportResource {
+    /// the settings, variables, protected branches and webhooks of every project
+    Config(ExportConfigArgs),
+}
+
+/// How `export config` writes the configuration.
+#[derive(Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum ExportFormat {
+    /// Resources of the Terraform GitLab provider with `import` blocks.
+    #[default]
+    Hcl,
+    Yaml,
+}
+
+#[derive(Args)]
+pub struct ExportConfigArgs {
+    /// Filters the resources to the given group path, including subgroups.
+    #[arg(short, long)]
+    pub namespace: Option<String>,
+    #[arg(short, long, value_enum, default_value_t)]
+    pub output: ExportFormat,
+    /// The maximum number of requests to run at the same time.
+    #[arg(long, default_value_t = 16, value_parser = clap::value_parser!(u16).range(1..))]
+    pub concurrency: u16,
+}
+
+#[derive(Subcommand)]
+pub enum UpdateResource {
+    /// change who may deploy to a protected environment or how many approvals it needs
+    ProtectedEnvironment(ProtectedEnvironmentArgs),
+}
+
+#[derive(Args)]
+pub struct ProtectedEnvironmentArgs {
+    /// The name of the environment, e.g. `production`.
+    pub name: String,
+    /// The project with this path, by default the project of the git checkout.
+    #[arg(short, long)]
+    pub project: Option<String>,
+    /// The roles that may deploy.
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub access_level: Vec<AccessLevel>,
+    /// The users that may deploy, by username.
+    #[arg(long, value_delimiter = ',')]
+    pub user: Vec<String>,
+    /// The groups that may deploy, by path.
+    #[arg(long, value_delimiter = ',')]
+    pub group: Vec<String>,
+    /// How many approvals a deployment needs before it runs.
+    #[arg(long)]
+    pub required_approvals: Option<u64>,
+}
+
+/// Who can see a snippet.
+#[derive(Clone, Copy, Default, ValueEnum)]
+pub enum Visibility {
+    #[default]
+    Private,
+    Internal,
+    Public,
+}
+
+#[derive(Args)]
+pub struct CreateSnippetArgs {
+    #[arg(short, long)]
+    pub title: String,
+    #[arg(long, value_enum, default_value_t)]
+    pub visibility: Visibility,
+    /// The file name of the content, which GitLab highlights by its extension.
+    #[arg(short, long, default_value = "snippet.txt")]
+    pub file_name: String,
+    /// Creates the snippet in the project with this path instead of a personal one.
+    #[arg(short, long)]
+    pub project: Option<String>,
+}
+
+#[derive(Args)]
+pub struct DeleteEnvironmentsArgs {
+    /// The environments to delete, by name, a glob like `review/*` or a regex between slashes.
+    #[arg(required = true, value_parser = NameFilter::parse)]
+    pub names: Vec<NameFilter>,
+    /// Filters the resources to the given group path, including subgroups.
+    #[arg(short, long)]
+    pub namespace: Option<String>,
+}
+
+#[derive(Subcommand)]
+pub enum OpenResource {
+    /// a merge request
+    #[command(alias = "merge-request")]
+    Mr {
+        /// Chosen from the open merge requests if not given.
+        iid: Option<u64>,
+        /// The path of the project, by default the project of the git checkout.
+        #[arg(short, long)]
+        project: Option<String>,
+    },
+    /// a pipeline
+    Pipeline {
+        id: u64,
+        /// The path of the project, by default the project of the git checkout.
+        #[arg(short, long)]
+        project: Option<String>,
+    },
+    /// an environment, given as `<project>/<environment>` or by name with --project
+    #[command(alias = "env")]
+    Environment {
+        /// Chosen from the environments of the project if not given.
+        target: Option<String>,
+        /// The path of the project.
+        #[arg(short, long)]
+        project: Option<String>,
+    },
+    /// a project
+    Project {
+        /// The path of the project, by default the project of the git checkout.
+        project: Option<String>,
+    },
+}
+
+/// Pagination controls of `get environments`. Other `get` commands fetch everything, or take
+/// a `--limit` of their own that counts results rather than projects.
+#[derive(Args)]
+pub struct ListArgs {
+    /// Only fetch the first N projects, to explore a namespace quickly.
+    #[arg(long, value_name = "N")]
+    pub limit: Option<usize>,
+    /// How many results to request per page, at most 100.
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u8).range(1..=100))]
+    pub page_size: Option<u8>,
+}
+
+#[derive(Args)]
+pub struct EnvironmentsArgs {
+    /// Filters the resources to the given group path, including subgroups. Can be given
+    /// more than once to show several groups together.
+    #[arg(short, long)]
+    pub namespace: Vec<String>,
+    /// Only shows the project with this path, by default the project of the git checkout.
+    #[arg(short, long, conflicts_with = "namespace")]
+    pub project: Option<String>,
+    /// Only shows the environments with this name, a glob like `review/*` or a regex between
+    /// slashes like `/^review-\d+$/`.
+    #[arg(short, long, value_parser = NameFilter::parse)]
+    pub environment: Option<NameFilter>,
+    #[command(flatten)]
+    pub list: ListArgs,
+    /// `wide` adds a column with the URL of each environment, `jsonl` prints a JSON object
+    /// per row as soon as it is fetched.
+    #[arg(short, long, value_enum, default_value_t)]
+    pub output: EnvironmentsFormat,
+    /// Opens the URL of the environment with this name in the browser once it is fetched.
+    #[arg(long, value_name = "ENVIRONMENT", conflicts_with_all = ["watch", "all_contexts"])]
+    pub open: Option<String>,
+    /// Prints how long discovery, fetching and rendering took.
+    #[arg(long)]
+    pub timings: bool,
+    /// Exits with 5 if the environments of a project run different commits, the rows that
+    /// are shown in red.
+    #[arg(long, visible_alias = "fail-on-drift")]
+    pub exit_code: bool,
+    /// Prints all rows again, sorted, once everything is fetched.
+    #[arg(long)]
+    pub summary: bool,
+    /// Prints the rows once everything is fetched, grouped by their deployment tier with a
+    /// subtotal for each.
+    #[arg(long, conflicts_with = "summary")]
+    pub by_tier: bool,
+    /// Colors the UPDATED cell yellow once a deployment is older than this, e.g. 14d, and red
+    /// once it is older than twice this.
+    #[arg(long, value_parser = humantime::parse_duration)]
+    pub stale_after: Option<Duration>,
+    /// Only shows the environments whose newest deployment failed.
+    #[arg(long)]
+    pub only_failed: bool,
+    /// Only shows the environments of this deployment tier.
+    #[arg(long, value_parser = PossibleValuesParser::new(table::TIERS))]
+    pub tier: Option<String>,
+    /// Always uses the REST API, even if GraphQL is available.
+    #[arg(long)]
+    pub rest: bool,
+    /// Refreshes the table every --interval, highlighting what changed.
+    #[arg(short, long, conflicts_with_all = ["summary", "by_tier", "exit_code", "timings"])]
+    pub watch: bool,
+    /// Posts the projects that run different commits to the webhook in the [notify] config.
+    /// With --watch, only when projects start to run different commits.
+    #[arg(long)]
+    pub notify: bool,
+    /// How often --watch refreshes, e.g. 10s.
+    #[arg(long, default_value = "5s", value_parser = humantime::parse_duration)]
+    pub interval: Duration,
+    /// The maximum number of requests to run at the same time.
+    #[arg(long, default_value_t = 16, value_parser = clap::value_parser!(u16).range(1..))]
+    pub concurrency: u16,
+    /// Adds a column with the title of the deployed commit.
+    #[arg(long)]
+    pub show_commit_title: bool,
+    /// The maximum number of environments of one project to fetch at the same time, unless
+    /// only one project is shown with --project.
+    #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u16).range(1..))]
+    pub project_concurrency: u16,
+    /// Queries all configured contexts at the same time, with a column for the instance.
+    #[arg(long, conflicts_with_all = ["watch", "project"])]
+    pub all_contexts: bool,
+}
+
+#[derive(Args)]
+pub struct UiArgs {
+    /// Filters the resources to the given group path, including subgroups.
+    #[arg(short, long)]
+    pub namespace: Option<String>,
+    /// The maximum number of requests to run at the same time.
+    #[arg(long, default_value_t = 16, value_parser = clap::value_parser!(u16).range(1..))]
+    pub concurrency: u16,
+}
+
+#[derive(Args)]
+pub struct ServeArgs {
+    /// The address to listen on, `:9100` listens on all interfaces.
+    #[arg(long, default_value = ":9100")]
+    pub listen: String,
+    /// Filters the resources to the given group path, including subgroups.
+    #[arg(short, long)]
+    pub namespace: Option<String>,
+    /// How often the environments are fetched again, e.g. 5m.
+    #[arg(long, default_value = "60s", value_parser = humantime::parse_duration)]
+    pub interval: Duration,
+    /// The maximum number of requests to run at the same time.
+    #[arg(long, default_value_t = 16, value_parser = clap::value_parser!(u16).range(1..))]
+    pub concurrency: u16,
+}
+
+#[derive(Args)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct MetricsArgs {
+    #[command(subcommand)]
+    pub command: Option<MetricsCommand>,
+    #[command(flatten)]
+    pub serve: ServeArgs,
+    /// Prints the metrics once instead of serving them, e.g. for the textfile collector.
+    #[arg(long)]
+    pub once: bool,
+}
+
+#[derive(Subcommand)]
+pub enum MetricsCommand {
+    /// compute the DORA metrics of the projects of a group
+    Dora(DoraArgs),
+}
+
+/// How results are printed.
+#[derive(Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Table,
+    Json,
+}
+
+/// How `get environments` prints the rows.
+#[derive(Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum EnvironmentsFormat {
+    #[default]
+    Table,
+    Wide,
+    Json,
+    Jsonl,
+}
+
+#[derive(Args)]
+pub struct DoraArgs {
+    /// Filters the resources to the given group path, including subgroups.
+    #[arg(short, long)]
+    pub namespace: Option<String>,
+    /// How far back to look, e.g. 30d.
+    #[arg(long, default_value = "90d", value_parser = humantime::parse_duration)]
+    pub since: Duration,
+    /// The environments that count as production.
+    #[arg(long, value_delimiter = ',', default_value = "production,prod")]
+    pub environment: Vec<String>,
+    #[arg(short, long, value_enum, default_value_t)]
+    pub output: OutputFormat,
+    /// The maximum number of requests to run at the same time.
+    #[arg(long, default_value_t = 16, value_parser = clap::value_parser!(u16).range(1..))]
+    pub concurrency: u16,
+}
+
+#[derive(Subcommand)]
+pub enum HistoryResource {
+    /// the deployments to an environment, given as `<project>/<environment>` or by name with
+    /// --project
+    #[command(alias = "env")]
+    Environment(EnvironmentHistoryArgs),
+}
+
+#[derive(Args)]
+pub struct EnvironmentHistoryArgs {
+    pub target: String,
+    /// The path of the project.
+    #[arg(short, long)]
+    pub project: Option<String>,
+    /// The number of deployments to show.
+    #[arg(long, default_value_t = 30)]
+    pub limit: usize,
+}
+
+#[derive(Subcommand)]
+pub enum DiffResource {
+    /// who has access to one group but not the other, or at another level
+    Members {
+        /// The path of the first group.
+        a: String,
+        /// The path of the second group.
+        b: String,
+        /// Compares two projects instead of groups.
+        #[arg(long)]
+        projects: bool,
+    },
+}
+
+/// How `compare` prints the changes.
+#[derive(Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum CompareFormat {
+    #[default]
+    Text,
+    /// release notes that list every merge request once
+    Markdown,
+    Json,
+}
+
+#[derive(Args)]
+pub struct CompareArgs {
+    /// The ref to compare from, e.g. the last release tag.
+    pub from: String,
+    /// The ref to compare to.
+    pub to: String,
+    /// The path of the project, by default the project of the git checkout.
+    #[arg(short, long)]
+    pub project: Option<String>,
+    #[arg(long, value_enum, default_value_t)]
+    pub format: CompareFormat,
+}
+
+/// What `search` looks for.
+#[derive(Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum SearchScope {
+    #[default]
+    Projects,
+    Issues,
+    MergeRequests,
+    Blobs,
+    Commits,
+}
+
+impl SearchScope {
+    /// The scope in the search API.
+    pub fn name(self) -> &'static str {
+        match self {
+            SearchScope::Projects => "projects",
+            SearchScope::Issues => "issues",
+            SearchScope::MergeRequests => "merge_requests",
+            SearchScope::Blobs => "blobs",
+            SearchScope::Commits => "commits",
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct ClusterInfoArgs {
+    #[arg(short, long, value_enum, default_value_t)]
+    pub output: OutputFormat,
+}
+
+#[derive(Args)]
+pub struct RateLimitArgs {
+    #[arg(short, long, value_enum, default_value_t)]
+    pub output: OutputFormat,
+}
+
+#[derive(Args)]
+pub struct SearchArgs {
+    /// The text to search for.
+    pub query: String,
+    #[arg(short, long, value_enum, default_value_t)]
+    pub scope: SearchScope,
+    /// Searches the given group path, including subgroups, instead of the whole instance.
+    #[arg(short, long)]
+    pub namespace: Option<String>,
+    /// The maximum number of results.
+    #[arg(long, default_value_t = 50)]
+    pub limit: usize,
+    #[arg(short, long, value_enum, default_value_t)]
+    pub output: OutputFormat,
+}
+
+#[derive(Subcommand)]
+pub enum StatsCommand {
+    /// rank the jobs that failed and then passed on retry
+    Flaky(FlakyArgs),
+    /// show how the test coverage of a project changed over time
+    Coverage(CoverageArgs),
+    /// report how fast the merge requests of a group are reviewed and merged
+    #[command(alias = "merge-requests")]
+    Mrs(MergeRequestStatsArgs),
+    /// show the burndown of the issues of a milestone
+    Issues(IssueStatsArgs),
+    /// count the commits, merge requests and reviews of everyone
+    Contributors(ContributorsArgs),
+    /// group the failed jobs of a group by name and failure reason
+    Failures(FailuresArgs),
+    /// list what uses the storage of the projects of a group
+    Storage(StorageArgs),
+    /// sum up the time estimated and spent on the issues of a milestone by assignee
+    Time(TimeStatsArgs),
+}
+
+/// What `stats storage` sorts the projects by, the largest first.
+#[derive(Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum StorageSort {
+    #[default]
+    Total,
+    Repository,
+    Artifacts,
+    Lfs,
+    Packages,
+    Registry,
+}
+
+#[derive(Args)]
+pub struct StorageArgs {
+    /// Filters the resources to the given group path, including subgroups.
+    #[arg(short, long)]
+    pub namespace: Option<String>,
+    #[arg(long, value_enum, default_value_t)]
+    pub sort_by: StorageSort,
+    /// Only shows this many of the largest projects.
+    #[arg(long)]
+    pub top: Option<usize>,
+    #[arg(short, long, value_enum, default_value_t)]
+    pub output: OutputFormat,
+}
+
+#[derive(Args)]
+pub struct FailuresArgs {
+    /// Filters the resources to the given group path, including subgroups.
+    #[arg(short, long)]
+    pub namespace: Option<String>,
+    /// How far back to look, e.g. 30d.
+    #[arg(long, default_value = "14d", value_parser = humantime::parse_duration)]
+    pub since: Duration,
+    /// The most recent failed jobs of each project to look at.
+    #[arg(long, default_value_t = 100)]
+    pub jobs: usize,
+    /// Only shows this many of the most frequent failures.
+    #[arg(long, default_value_t = 20)]
+    pub top: usize,
+    #[arg(short, long, value_enum, default_value_t)]
+    pub output: OutputFormat,
+    /// The maximum number of requests to run at the same time.
+    #[arg(long, default_value_t = 16, value_parser = clap::value_parser!(u16).range(1..))]
+    pub concurrency: u16,
+}
+
+#[derive(Args)]
+pub struct ContributorsArgs {
+    /// The path of the project, by default the project of the git checkout.
+    #[arg(short, long, conflicts_with = "namespace")]
+    pub project: Option<String>,
+    /// Counts the contributions to all projects of this group path instead.
+    #[arg(short, long)]
+    pub namespace: Option<String>,
+    /// How far back to look, e.g. 90d.
+    #[arg(long, default_value = "1y", value_parser = humantime::parse_duration)]
+    pub since: Duration,
+    #[arg(short, long, value_enum, default_value_t)]
+    pub output: OutputFormat,
+    /// The maximum number of requests to run at the same time.
+    #[arg(long, default_value_t = 16, value_parser = clap::value_parser!(u16).range(1..))]
+    pub concurrency: u16,
+}
+
+#[derive(Args)]
+pub struct IssueStatsArgs {
+    /// The title of the milestone, e.g. 24.04.
+    #[arg(long)]
+    pub milestone: String,
+    /// Filters the resources to the given group path, including subgroups.
+    #[arg(short, long)]
+    pub namespace: Option<String>,
+    #[arg(short, long, value_enum, default_value_t)]
+    pub output: OutputFormat,
+}
+
+#[derive(Args)]
+pub struct TimeStatsArgs {
+    /// The title of the milestone, e.g. 24.04.
+    #[arg(long)]
+    pub milestone: String,
+    /// Filters the resources to the given group path, including subgroups.
+    #[arg(short, long)]
+    pub namespace: Option<String>,
+    #[arg(short, long, value_enum, default_value_t)]
+    pub output: OutputFormat,
+}
+
+#[derive(Args)]
+pub struct MergeRequestStatsArgs {
+    /// Filters the resources to the given group path, including subgroups.
+    #[arg(short, long)]
+    pub namespace: Option<String>,
+    /// Looks at the merge requests opened since, e.g. 90d.
+    #[arg(long, default_value = "30d", value_parser = humantime::parse_duration)]
+    pub since: Duration,
+    /// Only shows this many of the most active reviewers.
+    #[arg(long, default_value_t = 10)]
+    pub top: usize,
+    #[arg(short, long, value_enum, default_value_t)]
+    pub output: OutputFormat,
+    /// The maximum number of requests to run at the same time.
+    #[arg(long, default_value_t = 16, value_parser = clap::value_parser!(u16).range(1..))]
+    pub concurrency: u16,
+}
+
+#[derive(Args)]
+pub struct CoverageArgs {
+    /// The path of the project, by default the project of the git checkout.
+    #[arg(short, long)]
+    pub project: Option<String>,
+    /// Only looks at the pipelines of this branch or tag, e.g. main.
+    #[arg(long = "ref")]
+    pub ref_: Option<String>,
+    /// How far back to look, e.g. 30d.
+    #[arg(long, default_value = "60d", value_parser = humantime::parse_duration)]
+    pub since: Duration,
+    /// The most recent pipelines to look at.
+    #[arg(long, default_value_t = 100)]
+    pub pipelines: usize,
+    #[arg(short, long, value_enum, default_value_t)]
+    pub output: OutputFormat,
+}
+
+#[derive(Args)]
+pub struct FlakyArgs {
+    /// Filters the resources to the given group path, including subgroups.
+    #[arg(short, long)]
+    pub namespace: Option<String>,
+    /// How far back to look, e.g. 30d.
+    #[arg(long, default_value = "14d", value_parser = humantime::parse_duration)]
+    pub since: Duration,
+    /// The most recent pipelines of each project to look at.
+    #[arg(long, default_value_t = 100)]
+    pub pipelines: usize,
+    /// Only shows this many of the flakiest jobs.
+    #[arg(long, default_value_t = 20)]
+    pub top: usize,
+    #[arg(short, long, value_enum, default_value_t)]
+    pub output: OutputFormat,
+    /// The maximum number of requests to run at the same time.
+    #[arg(long, default_value_t = 16, value_parser = clap::value_parser!(u16).range(1..))]
+    pub concurrency: u16,
+}
+
+#[derive(Args)]
+pub struct ListenArgs {
+    /// The address to listen on, `:9000` listens on all interfaces.
+    #[arg(long, default_value = ":9000")]
+    pub listen: String,
+    /// Only handles these events, e.g. pipeline,deployment. Handles all by default.
+    #[arg(long, value_delimiter = ',')]
+    pub events: Vec<String>,
+    /// Runs this program with the payload on stdin for every event, instead of printing it.
+    #[arg(long, value_name = "PROGRAM")]
+    pub exec: Option<PathBuf>,
+    /// Rejects webhooks whose secret token differs.
+    #[arg(long, env = "GITLABCTL_WEBHOOK_SECRET", hide_env_values = true)]
+    pub secret: Option<String>,
+}
+
+#[derive(Args)]
+pub struct SelfUpdateArgs {
+    /// Only says whether a newer release is available.
+    #[arg(long)]
+    pub check: bool,
+}
+
+#[derive(Subcommand)]
+pub enum CacheCommand {
+    /// remove all cached responses
+    Clear,
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    /// interactively create the configuration file
+    Init,
+    /// import contexts from the config of another tool
+    Import {
+        /// The tool to import from.
+        #[arg(long, value_parser = PossibleValuesParser::new(import::SOURCES))]
+        from: String,
+        /// Only import this netrc machine.
+        #[arg(long)]
+        machine: Option<String>,
+    },
+    /// check the config file and the server and token of every context
+    Validate {
+        /// Also signs in to every context to check that its token works.
+        #[arg(long)]
+        live: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum CiCommand {
+    /// draw the stages and jobs of a pipeline with their status and duration
+    View(CiViewArgs),
+    /// diff the CI configuration of two refs with all includes expanded
+    Diff(CiDiffArgs),
+}
+
+#[derive(Subcommand)]
+pub enum TraceResource {
+    /// draw the tree of a pipeline and the downstream pipelines its trigger jobs created
+    Pipeline(TracePipelineArgs),
+}
+
+#[derive(Args)]
+pub struct TracePipelineArgs {
+    /// The ID of the pipeline.
+    pub pipeline: u64,
+    /// The project with this path, by default the project of the git checkout.
+    #[arg(short, long)]
+    pub project: Option<String>,
+    /// Also follows the trigger jobs of the downstream pipelines, all the way down.
+    #[arg(long)]
+    pub follow_downstream: bool,
+}
+
+#[derive(Args)]
+pub struct CiDiffArgs {
+    /// The ref to compare from, e.g. `main`.
+    pub from: String,
+    /// The ref to compare to, e.g. a feature branch.
+    pub to: String,
+    /// The project with this path, by default the project of the git checkout.
+    #[arg(short, long)]
+    pub project: Option<String>,
+}
+
+#[derive(Args)]
+pub struct CiViewArgs {
+    /// The project with this path, by default the project of the git checkout.
+    #[arg(short, long)]
+    pub project: Option<String>,
+    /// The ID of the pipeline, by default the newest one.
+    #[arg(long)]
+    pub pipeline: Option<u64>,
+}
+
+/// The time to track on an issue or merge request.
+#[derive(Args)]
+#[command(group(clap::ArgGroup::new("issuable").required(true).args(["issue", "mr"])))]
+pub struct TimeArgs {
+    /// E.g. 1h30m, where a day has 8 hours and a week 5 days.
+    pub duration: String,
+    /// The IID of the issue, e.g. 42 for #42.
+    #[arg(long)]
+    pub issue: Option<u64>,
+    /// The IID of the merge request, e.g. 42 for !42.
+    #[arg(long)]
+    pub mr: Option<u64>,
+    /// The project with this path, by default the project of the git checkout.
+    #[arg(short, long)]
+    pub project: Option<String>,
+}
+
+/// The description of a new issue or merge request.
+#[derive(Args)]
+pub struct DescriptionArgs {
+    /// Fills in the description template with this name, e.g. `bug` for
+    /// `.gitlab/issue_templates/bug.md`.
+    #[arg(short, long)]
+    pub template: Option<String>,
+    #[arg(short, long, conflicts_with = "template")]
+    pub description: Option<String>,
+    /// Opens the description in $EDITOR to finish it.
+    #[arg(short, long)]
+    pub edit: bool,
+}
+
+#[derive(Subcommand)]
+pub enum IssueCommand {
+    /// create an issue, optionally from a description template
+    Create(IssueCreateArgs),
+}
+
+#[derive(Args)]
+pub struct IssueCreateArgs {
+    pub title: String,
+    /// The project with this path, by default the project of the git checkout.
+    #[arg(short, long)]
+    pub project: Option<String>,
+    #[command(flatten)]
+    pub description: DescriptionArgs,
+}
+
+#[derive(Subcommand)]
+pub enum TodoCommand {
+    /// list the pending to-do items
+    #[command(visible_alias = "ls")]
+    List(TodoListArgs),
+    /// mark to-do items as done
+    Done(TodoDoneArgs),
+}
+
+#[derive(Args)]
+pub struct TodoListArgs {
+    #[arg(short, long, value_enum, default_value_t)]
+    pub output: OutputFormat,
+}
+
+#[derive(Args)]
+pub struct TodoDoneArgs {
+    /// The IDs of the to-do items, as listed by `todo list`.
+    #[arg(required_unless_present = "all")]
+    pub ids: Vec<u64>,
+    /// Marks all pending to-do items as done.
+    #[arg(long, conflicts_with = "ids")]
+    pub all: bool,
+}
+
+#[derive(Subcommand)]
+pub enum MrCommand {
+    /// fetch the source branch of a merge request and check it out, also from forks
+    Checkout(MrCheckoutArgs),
+    /// create a merge request, optionally from a description template
+    Create(MrCreateArgs),
+}
+
+#[derive(Args)]
+pub struct MrCreateArgs {
+    pub title: String,
+    /// The project with this path, by default the project of the git checkout.
+    #[arg(short, long)]
+    pub project: Option<String>,
+    /// The branch to merge, by default the one that is checked out.
+    #[arg(long)]
+    pub source: Option<String>,
+    /// The branch to merge into, by default the default branch of the project.
+    #[arg(long)]
+    pub target: Option<String>,
+    #[command(flatten)]
+    pub description: DescriptionArgs,
+}
+
+#[derive(Args)]
+pub struct MrCheckoutArgs {
+    /// The IID of the merge request, e.g. 123 for !123.
+    pub iid: u64,
+    /// The local branch, by default the source branch or `mr/<iid>` for forks.
+    #[arg(short, long)]
+    pub branch: Option<String>,
+}
+
+#[derive(Subcommand)]
+pub enum AdminCommand {
+    /// show the Sidekiq queues with a backlog and the unfinished background migrations
+    Sidekiq(SidekiqArgs),
+}
+
+#[derive(Args)]
+pub struct SidekiqArgs {
+    /// Also shows the empty queues and the finished migrations.
+    #[arg(long)]
+    pub all: bool,
+    #[arg(short, long, value_enum, default_value_t)]
+    pub output: OutputFormat,
+}
+
+#[derive(Subcommand)]
+pub enum AuditCommand {
+    /// compare the settings of every project to a baseline
+    Settings(AuditSettingsArgs),
+    /// list the tokens and keys of every project with when they expire
+    Credentials(AuditCredentialsArgs),
+    /// list the projects without commits, pipelines or issues for a while, to archive them
+    InactiveProjects(InactiveProjectsArgs),
+    /// list the production environments that anyone with write access may deploy to
+    UnprotectedEnvironments(UnprotectedEnvironmentsArgs),
+}
+
+#[derive(Args)]
+pub struct UnprotectedEnvironmentsArgs {
+    /// Filters the resources to the given group path, including subgroups.
+    #[arg(short, long)]
+    pub namespace: Option<String>,
+    /// The deployment tier of the environments that should be protected.
+    #[arg(long, default_value = "production")]
+    pub tier: String,
+    #[arg(short, long, value_enum, default_value_t)]
+    pub output: OutputFormat,
+    /// The maximum number of requests to run at the same time.
+    #[arg(long, default_value_t = 16, value_parser = clap::value_parser!(u16).range(1..))]
+    pub concurrency: u16,
+}
+
+#[derive(Args)]
+pub struct InactiveProjectsArgs {
+    /// Filters the resources to the given group path, including subgroups.
+    #[arg(short, long)]
+    pub namespace: Option<String>,
+    /// Lists the projects without activity for this long, e.g. 6months.
+    #[arg(long, default_value = "1y", value_parser = humantime::parse_duration)]
+    pub no_commits_since: Duration,
+    #[arg(short, long, value_enum, default_value_t)]
+    pub output: OutputFormat,
+    /// The maximum number of requests to run at the same time.
+    #[arg(long, default_value_t = 16, value_parser = clap::value_parser!(u16).range(1..))]
+    pub concurrency: u16,
+}
+
+#[derive(Args)]
+pub struct AuditCredentialsArgs {
+    /// Filters the resources to the given group path, including subgroups.
+    #[arg(short, long)]
+    pub namespace: Option<String>,
+    /// Flags the credentials that expire within this time, e.g. 14d.
+    #[arg(long, default_value = "30d", value_parser = humantime::parse_duration)]
+    pub expiring_within: Duration,
+    /// Only lists the flagged credentials.
+    #[arg(long)]
+    pub only_expiring: bool,
+    #[arg(short, long, value_enum, default_value_t)]
+    pub output: OutputFormat,
+    /// The maximum number of requests to run at the same time.
+    #[arg(long, default_value_t = 16, value_parser = clap::value_parser!(u16).range(1..))]
+    pub concurrency: u16,
+}
+
+#[derive(Args)]
+pub struct AuditSettingsArgs {
+    /// A YAML file with the settings every project should have, by their name in the
+    /// projects API, e.g. `merge_method: ff`.
+    #[arg(long)]
+    pub baseline: PathBuf,
+    /// Filters the resources to the given group path, including subgroups.
+    #[arg(short, long)]
+    pub namespace: Option<String>,
+    #[arg(short, long, value_enum, default_value_t)]
+    pub output: OutputFormat,
+    /// The maximum number of requests to run at the same time.
+    #[arg(long, default_value_t = 16, value_parser = clap::value_parser!(u16).range(1..))]
+    pub concurrency: u16,
+}
+
+#[derive(Subcommand)]
+pub enum BulkCommand {
+    /// create or update a CI/CD variable in every project
+    SetVariable(SetVariableArgs),
+    /// protect a branch the same way in every project
+    ProtectBranch(ProtectBranchArgs),
+    /// add a webhook to every project that does not have it yet
+    AddHook(AddHookArgs),
+    /// point the webhooks with a URL to another one, e.g. to rotate an endpoint
+    UpdateHook(UpdateHookArgs),
+    /// remove the webhooks with a URL
+    RemoveHook(RemoveHookArgs),
+}
+
+/// The events a webhook can be called for.
+pub const HOOK_EVENTS: &[&str] = &[
+    "push",
+    "tag_push",
+    "merge_requests",
+    "issues",
+    "note",
+    "job",
+    "pipeline",
+    "deployment",
+    "releases",
+    "wiki_page",
+];
+
+#[derive(Args)]
+pub struct AddHookArgs {
+    pub url: String,
+    /// The events to call the webhook for.
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_value = "push",
+        value_parser = PossibleValuesParser::new(HOOK_EVENTS)
+    )]
+    pub events: Vec<String>,
+    /// The secret token GitLab sends along in the X-Gitlab-Token header.
+    #[arg(long, env = "GITLABCTL_HOOK_TOKEN")]
+    pub token: Option<String>,
+    /// Only lists the projects that would change.
+    #[arg(long)]
+    pub dry_run: bool,
+    #[command(flatten)]
+    pub projects: BulkProjects,
+}
+
+#[derive(Args)]
+pub struct UpdateHookArgs {
+    /// The URL the webhooks call now.
+    pub old_url: String,
+    /// The URL they should call instead.
+    pub new_url: String,
+    /// Only lists the projects that would change.
+    #[arg(long)]
+    pub dry_run: bool,
+    #[command(flatten)]
+    pub projects: BulkProjects,
+}
+
+#[derive(Args)]
+pub struct RemoveHookArgs {
+    pub url: String,
+    /// Only lists the projects that would change.
+    #[arg(long)]
+    pub dry_run: bool,
+    #[command(flatten)]
+    pub projects: BulkProjects,
+}
+
+/// Who may push or merge to a protected branch.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum AccessLevel {
+    Noone,
+    Developers,
+    Maintainers,
+    Admins,
+}
+
+impl AccessLevel {
+    /// The access level as GitLab numbers it.
+    pub fn level(self) -> u64 {
+        match self {
+            AccessLevel::Noone => 0,
+            AccessLevel::Developers => 30,
+            AccessLevel::Maintainers => 40,
+            AccessLevel::Admins => 60,
+        }
+    }
+
+    pub fn from_level(level: u64) -> Option<AccessLevel> {
+        [
+            AccessLevel::Noone,
+            AccessLevel::Developers,
+            AccessLevel::Maintainers,
+            AccessLevel::Admins,
+        ]
+        .iter()
+        .copied()
+        .find(|access| access.level() == level)
+    }
+}
+
+#[derive(Args)]
+pub struct ProtectBranchArgs {
+    /// The branch, or a wildcard like `release/*`.
+    pub branch: String,
+    /// Who may merge to the branch.
+    #[arg(long, value_enum, default_value = "maintainers")]
+    pub merge: AccessLevel,
+    /// Who may push to the branch.
+    #[arg(long, value_enum, default_value = "noone")]
+    pub push: AccessLevel,
+    /// Only reports the projects whose protection differs, without changing them.
+    #[arg(long)]
+    pub dry_run: bool,
+    #[command(flatten)]
+    pub projects: BulkProjects,
+}
+
+/// Which projects of a group a bulk command changes.
+#[derive(Args)]
+pub struct BulkProjects {
+    /// Filters the resources to the given group path, including subgroups.
+    #[arg(short, long)]
+    pub namespace: Option<String>,
+    /// Only changes the projects with these names, globs like `svc-*` or regexes between
+    /// slashes.
+    #[arg(long, value_delimiter = ',', value_parser = NameFilter::parse)]
+    pub only_projects: Vec<NameFilter>,
+    /// The maximum number of requests to run at the same time.
+    #[arg(long, default_value_t = 16, value_parser = clap::value_parser!(u16).range(1..))]
+    pub concurrency: u16,
+}
+
+#[derive(Args)]
+pub struct SetVariableArgs {
+    /// The variable as `KEY=value`.
+    #[arg(value_parser = parse_variable)]
+    pub variable: (String, String),
+    /// Only exposes the variable to protected branches and tags.
+    #[arg(long)]
+    pub protected: bool,
+    /// Hides the value in job logs.
+    #[arg(long)]
+    pub masked: bool,
+    #[command(flatten)]
+    pub projects: BulkProjects,
+}
+
+fn parse_variable(variable: &str) -> Result<(String, String), String> {
+    match variable.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_owned(), value.to_owned())),
+        _ => Err("expected KEY=value".to_owned()),
+    }
+}
+
+#[derive(Subcommand)]
+pub enum PruneResource {
+    /// delete the merged or inactive branches of a project, but never protected ones
+    Branches(PruneBranchesArgs),
+}
+
+#[derive(Args)]
+#[command(group(clap::ArgGroup::new("which").required(true).multiple(true).args(["merged", "older_than"])))]
+pub struct PruneBranchesArgs {
+    /// The path of the project, by default the project of the git checkout.
+    #[arg(short, long)]
+    pub project: Option<String>,
+    /// Deletes the branches that were merged into the default branch.
+    #[arg(long)]
+    pub merged: bool,
+    /// Deletes the branches without commits for this long, e.g. 90d. With --merged, only the
+    /// merged branches without commits for this long.
+    #[arg(long, value_parser = humantime::parse_duration)]
+    pub older_than: Option<Duration>,
+    /// Only lists the branches that would be deleted.
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+/// A `<field>=<value>` that `wait` waits for.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Condition {
+    pub field: String,
+    pub value: String,
+}
+
+fn parse_condition(condition: &str) -> Result<Condition, String> {
+    match condition.split_once('=') {
+        Some((field, value)) if !field.is_empty() && !value.is_empty() => Ok(Condition {
+            field: field.to_owned(),
+            value: value.to_owned(),
+        }),
+        _ => Err("expected <field>=<value>, e.g. status=success".to_owned()),
+    }
+}
+
+#[derive(Subcommand)]
+pub enum WaitResource {
+    /// the last deployment to an environment, given as `<project>/<environment>` or by name
+    /// with --project
+    Deployment {
+        target: String,
+        /// The path of the project.
+        #[arg(short, long)]
+        project: Option<String>,
+        /// What to wait for, `sha=<commit>` or `status=<status>`. Without a status, waits
+        /// for the deployment to succeed.
+        #[arg(long = "for", required = true, value_parser = parse_condition)]
+        conditions: Vec<Condition>,
+        #[command(flatten)]
+        wait: WaitArgs,
+    },
+    /// a pipeline
+    Pipeline {
+        id: u64,
+        /// The path of the project, by default the project of the git checkout.
+        #[arg(short, long)]
+        project: Option<String>,
+        /// What to wait for, `status=<status>` or `sha=<commit>`.
+        #[arg(long = "for", required = true, value_parser = parse_condition)]
+        conditions: Vec<Condition>,
+        #[command(flatten)]
+        wait: WaitArgs,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum AnnotateResource {
+    /// comment on the commit of the last deployment to an environment
+    Deployment(AnnotateDeploymentArgs),
+}
+
+#[derive(Args)]
+pub struct AnnotateDeploymentArgs {
+    /// The path of the project and the name of the environment, e.g. group/app/production.
+    #[arg(value_name = "PROJECT/ENVIRONMENT")]
+    pub target: String,
+    /// The note, e.g. "canary at 10%".
+    pub message: String,
+    /// Also comments on the merge requests that the deployment shipped.
+    #[arg(long)]
+    pub merge_requests: bool,
+}
+
+#[derive(Args)]
+pub struct DeployArgs {
+    /// The path of the project, by default the project of the git checkout.
+    #[arg(short, long)]
+    pub project: Option<String>,
+    /// The environment to deploy to, e.g. production.
+    #[arg(long)]
+    pub env: String,
+    /// The branch or tag to deploy.
+    #[arg(long = "ref", value_name = "REF")]
+    pub ref_: String,
+    /// Streams the log of the deploy job and shows the environment once it finished.
+    #[arg(short, long)]
+    pub follow: bool,
+}
+
+#[derive(Args)]
+pub struct WaitArgs {
+    /// How long to wait before giving up with exit code 6.
+    #[arg(long, default_value = "30m", value_parser = humantime::parse_duration)]
+    pub timeout: Duration,
+    /// The longest pause between two checks, which start out more frequent.
+    #[arg(long, default_value = "30s", value_parser = humantime::parse_duration)]
+    pub max_interval: Duration,
+}
+
+#[derive(Subcommand)]
+pub enum DocsCommand {
+    /// write man pages for all commands
+    Man {
+        /// The directory to write the pages to.
+        #[arg(long, default_value = ".")]
+        out_dir: PathBuf,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_fail_on_drift_is_exit_code() {
+        let cli = Cli::try_parse_from(["gitlabctl", "get", "environments", "--fail-on-drift"]);
+        match cli.unwrap().command {
+            Commands::Get {
+                resource: GetResource::Environments(args),
+            } => assert!(args.exit_code),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_unknown_resources_are_rejected() {
+        assert!(Cli::try_parse_from(["gitlabctl", "get", "environment"]).is_ok());
+        assert!(Cli::try_parse_from(["gitlabctl", "get", "enviroments"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_condition() {
+        assert_eq!(
+            parse_condition("sha=abc123"),
+            Ok(Condition {
+                field: "sha".to_string(),
+                value: "abc123".to_string()
+            })
+        );
+        assert!(parse_condition("status").is_err());
+        assert!(parse_condition("=success").is_err());
+    }
+
+    #[test]
+    fn test_parse_variable() {
+        assert_eq!(
+            parse_variable("URL=https://x?a=b"),
+            Ok(("URL".to_string(), "https://x?a=b".to_string()))
+        );
+        assert!(parse_variable("=value").is_err());
+    }
+}
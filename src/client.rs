@@ -0,0 +1,392 @@
+use async_trait::async_trait;
+use bytes::Bytes;
+use chrono::{DateTime, TimeZone, Utc};
+use gitlab::api::{ApiError, AsyncClient, RestClient};
+use gitlab::{AsyncGitlab, GitlabBuilder};
+use http::header::{HeaderMap, HeaderValue, ETAG, IF_NONE_MATCH};
+use http::{request, Method, Response, StatusCode};
+use rand::Rng;
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+use url::Url;
+
+use crate::cache::{Cache, Entry};
+use crate::config::{Context, RetryPolicy};
+use crate::environments::ProjectFilter;
+use crate::error;
+
+/// The error of REST requests, which gitlab does not export by name.
+pub type RestError = <AsyncGitlab as RestClient>::Error;
+
+/// How often a throttled request is retried before its response is passed on.
+const MAX_RATE_LIMIT_RETRIES: u32 = 5;
+/// The wait when GitLab throttles without saying for how long.
+const DEFAULT_RATE_LIMIT_WAIT_SECS: i64 = 10;
+const MAX_RATE_LIMIT_WAIT_SECS: i64 = 120;
+
+fn header_number(headers: &HeaderMap, name: &str) -> Option<i64> {
+    headers.get(name)?.to_str().ok()?.trim().parse().ok()
+}
+
+/// How long GitLab asks us to wait, from `Retry-After` or else the `RateLimit-Reset` timestamp.
+fn rate_limit_wait(headers: &HeaderMap, now: i64) -> Duration {
+    let secs = header_number(headers, "retry-after")
+        .or_else(|| header_number(headers, "ratelimit-reset").map(|reset| reset - now))
+        .unwrap_or(DEFAULT_RATE_LIMIT_WAIT_SECS);
+    Duration::from_secs(secs.clamp(1, MAX_RATE_LIMIT_WAIT_SECS) as u64)
+}
+
+/// The rate limit of the token, as the `RateLimit-*` headers of a response report it.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct RateLimit {
+    /// The requests allowed per window.
+    pub limit: Option<i64>,
+    /// The requests made in the current window.
+    pub observed: Option<i64>,
+    pub remaining: Option<i64>,
+    /// When the window ends and `remaining` is back at `limit`.
+    pub reset: Option<DateTime<Utc>>,
+}
+
+impl RateLimit {
+    pub fn from_headers(headers: &HeaderMap) -> RateLimit {
+        RateLimit {
+            limit: header_number(headers, "ratelimit-limit"),
+            observed: header_number(headers, "ratelimit-observed"),
+            remaining: header_number(headers, "ratelimit-remaining"),
+            reset: header_number(headers, "ratelimit-reset")
+                .and_then(|reset| Utc.timestamp_opt(reset, 0).single()),
+        }
+    }
+
+    /// Whether GitLab limits the token at all, otherwise it sends no headers.
+    pub fn is_limited(&self) -> bool {
+        *self != RateLimit::default()
+    }
+}
+
+/// The wait before retry number `retry` (starting at 0), where `random` is in `[0, 1)`.
+fn backoff(policy: &RetryPolicy, retry: u32, random: f64) -> Duration {
+    let base = policy.backoff_ms.saturating_mul(1 << retry.min(16)) as f64;
+    Duration::from_millis((base * (1.0 + policy.jitter * random)) as u64)
+}
+
+/// Connection errors and server errors are worth retrying, but only for requests that do no
+/// harm when they reach GitLab twice.
+fn is_transient<E>(method: &Method, result: &Result<Response<Bytes>, ApiError<E>>) -> bool
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    let idempotent = [Method::GET, Method::HEAD, Method::PUT, Method::DELETE].contains(method);
+    idempotent
+        && match result {
+            Ok(rsp) => rsp.status().is_server_error(),
+            Err(ApiError::Client { .. }) => true,
+            Err(_) => false,
+        }
+}
+
+#[derive(Default)]
+pub struct Options {
+    pub use_cache: bool,
+    pub retry: RetryPolicy,
+    /// How long a single request may take before it is given up.
+    pub timeout: Option<Duration>,
+    /// How many results list requests ask for per page, instead of as many as possible.
+    pub page_size: Option<usize>,
+    /// Answers all requests from the cache without connecting to GitLab.
+    pub offline: bool,
+    /// Which projects of a namespace to query.
+    pub projects: ProjectFilter,
+}
+
+/// Wraps the GitLab client to add the behaviour that all requests share, like caching,
+/// retries and backing off when GitLab throttles us.
+pub struct Client {
+    /// Not connected when offline.
+    gitlab: Option<AsyncGitlab>,
+    /// The base URL of the REST API, e.g. `https://gitlab.com/api/v4/`.
+    api: Url,
+    cache: Option<Cache>,
+    retry: RetryPolicy,
+    timeout: Option<Duration>,
+    page_size: Option<usize>,
+    project_filter: ProjectFilter,
+    /// Set when GitLab reported that no requests are left, so that no request is sent before.
+    paused_until: Mutex<Option<Instant>>,
+    /// When the oldest response that was answered offline was fetched.
+    data_as_of: Mutex<Option<DateTime<Utc>>>,
+}
+
+impl Client {
+    pub async fn new(context: &Context, options: Options) -> Result<Client, error::ApiError> {
+        // A server given with `http://` is spoken to without TLS, e.g. on a local instance
+        let (server, insecure) = match context.server.strip_prefix("http://") {
+            Some(server) => (server, true),
+            None => (context.server.as_str(), false),
+        };
+        let scheme = if insecure { "http" } else { "https" };
+        let api = Url::parse(&format!("{}://{}/api/v4/", scheme, server))
+            .map_err(error::ApiError::builder)?;
+        let gitlab = if options.offline {
+            None
+        } else {
+            let mut builder = GitlabBuilder::new(server, &context.access_token);
+            if insecure {
+                builder.insecure();
+            }
+            Some(builder.build_async().await?)
+        };
+        let cache = if options.use_cache || options.offline {
+            Cache::for_context(context)
+        } else {
+            None
+        };
+        Ok(Client {
+            gitlab,
+            api,
+            cache,
+            retry: options.retry,
+            timeout: options.timeout,
+            page_size: options.page_size,
+            project_filter: options.projects,
+            paused_until: Mutex::new(None),
+            data_as_of: Mutex::new(None),
+        })
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.gitlab.is_none()
+    }
+
+    /// When the oldest response that was answered from the cache offline was fetched.
+    pub fn data_as_of(&self) -> Option<DateTime<Utc>> {
+        *self.data_as_of.lock().unwrap()
+    }
+
+    fn offline_response(
+        &self,
+        method: &Method,
+        url: &str,
+    ) -> Result<Response<Bytes>, ApiError<RestError>> {
+        let entry = self
+            .cache
+            .as_ref()
+            .filter(|_| *method == Method::GET)
+            .and_then(|cache| cache.get(url))
+            .ok_or_else(|| ApiError::Gitlab {
+                msg: format!(
+                    "{} {} is not in the cache to answer it offline",
+                    method, url
+                ),
+            })?;
+        let mut data_as_of = self.data_as_of.lock().unwrap();
+        *data_as_of = Some(data_as_of.map_or(entry.fetched_at, |at| at.min(entry.fetched_at)));
+        Ok(entry.into_response())
+    }
+
+    pub fn cache(&self) -> Option<&Cache> {
+        self.cache.as_ref()
+    }
+
+    pub fn page_size(&self) -> Option<usize> {
+        self.page_size
+    }
+
+    pub fn project_filter(&self) -> &ProjectFilter {
+        &self.project_filter
+    }
+
+    fn pause_for(&self, wait: Duration) {
+        let until = Instant::now() + wait;
+        let mut paused_until = self.paused_until.lock().unwrap();
+        *paused_until = Some(paused_until.map_or(until, |current| current.max(until)));
+    }
+
+    async fn wait_if_paused(&self) {
+        let paused_until = *self.paused_until.lock().unwrap();
+        if let Some(until) = paused_until {
+            let now = Instant::now();
+            if until > now {
+                tokio::time::sleep(until - now).await;
+            }
+        }
+    }
+}
+
+impl RestClient for Client {
+    type Error = RestError;
+
+    fn rest_endpoint(&self, endpoint: &str) -> Result<Url, ApiError<Self::Error>> {
+        Ok(self.api.join(endpoint)?)
+    }
+}
+
+#[async_trait]
+impl AsyncClient for Client {
+    async fn rest_async(
+        &self,
+        request: request::Builder,
+        body: Vec<u8>,
+    ) -> Result<Response<Bytes>, ApiError<Self::Error>> {
+        // The builder is consumed by sending it, so keep its parts to send it again on retries
+        let method = request.method_ref().cloned().unwrap_or(Method::GET);
+        let uri = request.uri_ref().cloned().unwrap_or_default();
+        let mut headers = request.headers_ref().cloned().unwrap_or_default();
+
+        let url = uri.to_string();
+        let gitlab = match &self.gitlab {
+            Some(gitlab) => gitlab,
+            None => return self.offline_response(&method, &url),
+        };
+        let cache = self.cache.as_ref().filter(|_| method == Method::GET);
+        let cached = cache.and_then(|cache| cache.get(&url));
+        if let Some(etag) = cached
+            .as_ref()
+            .and_then(|entry| HeaderValue::from_str(&entry.etag).ok())
+        {
+            headers.insert(IF_NONE_MATCH, etag);
+        }
+
+        let mut attempt = 0;
+        let mut throttled = 0;
+        let rsp = loop {
+            self.wait_if_paused().await;
+            let mut request = request::Builder::new()
+                .method(method.clone())
+                .uri(uri.clone());
+            if let Some(request_headers) = request.headers_mut() {
+                request_headers.extend(headers.clone());
+            }
+            let start = Instant::now();
+            let send = gitlab.rest_async(request, body.clone());
+            let result = match self.timeout {
+                Some(timeout) => tokio::time::timeout(timeout, send)
+                    .await
+                    .unwrap_or_else(|_| {
+                        Err(ApiError::Gitlab {
+                            msg: format!("request to {} timed out after {:?}", uri.path(), timeout),
+                        })
+                    }),
+                None => send.await,
+            };
+            match &result {
+                Ok(rsp) => debug!(
+                    "{} {} {} [{:.2?}]",
+                    method,
+                    uri.path(),
+                    rsp.status(),
+                    start.elapsed()
+                ),
+                Err(e) => debug!(
+                    "{} {} failed: {} [{:.2?}]",
+                    method,
+                    uri.path(),
+                    e,
+                    start.elapsed()
+                ),
+            }
+            attempt += 1;
+            if attempt < self.retry.attempts && is_transient(&method, &result) {
+                let wait = backoff(&self.retry, attempt - 1, rand::thread_rng().gen());
+                warn!("request to {} failed, retrying in {:.1?}", uri.path(), wait);
+                tokio::time::sleep(wait).await;
+                continue;
+            }
+            let rsp = result?;
+            let now = Utc::now().timestamp();
+            if rsp.status() == StatusCode::TOO_MANY_REQUESTS && throttled < MAX_RATE_LIMIT_RETRIES {
+                let wait = rate_limit_wait(rsp.headers(), now);
+                warn!("GitLab is rate limiting requests, retrying in {:.0?}", wait);
+                self.pause_for(wait);
+                throttled += 1;
+                continue;
+            }
+            if header_number(rsp.headers(), "ratelimit-remaining") == Some(0) {
+                self.pause_for(rate_limit_wait(rsp.headers(), now));
+            }
+            break rsp;
+        };
+
+        if let Some(cache) = cache {
+            if rsp.status() == StatusCode::NOT_MODIFIED {
+                if let Some(entry) = cached {
+                    return Ok(entry.into_response());
+                }
+            } else if rsp.status().is_success() {
+                let etag = rsp.headers().get(ETAG).and_then(|etag| etag.to_str().ok());
+                if let Some(entry) = etag.and_then(|etag| Entry::from_response(etag, &rsp)) {
+                    cache.put(&url, &entry);
+                }
+            }
+        }
+        Ok(rsp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy {
+            attempts: 3,
+            backoff_ms: 100,
+            jitter: 0.5,
+        };
+        assert_eq!(backoff(&policy, 0, 0.0), Duration::from_millis(100));
+        assert_eq!(backoff(&policy, 2, 0.0), Duration::from_millis(400));
+        assert_eq!(backoff(&policy, 1, 0.5), Duration::from_millis(250));
+    }
+
+    #[test]
+    fn test_only_idempotent_requests_are_retried() {
+        let failed: Result<Response<Bytes>, ApiError<RestError>> = Ok(Response::builder()
+            .status(StatusCode::BAD_GATEWAY)
+            .body(Bytes::new())
+            .unwrap());
+        assert!(is_transient(&Method::GET, &failed));
+        assert!(!is_transient(&Method::POST, &failed));
+        let ok: Result<Response<Bytes>, ApiError<RestError>> = Ok(Response::new(Bytes::new()));
+        assert!(!is_transient(&Method::GET, &ok));
+    }
+
+    #[test]
+    fn test_rate_limit_from_headers() {
+        let mut headers = HeaderMap::new();
+        assert!(!RateLimit::from_headers(&headers).is_limited());
+        headers.insert("ratelimit-limit", HeaderValue::from_static("2000"));
+        headers.insert("ratelimit-remaining", HeaderValue::from_static("1987"));
+        headers.insert("ratelimit-reset", HeaderValue::from_static("1030"));
+        assert_eq!(
+            RateLimit::from_headers(&headers),
+            RateLimit {
+                limit: Some(2000),
+                observed: None,
+                remaining: Some(1987),
+                reset: Utc.timestamp_opt(1030, 0).single(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_rate_limit_wait() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(
+            rate_limit_wait(&headers, 0),
+            Duration::from_secs(DEFAULT_RATE_LIMIT_WAIT_SECS as u64)
+        );
+        headers.insert("ratelimit-reset", HeaderValue::from_static("1030"));
+        assert_eq!(rate_limit_wait(&headers, 1000), Duration::from_secs(30));
+        headers.insert("retry-after", HeaderValue::from_static("5"));
+        assert_eq!(rate_limit_wait(&headers, 1000), Duration::from_secs(5));
+        headers.insert("retry-after", HeaderValue::from_static("100000"));
+        assert_eq!(
+            rate_limit_wait(&headers, 1000),
+            Duration::from_secs(MAX_RATE_LIMIT_WAIT_SECS as u64)
+        );
+    }
+}
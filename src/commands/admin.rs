@@ -0,0 +1,227 @@
+use colored::*;
+use gitlab::api::{users, AsyncQuery};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tracing::warn;
+
+use gitlabctl::client::Client;
+use gitlabctl::endpoints::{BatchedBackgroundMigrations, SidekiqJobStats, SidekiqQueueMetrics};
+use gitlabctl::error::{ApiError, Error};
+use gitlabctl::types::CurrentUser;
+
+use crate::cli::{AdminCommand, GlobalArgs, OutputFormat, SidekiqArgs};
+use crate::commands;
+
+#[derive(Clone, Deserialize, Serialize)]
+struct Queue {
+    /// The jobs waiting in the queue.
+    backlog: u64,
+    /// How many seconds the oldest job has been waiting.
+    latency: f64,
+}
+
+#[derive(Deserialize)]
+struct QueueMetrics {
+    queues: BTreeMap<String, Queue>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Jobs {
+    processed: u64,
+    failed: u64,
+    enqueued: u64,
+    dead: u64,
+}
+
+#[derive(Deserialize)]
+struct JobStats {
+    jobs: Jobs,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Migration {
+    id: u64,
+    job_class_name: String,
+    table_name: String,
+    /// E.g. `active`, `paused`, `failed` or `finished`.
+    status: String,
+    /// In percent, missing while GitLab cannot estimate it.
+    progress: Option<f64>,
+}
+
+impl Migration {
+    fn is_done(&self) -> bool {
+        matches!(self.status.as_str(), "finished" | "finalized")
+    }
+}
+
+#[derive(Serialize)]
+struct Sidekiq {
+    jobs: Jobs,
+    queues: Vec<(String, Queue)>,
+    /// Missing before GitLab 15.8.
+    migrations: Option<Vec<Migration>>,
+}
+
+/// The queues with a backlog, or all with `all`, with the largest backlog first.
+fn queues(queues: BTreeMap<String, Queue>, all: bool) -> Vec<(String, Queue)> {
+    let mut queues: Vec<(String, Queue)> = queues
+        .into_iter()
+        .filter(|(_, queue)| all || queue.backlog > 0)
+        .collect();
+    queues.sort_by(|a, b| b.1.backlog.cmp(&a.1.backlog).then_with(|| a.0.cmp(&b.0)));
+    queues
+}
+
+/// Fails with a hint if the token cannot use the admin APIs.
+async fn require_admin(gitlab: &Client) -> Result<(), Error> {
+    let user: CurrentUser = users::CurrentUser::builder()
+        .build()
+        .map_err(ApiError::builder)?
+        .query_async(gitlab)
+        .await
+        .map_err(ApiError::from)?;
+    if !user.is_admin {
+        return Err(Error::Usage(format!(
+            "{} is no administrator, the admin commands need the token of one",
+            user.username
+        )));
+    }
+    Ok(())
+}
+
+fn status(status: &str) -> ColoredString {
+    let padded = format!("{:10}", status);
+    match status {
+        "active" | "finalizing" => padded.blue(),
+        "paused" => padded.yellow(),
+        "failed" => padded.red(),
+        _ => padded.green(),
+    }
+}
+
+fn print(sidekiq: &Sidekiq) {
+    let jobs = &sidekiq.jobs;
+    println!(
+        "JOBS  processed {}  failed {}  enqueued {}  dead {}",
+        jobs.processed, jobs.failed, jobs.enqueued, jobs.dead
+    );
+    println!();
+    if sidekiq.queues.is_empty() {
+        println!("No queue has a backlog");
+    } else {
+        let width = sidekiq
+            .queues
+            .iter()
+            .map(|(name, _)| name.len())
+            .max()
+            .unwrap_or(0)
+            .max(5);
+        println!(
+            "{:width$}  {:>7}  LATENCY",
+            "QUEUE",
+            "BACKLOG",
+            width = width
+        );
+        for (name, queue) in &sidekiq.queues {
+            println!(
+                "{:width$}  {:>7}  {:.0}s",
+                name,
+                queue.backlog,
+                queue.latency,
+                width = width
+            );
+        }
+    }
+    println!();
+    match &sidekiq.migrations {
+        None => println!("The background migrations are only listed since GitLab 15.8"),
+        Some(migrations) if migrations.is_empty() => {
+            println!("All background migrations are finished")
+        }
+        Some(migrations) => {
+            println!("BACKGROUND MIGRATIONS");
+            for migration in migrations {
+                let progress = migration
+                    .progress
+                    .map_or("?".to_owned(), |p| format!("{:.0}%", p));
+                println!(
+                    "  {:>5}  {}  {:>4}  {} on {}",
+                    migration.id,
+                    status(&migration.status),
+                    progress,
+                    migration.job_class_name,
+                    migration.table_name
+                );
+            }
+        }
+    }
+}
+
+async fn sidekiq(global: &GlobalArgs, args: &SidekiqArgs) -> Result<(), Error> {
+    let (gitlab, _) = commands::connect(global, None).await?;
+    require_admin(&gitlab).await?;
+    let metrics: QueueMetrics = SidekiqQueueMetrics
+        .query_async(&gitlab)
+        .await
+        .map_err(ApiError::from)?;
+    let stats: JobStats = SidekiqJobStats
+        .query_async(&gitlab)
+        .await
+        .map_err(ApiError::from)?;
+    let migrations: Option<Vec<Migration>> = BatchedBackgroundMigrations
+        .query_async(&gitlab)
+        .await
+        .map_err(|e| warn!("could not list the background migrations: {}", e))
+        .ok();
+    let sidekiq = Sidekiq {
+        jobs: stats.jobs,
+        queues: queues(metrics.queues, args.all),
+        migrations: migrations.map(|migrations| {
+            migrations
+                .into_iter()
+                .filter(|migration| args.all || !migration.is_done())
+                .collect()
+        }),
+    };
+    match args.output {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&sidekiq).unwrap()),
+        OutputFormat::Table => print(&sidekiq),
+    }
+    Ok(())
+}
+
+pub async fn run(global: &GlobalArgs, command: &AdminCommand) -> Result<(), Error> {
+    match command {
+        AdminCommand::Sidekiq(args) => sidekiq(global, args).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queues() {
+        let queue = |backlog| Queue {
+            backlog,
+            latency: 0.0,
+        };
+        let all: BTreeMap<String, Queue> = vec![
+            ("default".to_string(), queue(3)),
+            ("mailers".to_string(), queue(0)),
+            ("pipeline".to_string(), queue(12)),
+            ("cronjob".to_string(), queue(3)),
+        ]
+        .into_iter()
+        .collect();
+        let names = |queues: Vec<(String, Queue)>| -> Vec<String> {
+            queues.into_iter().map(|(name, _)| name).collect()
+        };
+        assert_eq!(
+            names(queues(all.clone(), false)),
+            vec!["pipeline", "cronjob", "default"]
+        );
+        assert_eq!(queues(all, true).len(), 4);
+    }
+}
@@ -0,0 +1,119 @@
+use gitlab::api::{self, AsyncQuery};
+use serde::Deserialize;
+
+use gitlabctl::client::Client;
+use gitlabctl::endpoints::{CreateCommitComment, CreateMergeRequestNote, DeploymentMergeRequests};
+use gitlabctl::environments::{deployment_history, get_project};
+use gitlabctl::error::{ApiError, Error};
+use gitlabctl::pagination;
+use gitlabctl::types::ProjectId;
+
+use crate::cli::{AnnotateDeploymentArgs, GlobalArgs};
+use crate::commands;
+
+#[derive(Deserialize)]
+struct MergeRequest {
+    iid: u64,
+}
+
+/// The ways to split `target` into the path of a project and the name of an environment,
+/// the longest path first, as both can have slashes like `group/app/review/fix`.
+fn splits(target: &str) -> Vec<(&str, &str)> {
+    target
+        .match_indices('/')
+        .map(|(i, _)| (&target[..i], &target[i + 1..]))
+        .filter(|(project, environment)| project.contains('/') && !environment.is_empty())
+        .rev()
+        .collect()
+}
+
+/// The path and ID of the project of `target` and the name of its environment.
+async fn resolve<'a>(
+    gitlab: &Client,
+    target: &'a str,
+) -> Result<(&'a str, ProjectId, &'a str), Error> {
+    let mut last_error = None;
+    for (path, environment) in splits(target) {
+        match get_project(gitlab, path).await {
+            Ok((_, id)) => return Ok((path, id, environment)),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    match last_error {
+        Some(e) => Err(e.into()),
+        None => Err(Error::Usage(format!(
+            "Expected a project and an environment like group/app/production, not {}",
+            target
+        ))),
+    }
+}
+
+pub async fn deployment(global: &GlobalArgs, args: &AnnotateDeploymentArgs) -> Result<(), Error> {
+    let (gitlab, _) = commands::connect(global, None).await?;
+    let (path, project, environment) = resolve(&gitlab, &args.target).await?;
+    let deployment = deployment_history(&gitlab, project, environment, 1)
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| Error::Usage(format!("{} was never deployed to {}", path, environment)))?;
+    let note = format!(
+        "Deployment #{} to {}: {}",
+        deployment.iid, environment, args.message
+    );
+    let endpoint = CreateCommitComment {
+        project,
+        sha: deployment.sha.clone(),
+        note: note.clone(),
+    };
+    api::ignore(endpoint)
+        .query_async(&gitlab)
+        .await
+        .map_err(ApiError::from)?;
+    println!(
+        "Annotated deployment #{} of {} to {} on commit {}",
+        deployment.iid,
+        path,
+        environment,
+        &deployment.sha[..deployment.sha.len().min(8)]
+    );
+    if args.merge_requests {
+        let endpoint = DeploymentMergeRequests {
+            project,
+            deployment: deployment.id,
+        };
+        let merge_requests: Vec<MergeRequest> =
+            pagination::list(&gitlab, &endpoint, None, |_| ()).await?;
+        for merge_request in merge_requests {
+            let endpoint = CreateMergeRequestNote {
+                project,
+                merge_request: merge_request.iid,
+                body: note.clone(),
+            };
+            api::ignore(endpoint)
+                .query_async(&gitlab)
+                .await
+                .map_err(ApiError::from)?;
+            println!("Annotated !{}", merge_request.iid);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_splits() {
+        assert_eq!(
+            splits("group/app/production"),
+            vec![("group/app", "production")]
+        );
+        assert_eq!(
+            splits("group/app/review/fix"),
+            vec![("group/app/review", "fix"), ("group/app", "review/fix")]
+        );
+        assert!(splits("app/production").is_empty());
+        assert!(splits("group/app/").is_empty());
+    }
+}
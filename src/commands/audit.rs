@@ -0,0 +1,304 @@
+use chrono::Utc;
+use colored::*;
+use serde::Serialize;
+use serde_json::Value;
+use tracing::debug;
+
+use gitlabctl::audit::{self, Credential, SettingDrift};
+use gitlabctl::endpoints::GroupProtectedEnvironments;
+use gitlabctl::error::Error;
+use gitlabctl::pagination;
+use gitlabctl::types::ProtectedEnvironment;
+
+use crate::cli::{
+    AuditCommand, AuditCredentialsArgs, AuditSettingsArgs, GlobalArgs, InactiveProjectsArgs,
+    OutputFormat, UnprotectedEnvironmentsArgs,
+};
+use crate::commands::{self, stats};
+
+#[derive(Serialize)]
+struct ProjectDrift {
+    project: String,
+    drift: Vec<SettingDrift>,
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => "-".to_owned(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+async fn settings(global: &GlobalArgs, args: &AuditSettingsArgs) -> Result<(), Error> {
+    let baseline = audit::load_baseline(&args.baseline)?;
+    let (gitlab, context) = commands::connect(global, None).await?;
+    let namespace = args
+        .namespace
+        .clone()
+        .or(context.namespace)
+        .unwrap_or_default();
+    let (settings, failures) =
+        commands::for_each_project(&gitlab, &namespace, args.concurrency, |id| {
+            audit::project_settings(&gitlab, id)
+        })
+        .await?;
+    let total = settings.len();
+    let drifted: Vec<ProjectDrift> = settings
+        .into_iter()
+        .map(|(project, settings)| ProjectDrift {
+            project,
+            drift: audit::settings_drift(&baseline, &settings),
+        })
+        .filter(|p| !p.drift.is_empty())
+        .collect();
+
+    if args.output == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&drifted).unwrap());
+        return failures.finish();
+    }
+    let width = |f: &dyn Fn(&ProjectDrift, &SettingDrift) -> usize, header: &str| {
+        drifted
+            .iter()
+            .flat_map(|p| p.drift.iter().map(move |d| f(p, d)))
+            .chain([header.len()])
+            .max()
+            .unwrap_or_default()
+    };
+    let project_width = width(&|p, _| p.project.len(), "PROJECT");
+    let setting_width = width(&|_, d| d.setting.len(), "SETTING");
+    let expected_width = width(&|_, d| cell(&d.expected).len(), "EXPECTED");
+    if !drifted.is_empty() {
+        println!(
+            "{:project_width$}  {:setting_width$}  {:expected_width$}  ACTUAL",
+            "PROJECT", "SETTING", "EXPECTED"
+        );
+    }
+    for project in &drifted {
+        for drift in &project.drift {
+            println!(
+                "{:project_width$}  {:setting_width$}  {}  {}",
+                project.project,
+                drift.setting,
+                format!("{:expected_width$}", cell(&drift.expected)).green(),
+                cell(&drift.actual).red()
+            );
+        }
+    }
+    println!(
+        "{} of {} projects differ from the baseline",
+        drifted.len(),
+        total
+    );
+    failures.finish()
+}
+
+#[derive(Serialize)]
+struct ProjectCredential {
+    project: String,
+    #[serde(flatten)]
+    credential: Credential,
+    expiring: bool,
+}
+
+async fn credentials(global: &GlobalArgs, args: &AuditCredentialsArgs) -> Result<(), Error> {
+    let (gitlab, context) = commands::connect(global, None).await?;
+    let namespace = args
+        .namespace
+        .clone()
+        .or(context.namespace)
+        .unwrap_or_default();
+    let window = chrono::Duration::from_std(args.expiring_within).map_err(|_| {
+        Error::Usage(format!(
+            "--expiring-within {:?} is too long",
+            args.expiring_within
+        ))
+    })?;
+    let until = (Utc::now() + window).date_naive();
+    let (credentials, failures) =
+        commands::for_each_project(&gitlab, &namespace, args.concurrency, |id| {
+            audit::project_credentials(&gitlab, id)
+        })
+        .await?;
+    let mut listed: Vec<ProjectCredential> = credentials
+        .into_iter()
+        .flat_map(|(project, credentials)| {
+            credentials
+                .into_iter()
+                .map(move |credential| ProjectCredential {
+                    project: project.clone(),
+                    expiring: credential.expires_before(until),
+                    credential,
+                })
+        })
+        .filter(|c| c.expiring || !args.only_expiring)
+        .collect();
+    // The ones that expire first come first, the ones that never expire last
+    listed.sort_by_key(|c| (c.credential.expires_at.is_none(), c.credential.expires_at));
+
+    if args.output == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&listed).unwrap());
+        return failures.finish();
+    }
+    let project_width = listed
+        .iter()
+        .map(|c| c.project.len())
+        .chain(["PROJECT".len()])
+        .max()
+        .unwrap_or_default();
+    let name_width = listed
+        .iter()
+        .map(|c| c.credential.name.len())
+        .chain(["NAME".len()])
+        .max()
+        .unwrap_or_default();
+    println!(
+        "{:project_width$}  {:13}  {:name_width$}  EXPIRES",
+        "PROJECT", "KIND", "NAME"
+    );
+    for c in &listed {
+        let expires = match c.credential.expires_at {
+            Some(date) => date.to_string(),
+            None => "never".to_owned(),
+        };
+        let expires = if c.expiring {
+            expires.red()
+        } else {
+            expires.normal()
+        };
+        println!(
+            "{:project_width$}  {:13}  {:name_width$}  {}",
+            c.project, c.credential.kind, c.credential.name, expires
+        );
+    }
+    let expiring = listed.iter().filter(|c| c.expiring).count();
+    println!(
+        "{} credentials expire within {}",
+        expiring,
+        humantime::format_duration(args.expiring_within)
+    );
+    failures.finish()
+}
+
+async fn inactive_projects(global: &GlobalArgs, args: &InactiveProjectsArgs) -> Result<(), Error> {
+    let (gitlab, context) = commands::connect(global, None).await?;
+    let namespace = args
+        .namespace
+        .clone()
+        .or(context.namespace)
+        .unwrap_or_default();
+    let since = stats::window_start(args.no_commits_since)?;
+    let (projects, failures) =
+        commands::for_each_project(&gitlab, &namespace, args.concurrency, |id| {
+            audit::is_inactive(&gitlab, id, since)
+        })
+        .await?;
+    let total = projects.len();
+    let inactive: Vec<String> = projects
+        .into_iter()
+        .filter(|(_, inactive)| *inactive)
+        .map(|(project, _)| project)
+        .collect();
+
+    if args.output == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&inactive).unwrap());
+        return failures.finish();
+    }
+    for project in &inactive {
+        println!("{}", project);
+    }
+    println!(
+        "{} of {} projects had no commits, pipelines or issues for {}",
+        inactive.len(),
+        total,
+        humantime::format_duration(args.no_commits_since)
+    );
+    failures.finish()
+}
+
+#[derive(Serialize)]
+struct Unprotected {
+    project: String,
+    environment: String,
+}
+
+async fn unprotected_environments(
+    global: &GlobalArgs,
+    args: &UnprotectedEnvironmentsArgs,
+) -> Result<(), Error> {
+    let (gitlab, context) = commands::connect(global, None).await?;
+    let namespace = args
+        .namespace
+        .clone()
+        .or(context.namespace)
+        .unwrap_or_default();
+    // A group can protect a tier in all of its projects at once
+    if !namespace.is_empty() {
+        let endpoint = GroupProtectedEnvironments {
+            group: namespace.clone(),
+        };
+        match pagination::list::<_, ProtectedEnvironment>(&gitlab, &endpoint, None, |_| ()).await {
+            Ok(protected) if protected.iter().any(|p| p.name == args.tier) => {
+                println!(
+                    "{} protects the {} tier in all of its projects",
+                    namespace, args.tier
+                );
+                return Ok(());
+            }
+            Ok(_) => {}
+            Err(e) => debug!("could not list the protected tiers of {}: {}", namespace, e),
+        }
+    }
+    let (projects, failures) =
+        commands::for_each_project(&gitlab, &namespace, args.concurrency, |id| {
+            audit::unprotected_environments(&gitlab, id, &args.tier)
+        })
+        .await?;
+    let total = projects.len();
+    let unprotected: Vec<Unprotected> = projects
+        .iter()
+        .flat_map(|(project, environments)| {
+            environments.iter().map(move |environment| Unprotected {
+                project: project.clone(),
+                environment: environment.clone(),
+            })
+        })
+        .collect();
+
+    if args.output == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&unprotected).unwrap());
+        return failures.finish();
+    }
+    let width = unprotected
+        .iter()
+        .map(|u| u.project.len())
+        .chain(["PROJECT".len()])
+        .max()
+        .unwrap_or_default();
+    if !unprotected.is_empty() {
+        println!("{:width$}  ENVIRONMENT", "PROJECT", width = width);
+    }
+    for u in &unprotected {
+        println!(
+            "{:width$}  {}",
+            u.project,
+            u.environment.red(),
+            width = width
+        );
+    }
+    let affected = projects.iter().filter(|(_, envs)| !envs.is_empty()).count();
+    println!(
+        "{} of {} projects have {} environments that are not protected",
+        affected, total, args.tier
+    );
+    failures.finish()
+}
+
+pub async fn run(global: &GlobalArgs, command: &AuditCommand) -> Result<(), Error> {
+    match command {
+        AuditCommand::Settings(args) => settings(global, args).await,
+        AuditCommand::Credentials(args) => credentials(global, args).await,
+        AuditCommand::InactiveProjects(args) => inactive_projects(global, args).await,
+        AuditCommand::UnprotectedEnvironments(args) => unprotected_environments(global, args).await,
+    }
+}
@@ -0,0 +1,404 @@
+use gitlab::api::projects::variables;
+use gitlab::api::{self, AsyncQuery};
+use serde::Deserialize;
+use std::future::Future;
+
+use gitlabctl::client::Client;
+use gitlabctl::endpoints::{
+    AddHook, DeleteHook, EditHook, ProjectHooks, ProjectVariables, ProtectBranch,
+    ProtectedBranches, UnprotectBranch,
+};
+use gitlabctl::environments::get_projects_for_namespace;
+use gitlabctl::error::{ApiError, Error};
+use gitlabctl::pagination;
+use gitlabctl::timings::Timings;
+use gitlabctl::types::ProjectId;
+
+use crate::cli::{
+    AccessLevel, AddHookArgs, BulkCommand, BulkProjects, GlobalArgs, ProtectBranchArgs,
+    RemoveHookArgs, SetVariableArgs, UpdateHookArgs,
+};
+use crate::commands;
+
+/// The projects of the group that `projects` selects.
+async fn selected_projects(
+    gitlab: &Client,
+    namespace: &str,
+    projects: &BulkProjects,
+) -> Result<Vec<(String, ProjectId)>, Error> {
+    let timings = Timings::new(false);
+    let all = get_projects_for_namespace(gitlab, namespace, None, &timings, |_| ()).await?;
+    Ok(all
+        .into_iter()
+        .filter(|(name, _)| {
+            projects.only_projects.is_empty()
+                || projects
+                    .only_projects
+                    .iter()
+                    .any(|filter| filter.matches(name))
+        })
+        .collect())
+}
+
+/// Runs `change` for the selected projects and prints how it went for each of them. Asks
+/// first whether to `confirm` in that many projects, for changes that are hard to undo.
+async fn apply<F, Fut>(
+    global: &GlobalArgs,
+    gitlab: &Client,
+    context_namespace: Option<String>,
+    confirm: Option<&str>,
+    projects: &BulkProjects,
+    change: F,
+) -> Result<(), Error>
+where
+    F: Fn(ProjectId) -> Fut,
+    Fut: Future<Output = Result<String, ApiError>>,
+{
+    let namespace = projects
+        .namespace
+        .clone()
+        .or(context_namespace)
+        .unwrap_or_default();
+    let selected = selected_projects(gitlab, &namespace, projects).await?;
+    let total = selected.len();
+    if let Some(change) = confirm {
+        let question = format!("{} in {} projects of {}?", change, total, namespace);
+        if !commands::confirm(global, &question)? {
+            println!("Nothing was changed");
+            return Ok(());
+        }
+    }
+    let results = commands::run_for_projects(selected, projects.concurrency, change).await;
+    let mut failed = 0;
+    for (name, result) in &results {
+        match result {
+            Ok(outcome) => println!("{}  {}", name, outcome),
+            Err(e) => {
+                failed += 1;
+                println!("{}  failed: {}", name, e);
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(Error::Partial { failed, total });
+    }
+    println!("Done for {} projects", total);
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct Variable {
+    key: String,
+}
+
+/// Updates the variable of `project`, or creates it if it does not exist yet.
+async fn set_variable(
+    gitlab: &Client,
+    project: ProjectId,
+    args: &SetVariableArgs,
+) -> Result<String, ApiError> {
+    let (key, value) = &args.variable;
+    let existing: Vec<Variable> =
+        pagination::list(gitlab, &ProjectVariables { project }, None, |_| ()).await?;
+    if existing.iter().any(|variable| variable.key == *key) {
+        let update = variables::UpdateProjectVariable::builder()
+            .project(project)
+            .key(key.as_str())
+            .value(value.as_str())
+            .protected(args.protected)
+            .masked(args.masked)
+            .build()
+            .map_err(ApiError::builder)?;
+        api::ignore(update).query_async(gitlab).await?;
+        return Ok("updated".to_owned());
+    }
+    let create = variables::CreateProjectVariable::builder()
+        .project(project)
+        .key(key.as_str())
+        .value(value.as_str())
+        .protected(args.protected)
+        .masked(args.masked)
+        .build()
+        .map_err(ApiError::builder)?;
+    api::ignore(create).query_async(gitlab).await?;
+    Ok("created".to_owned())
+}
+
+#[derive(Deserialize)]
+struct AccessLevelOf {
+    access_level: u64,
+}
+
+#[derive(Deserialize)]
+struct ProtectedBranch {
+    name: String,
+    push_access_levels: Vec<AccessLevelOf>,
+    merge_access_levels: Vec<AccessLevelOf>,
+}
+
+fn describe_levels(levels: &[AccessLevelOf]) -> String {
+    let names: Vec<String> = levels
+        .iter()
+        .map(|l| match AccessLevel::from_level(l.access_level) {
+            Some(access) => format!("{:?}", access).to_lowercase(),
+            None => l.access_level.to_string(),
+        })
+        .collect();
+    if names.is_empty() {
+        "nobody".to_owned()
+    } else {
+        names.join("+")
+    }
+}
+
+/// How `branch` differs from the wanted protection, or `None` if it does not.
+fn protection_drift(branch: Option<&ProtectedBranch>, args: &ProtectBranchArgs) -> Option<String> {
+    let Some(branch) = branch else {
+        return Some("not protected".to_owned());
+    };
+    let is = |levels: &[AccessLevelOf], wanted: AccessLevel| {
+        levels.len() == 1 && levels[0].access_level == wanted.level()
+    };
+    let mut drift = vec![];
+    if !is(&branch.merge_access_levels, args.merge) {
+        drift.push(format!(
+            "merge by {}",
+            describe_levels(&branch.merge_access_levels)
+        ));
+    }
+    if !is(&branch.push_access_levels, args.push) {
+        drift.push(format!(
+            "push by {}",
+            describe_levels(&branch.push_access_levels)
+        ));
+    }
+    Some(drift.join(", ")).filter(|drift| !drift.is_empty())
+}
+
+/// Protects the branch as asked, replacing a protection that differs as GitLab can only
+/// update it by the IDs of its access levels.
+async fn protect_branch(
+    gitlab: &Client,
+    project: ProjectId,
+    args: &ProtectBranchArgs,
+) -> Result<String, ApiError> {
+    let protected: Vec<ProtectedBranch> =
+        pagination::list(gitlab, &ProtectedBranches { project }, None, |_| ()).await?;
+    let existing = protected.iter().find(|branch| branch.name == args.branch);
+    let Some(drift) = protection_drift(existing, args) else {
+        return Ok("in sync".to_owned());
+    };
+    if args.dry_run {
+        return Ok(format!("would change, {}", drift));
+    }
+    if existing.is_some() {
+        let unprotect = UnprotectBranch {
+            project,
+            name: args.branch.clone(),
+        };
+        api::ignore(unprotect).query_async(gitlab).await?;
+    }
+    let protect = ProtectBranch {
+        project,
+        name: args.branch.clone(),
+        push_access_level: args.push.level(),
+        merge_access_level: args.merge.level(),
+    };
+    api::ignore(protect).query_async(gitlab).await?;
+    Ok(format!("changed, was {}", drift))
+}
+
+#[derive(Deserialize)]
+struct Hook {
+    id: u64,
+    url: String,
+}
+
+/// The webhooks of `project` that call `url`.
+async fn hooks_with_url(
+    gitlab: &Client,
+    project: ProjectId,
+    url: &str,
+) -> Result<Vec<Hook>, ApiError> {
+    let hooks: Vec<Hook> =
+        pagination::list(gitlab, &ProjectHooks { project }, None, |_| ()).await?;
+    Ok(hooks.into_iter().filter(|hook| hook.url == url).collect())
+}
+
+/// What a bulk change did to a project, or would do with `--dry-run`.
+fn outcome(dry_run: bool, done: &str, would: &str) -> String {
+    if dry_run { would } else { done }.to_owned()
+}
+
+async fn add_hook(
+    gitlab: &Client,
+    project: ProjectId,
+    args: &AddHookArgs,
+) -> Result<String, ApiError> {
+    if !hooks_with_url(gitlab, project, &args.url).await?.is_empty() {
+        return Ok("already has the webhook".to_owned());
+    }
+    if !args.dry_run {
+        let add = AddHook {
+            project,
+            url: args.url.clone(),
+            token: args.token.clone(),
+            events: args.events.clone(),
+        };
+        api::ignore(add).query_async(gitlab).await?;
+    }
+    Ok(outcome(args.dry_run, "added", "would add"))
+}
+
+async fn update_hook(
+    gitlab: &Client,
+    project: ProjectId,
+    args: &UpdateHookArgs,
+) -> Result<String, ApiError> {
+    let hooks = hooks_with_url(gitlab, project, &args.old_url).await?;
+    if hooks.is_empty() {
+        return Ok("has no such webhook".to_owned());
+    }
+    if !args.dry_run {
+        for hook in &hooks {
+            let edit = EditHook {
+                project,
+                hook: hook.id,
+                url: args.new_url.clone(),
+            };
+            api::ignore(edit).query_async(gitlab).await?;
+        }
+    }
+    Ok(format!(
+        "{} {} to {}",
+        outcome(args.dry_run, "changed", "would change"),
+        args.old_url,
+        args.new_url
+    ))
+}
+
+async fn remove_hook(
+    gitlab: &Client,
+    project: ProjectId,
+    args: &RemoveHookArgs,
+) -> Result<String, ApiError> {
+    let hooks = hooks_with_url(gitlab, project, &args.url).await?;
+    if hooks.is_empty() {
+        return Ok("has no such webhook".to_owned());
+    }
+    if !args.dry_run {
+        for hook in &hooks {
+            let delete = DeleteHook {
+                project,
+                hook: hook.id,
+            };
+            api::ignore(delete).query_async(gitlab).await?;
+        }
+    }
+    Ok(outcome(args.dry_run, "removed", "would remove"))
+}
+
+pub async fn run(global: &GlobalArgs, command: &BulkCommand) -> Result<(), Error> {
+    let (gitlab, context) = commands::connect(global, None).await?;
+    match command {
+        BulkCommand::SetVariable(args) => {
+            apply(
+                global,
+                &gitlab,
+                context.namespace,
+                None,
+                &args.projects,
+                |project| set_variable(&gitlab, project, args),
+            )
+            .await
+        }
+        BulkCommand::ProtectBranch(args) => {
+            let change = format!("Protect {}", args.branch);
+            let confirm = Some(change.as_str()).filter(|_| !args.dry_run);
+            apply(
+                global,
+                &gitlab,
+                context.namespace,
+                confirm,
+                &args.projects,
+                |project| protect_branch(&gitlab, project, args),
+            )
+            .await
+        }
+        BulkCommand::AddHook(args) => {
+            apply(
+                global,
+                &gitlab,
+                context.namespace,
+                None,
+                &args.projects,
+                |project| add_hook(&gitlab, project, args),
+            )
+            .await
+        }
+        BulkCommand::UpdateHook(args) => {
+            apply(
+                global,
+                &gitlab,
+                context.namespace,
+                None,
+                &args.projects,
+                |project| update_hook(&gitlab, project, args),
+            )
+            .await
+        }
+        BulkCommand::RemoveHook(args) => {
+            let change = format!("Remove the webhook {}", args.url);
+            let confirm = Some(change.as_str()).filter(|_| !args.dry_run);
+            apply(
+                global,
+                &gitlab,
+                context.namespace,
+                confirm,
+                &args.projects,
+                |project| remove_hook(&gitlab, project, args),
+            )
+            .await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    use crate::cli::{Cli, Commands};
+
+    fn levels(levels: &[u64]) -> Vec<AccessLevelOf> {
+        levels
+            .iter()
+            .map(|&access_level| AccessLevelOf { access_level })
+            .collect()
+    }
+
+    #[test]
+    fn test_protection_drift() {
+        let cli = Cli::parse_from(["gitlabctl", "bulk", "protect-branch", "main"]);
+        let Commands::Bulk {
+            command: BulkCommand::ProtectBranch(args),
+        } = cli.command
+        else {
+            unreachable!()
+        };
+        let branch = |push: &[u64], merge: &[u64]| ProtectedBranch {
+            name: "main".to_string(),
+            push_access_levels: levels(push),
+            merge_access_levels: levels(merge),
+        };
+        assert_eq!(protection_drift(Some(&branch(&[0], &[40])), &args), None);
+        assert_eq!(
+            protection_drift(Some(&branch(&[30], &[30, 40])), &args),
+            Some("merge by developers+maintainers, push by developers".to_string())
+        );
+        assert_eq!(
+            protection_drift(None, &args),
+            Some("not protected".to_string())
+        );
+    }
+}
@@ -0,0 +1,14 @@
+use gitlabctl::cache::Cache;
+use gitlabctl::error::Error;
+
+use crate::cli::CacheCommand;
+
+pub fn run(command: &CacheCommand) -> Result<(), Error> {
+    match command {
+        CacheCommand::Clear => {
+            Cache::clear().map_err(Error::ClearCache)?;
+            println!("Cleared the cache");
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,557 @@
+use colored::*;
+use futures::future::{FutureExt, LocalBoxFuture};
+use gitlab::api::projects::{self, pipelines};
+use gitlab::api::AsyncQuery;
+use serde::Deserialize;
+use similar::{ChangeTag, TextDiff};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use gitlabctl::client::Client;
+use gitlabctl::endpoints::{CiLint, PipelineBridges, PipelineJobs};
+use gitlabctl::environments::get_project;
+use gitlabctl::error::{ApiError, Error};
+use gitlabctl::pagination;
+use gitlabctl::stats;
+use gitlabctl::table::status_color;
+use gitlabctl::types::{Project, ProjectId};
+
+use crate::cli::{CiCommand, CiDiffArgs, CiViewArgs, GlobalArgs, TracePipelineArgs};
+use crate::commands;
+
+/// Between the columns of two stages.
+const ARROW: &str = "  ──▶  ";
+
+#[derive(Deserialize)]
+struct Pipeline {
+    id: u64,
+    #[serde(rename = "ref")]
+    ref_: String,
+    status: String,
+    /// In seconds, missing until the pipeline finished.
+    duration: Option<u64>,
+    web_url: String,
+}
+
+#[derive(Clone, Deserialize)]
+struct Job {
+    id: u64,
+    name: String,
+    stage: String,
+    status: String,
+    /// In seconds, missing until the job started.
+    duration: Option<f64>,
+    #[serde(default)]
+    allow_failure: bool,
+}
+
+fn symbol(job: &Job) -> &'static str {
+    match job.status.as_str() {
+        "failed" if job.allow_failure => "!",
+        status => status_symbol(status),
+    }
+}
+
+fn status_symbol(status: &str) -> &'static str {
+    match status {
+        "success" => "✓",
+        "failed" => "✗",
+        "running" => "●",
+        "manual" => "▶",
+        "canceled" | "skipped" => "⊘",
+        _ => "○",
+    }
+}
+
+fn color(job: &Job) -> Color {
+    match job.status.as_str() {
+        "failed" if job.allow_failure => Color::Yellow,
+        "manual" => Color::White,
+        status => status_color(status).unwrap_or(Color::White),
+    }
+}
+
+fn duration(seconds: f64) -> String {
+    humantime::format_duration(Duration::from_secs(seconds.round() as u64)).to_string()
+}
+
+fn cell(job: &Job) -> String {
+    match job.duration {
+        Some(seconds) => format!("{} {} {}", symbol(job), job.name, duration(seconds)),
+        None => format!("{} {}", symbol(job), job.name),
+    }
+}
+
+/// The last attempt of every job by stage, with the stages in the order they run.
+fn stages(jobs: Vec<Job>) -> Vec<(String, Vec<Job>)> {
+    // The jobs of a pipeline are created stage by stage, but retries get newer IDs
+    let mut first_ids: BTreeMap<String, u64> = BTreeMap::new();
+    for job in &jobs {
+        let first = first_ids.entry(job.stage.clone()).or_insert(job.id);
+        *first = (*first).min(job.id);
+    }
+    let mut latest: BTreeMap<String, Job> = BTreeMap::new();
+    for job in jobs {
+        match latest.get(&job.name) {
+            Some(attempt) if attempt.id > job.id => {}
+            _ => {
+                latest.insert(job.name.clone(), job);
+            }
+        }
+    }
+    let mut jobs: Vec<Job> = latest.into_values().collect();
+    jobs.sort_by(|a, b| (first_ids[&a.stage], &a.name).cmp(&(first_ids[&b.stage], &b.name)));
+    let mut stages: Vec<(String, Vec<Job>)> = vec![];
+    for job in jobs {
+        match stages.last_mut() {
+            Some((stage, jobs)) if *stage == job.stage => jobs.push(job),
+            _ => stages.push((job.stage.clone(), vec![job])),
+        }
+    }
+    stages
+}
+
+/// Prints the stages side by side, with their jobs below them.
+fn print(stages: &[(String, Vec<Job>)]) {
+    let widths: Vec<usize> = stages
+        .iter()
+        .map(|(stage, jobs)| {
+            jobs.iter()
+                .map(|job| cell(job).chars().count())
+                .chain([stage.chars().count()])
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let gap = " ".repeat(ARROW.chars().count());
+    let header: Vec<String> = stages
+        .iter()
+        .zip(&widths)
+        .map(|((stage, _), width)| {
+            format!("{:width$}", stage, width = width)
+                .bold()
+                .to_string()
+        })
+        .collect();
+    println!("{}", header.join(ARROW).trim_end());
+    let rows = stages.iter().map(|(_, jobs)| jobs.len()).max().unwrap_or(0);
+    for row in 0..rows {
+        let cells: Vec<String> = stages
+            .iter()
+            .zip(&widths)
+            .map(|((_, jobs), width)| match jobs.get(row) {
+                Some(job) => format!("{:width$}", cell(job), width = width)
+                    .color(color(job))
+                    .to_string(),
+                None => " ".repeat(*width),
+            })
+            .collect();
+        println!("{}", cells.join(&gap).trim_end());
+    }
+}
+
+/// The pipeline `id`, or else the newest one of `project`.
+async fn pipeline(gitlab: &Client, project: ProjectId, id: Option<u64>) -> Result<Pipeline, Error> {
+    let id = match id {
+        Some(id) => id,
+        None => {
+            let endpoint = pipelines::Pipelines::builder()
+                .project(project)
+                .build()
+                .map_err(ApiError::builder)?;
+            let newest: Vec<stats::Pipeline> =
+                pagination::list(gitlab, &endpoint, Some(1), |_| ()).await?;
+            newest
+                .first()
+                .ok_or_else(|| Error::Usage("The project has no pipelines".to_string()))?
+                .id
+        }
+    };
+    // Only the pipeline itself has its duration, the list leaves it out
+    let endpoint = pipelines::Pipeline::builder()
+        .project(project)
+        .pipeline(id)
+        .build()
+        .map_err(ApiError::builder)?;
+    Ok(endpoint.query_async(gitlab).await.map_err(ApiError::from)?)
+}
+
+async fn view(global: &GlobalArgs, args: &CiViewArgs) -> Result<(), Error> {
+    let (gitlab, context) = commands::connect(global, None).await?;
+    let project = commands::project_or_pick(&gitlab, args.project.as_deref(), &context).await?;
+    let (_, project_id) = get_project(&gitlab, &project).await?;
+    let pipeline = pipeline(&gitlab, project_id, args.pipeline).await?;
+    let endpoint = PipelineJobs {
+        project: project_id,
+        pipeline: pipeline.id,
+    };
+    let jobs: Vec<Job> = pagination::list(&gitlab, &endpoint, None, |_| ()).await?;
+    let status = pipeline
+        .status
+        .color(status_color(&pipeline.status).unwrap_or(Color::White));
+    match pipeline.duration {
+        Some(seconds) => println!(
+            "Pipeline {} on {}: {} after {}",
+            pipeline.id,
+            pipeline.ref_,
+            status,
+            duration(seconds as f64)
+        ),
+        None => println!("Pipeline {} on {}: {}", pipeline.id, pipeline.ref_, status),
+    }
+    println!("{}\n", pipeline.web_url.dimmed());
+    print(&stages(jobs));
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct Lint {
+    valid: bool,
+    #[serde(default)]
+    errors: Vec<String>,
+    merged_yaml: Option<String>,
+}
+
+/// The CI configuration of `content_ref` with its includes expanded.
+async fn merged_yaml(gitlab: &Client, project: &str, content_ref: &str) -> Result<String, Error> {
+    let endpoint = CiLint {
+        project: project.to_owned(),
+        content_ref: content_ref.to_owned(),
+    };
+    let lint: Lint = endpoint.query_async(gitlab).await.map_err(ApiError::from)?;
+    match lint.merged_yaml {
+        Some(yaml) if lint.valid => Ok(yaml),
+        _ => Err(Error::Usage(format!(
+            "The CI configuration of {} is invalid: {}",
+            content_ref,
+            lint.errors.join("; ")
+        ))),
+    }
+}
+
+/// The unified diff of `old` and `new`, line by line and without colors.
+fn unified_diff(from: &str, to: &str, old: &str, new: &str) -> Vec<String> {
+    let diff = TextDiff::from_lines(old, new);
+    let mut lines = vec![];
+    for hunk in diff.unified_diff().context_radius(3).iter_hunks() {
+        if lines.is_empty() {
+            lines.push(format!("--- {}", from));
+            lines.push(format!("+++ {}", to));
+        }
+        lines.push(hunk.header().to_string());
+        for change in hunk.iter_changes() {
+            let sign = match change.tag() {
+                ChangeTag::Delete => '-',
+                ChangeTag::Insert => '+',
+                ChangeTag::Equal => ' ',
+            };
+            lines.push(format!("{}{}", sign, change.value().trim_end_matches('\n')));
+        }
+    }
+    lines
+}
+
+async fn diff(global: &GlobalArgs, args: &CiDiffArgs) -> Result<(), Error> {
+    let (gitlab, context) = commands::connect(global, None).await?;
+    let project = commands::project_or_pick(&gitlab, args.project.as_deref(), &context).await?;
+    let (old, new) = futures::try_join!(
+        merged_yaml(&gitlab, &project, &args.from),
+        merged_yaml(&gitlab, &project, &args.to)
+    )?;
+    let lines = unified_diff(&args.from, &args.to, &old, &new);
+    if lines.is_empty() {
+        println!(
+            "The CI configuration of {} and {} is the same",
+            args.from, args.to
+        );
+    }
+    for line in lines {
+        let line = match line.chars().next() {
+            Some('@') => line.cyan(),
+            Some('-') if line.starts_with("--- ") => line.bold(),
+            Some('+') if line.starts_with("+++ ") => line.bold(),
+            Some('-') => line.red(),
+            Some('+') => line.green(),
+            _ => line.normal(),
+        };
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+/// How deep downstream pipelines are followed at most, as GitLab limits the nesting anyway.
+const MAX_DEPTH: usize = 10;
+
+#[derive(Deserialize)]
+struct DownstreamPipeline {
+    id: u64,
+    project_id: ProjectId,
+    #[serde(rename = "ref")]
+    ref_: String,
+    status: String,
+}
+
+#[derive(Deserialize)]
+struct Bridge {
+    name: String,
+    status: String,
+    /// Missing until the bridge job ran.
+    downstream_pipeline: Option<DownstreamPipeline>,
+}
+
+/// A pipeline with the pipelines that its bridge jobs triggered.
+struct Traced {
+    project: String,
+    id: u64,
+    ref_: String,
+    status: String,
+    bridges: Vec<TracedBridge>,
+}
+
+struct TracedBridge {
+    name: String,
+    status: String,
+    downstream: Option<Traced>,
+}
+
+async fn project_path(gitlab: &Client, project: ProjectId) -> Result<String, Error> {
+    let endpoint = projects::Project::builder()
+        .project(project)
+        .build()
+        .map_err(ApiError::builder)?;
+    let project: Project = endpoint.query_async(gitlab).await.map_err(ApiError::from)?;
+    Ok(project.path_with_namespace)
+}
+
+/// Resolves the bridge jobs of the pipeline into their downstream pipelines, and theirs in
+/// turn up to `depth` levels deep.
+fn trace(
+    gitlab: &Client,
+    project: ProjectId,
+    pipeline: u64,
+    depth: usize,
+) -> LocalBoxFuture<'_, Result<Vec<TracedBridge>, Error>> {
+    async move {
+        let endpoint = PipelineBridges { project, pipeline };
+        let bridges: Vec<Bridge> = pagination::list(gitlab, &endpoint, None, |_| ()).await?;
+        let mut traced = vec![];
+        for bridge in bridges {
+            let downstream = match bridge.downstream_pipeline {
+                Some(downstream) => Some(Traced {
+                    project: project_path(gitlab, downstream.project_id).await?,
+                    id: downstream.id,
+                    bridges: if depth > 1 {
+                        trace(gitlab, downstream.project_id, downstream.id, depth - 1).await?
+                    } else {
+                        vec![]
+                    },
+                    ref_: downstream.ref_,
+                    status: downstream.status,
+                }),
+                None => None,
+            };
+            traced.push(TracedBridge {
+                name: bridge.name,
+                status: bridge.status,
+                downstream,
+            });
+        }
+        Ok(traced)
+    }
+    .boxed_local()
+}
+
+fn describe(pipeline: &Traced) -> String {
+    format!(
+        "{} {} #{} on {}",
+        status_symbol(&pipeline.status),
+        pipeline.project,
+        pipeline.id,
+        pipeline.ref_
+    )
+}
+
+/// The lines of the tree below `bridges` without colors, with the status to color them by.
+fn tree_lines(bridges: &[TracedBridge], prefix: &str, lines: &mut Vec<(String, String)>) {
+    for (i, bridge) in bridges.iter().enumerate() {
+        let last = i + 1 == bridges.len();
+        let branch = if last { "└── " } else { "├── " };
+        match &bridge.downstream {
+            Some(downstream) => {
+                lines.push((
+                    format!(
+                        "{}{}{} → {}",
+                        prefix,
+                        branch,
+                        bridge.name,
+                        describe(downstream)
+                    ),
+                    downstream.status.clone(),
+                ));
+                let indent = if last { "    " } else { "│   " };
+                tree_lines(&downstream.bridges, &format!("{}{}", prefix, indent), lines);
+            }
+            None => lines.push((
+                format!(
+                    "{}{}{} {} ({}, nothing triggered yet)",
+                    prefix,
+                    branch,
+                    status_symbol(&bridge.status),
+                    bridge.name,
+                    bridge.status
+                ),
+                bridge.status.clone(),
+            )),
+        }
+    }
+}
+
+pub async fn trace_pipeline(global: &GlobalArgs, args: &TracePipelineArgs) -> Result<(), Error> {
+    let (gitlab, context) = commands::connect(global, None).await?;
+    let project = commands::project_or_pick(&gitlab, args.project.as_deref(), &context).await?;
+    let (_, project_id) = get_project(&gitlab, &project).await?;
+    let pipeline = pipeline(&gitlab, project_id, Some(args.pipeline)).await?;
+    let depth = if args.follow_downstream { MAX_DEPTH } else { 1 };
+    let root = Traced {
+        project,
+        id: pipeline.id,
+        bridges: trace(&gitlab, project_id, pipeline.id, depth).await?,
+        ref_: pipeline.ref_,
+        status: pipeline.status,
+    };
+    let mut lines = vec![(describe(&root), root.status.clone())];
+    tree_lines(&root.bridges, "", &mut lines);
+    for (line, status) in lines {
+        println!(
+            "{}",
+            line.color(status_color(&status).unwrap_or(Color::White))
+        );
+    }
+    Ok(())
+}
+
+pub async fn run(global: &GlobalArgs, command: &CiCommand) -> Result<(), Error> {
+    match command {
+        CiCommand::View(args) => view(global, args).await,
+        CiCommand::Diff(args) => diff(global, args).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(id: u64, name: &str, stage: &str, status: &str) -> Job {
+        Job {
+            id,
+            name: name.to_string(),
+            stage: stage.to_string(),
+            status: status.to_string(),
+            duration: None,
+            allow_failure: false,
+        }
+    }
+
+    #[test]
+    fn test_stages() {
+        let jobs = vec![
+            job(4, "deploy", "deploy", "manual"),
+            job(2, "unit", "test", "failed"),
+            job(1, "compile", "build", "success"),
+            job(3, "lint", "test", "success"),
+            job(5, "unit", "test", "success"),
+        ];
+        let stages = stages(jobs);
+        let names: Vec<(String, Vec<String>)> = stages
+            .iter()
+            .map(|(stage, jobs)| {
+                let jobs = jobs
+                    .iter()
+                    .map(|job| format!("{} {}", symbol(job), job.name))
+                    .collect();
+                (stage.clone(), jobs)
+            })
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("build".to_string(), vec!["✓ compile".to_string()]),
+                (
+                    "test".to_string(),
+                    vec!["✓ lint".to_string(), "✓ unit".to_string()]
+                ),
+                ("deploy".to_string(), vec!["▶ deploy".to_string()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cell() {
+        let mut job = job(1, "lint", "test", "failed");
+        job.duration = Some(62.4);
+        assert_eq!(cell(&job), "✗ lint 1m 2s");
+        job.allow_failure = true;
+        assert_eq!(symbol(&job), "!");
+    }
+
+    #[test]
+    fn test_unified_diff() {
+        let old = "test:\n  script: make test\n  stage: test\n";
+        let new = "test:\n  script: make check\n  stage: test\n";
+        assert_eq!(
+            unified_diff("main", "feature", old, new),
+            vec![
+                "--- main",
+                "+++ feature",
+                "@@ -1,3 +1,3 @@",
+                " test:",
+                "-  script: make test",
+                "+  script: make check",
+                "   stage: test",
+            ]
+        );
+        assert!(unified_diff("main", "main", old, old).is_empty());
+    }
+
+    #[test]
+    fn test_tree_lines() {
+        let pipeline = |project: &str, id, bridges| Traced {
+            project: project.to_string(),
+            id,
+            ref_: "main".to_string(),
+            status: "success".to_string(),
+            bridges,
+        };
+        let bridge = |name: &str, downstream| TracedBridge {
+            name: name.to_string(),
+            status: "success".to_string(),
+            downstream,
+        };
+        let bridges = vec![
+            bridge(
+                "deploy",
+                Some(pipeline(
+                    "group/infra",
+                    2,
+                    vec![bridge("smoke", Some(pipeline("group/tests", 3, vec![])))],
+                )),
+            ),
+            TracedBridge {
+                name: "docs".to_string(),
+                status: "manual".to_string(),
+                downstream: None,
+            },
+        ];
+        let mut lines = vec![];
+        tree_lines(&bridges, "", &mut lines);
+        let lines: Vec<String> = lines.into_iter().map(|(line, _)| line).collect();
+        assert_eq!(
+            lines,
+            vec![
+                "├── deploy → ✓ group/infra #2 on main",
+                "│   └── smoke → ✓ group/tests #3 on main",
+                "└── ▶ docs (manual, nothing triggered yet)",
+            ]
+        );
+    }
+}
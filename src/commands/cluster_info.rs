@@ -0,0 +1,218 @@
+use colored::*;
+use gitlab::api::{users, AsyncClient, AsyncQuery, RestClient};
+use http::{Method, Request, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use gitlabctl::client::Client;
+use gitlabctl::endpoints;
+use gitlabctl::error::{ApiError, Error};
+use gitlabctl::graphql;
+use gitlabctl::types::CurrentUser;
+
+use crate::cli::{ClusterInfoArgs, GlobalArgs, OutputFormat};
+use crate::commands;
+
+/// The features of gitlabctl that need a recent enough GitLab, with the version they need.
+const FEATURES: &[(&str, (u32, u32))] = &[
+    ("deployment tiers for --tier, --by-tier and -l", (13, 10)),
+    ("project topics for --topic and -l", (14, 5)),
+    (
+        "fetching whole groups with GraphQL in get environments",
+        graphql::MIN_VERSION,
+    ),
+];
+
+/// The health checks of self-hosted instances, only answered for allowed IPs.
+const CHECKS: &[&str] = &["readiness", "liveness"];
+
+#[derive(Deserialize, Serialize)]
+struct Kas {
+    enabled: bool,
+    version: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Metadata {
+    version: String,
+    revision: String,
+    /// Missing before GitLab 15.6.
+    #[serde(default)]
+    enterprise: Option<bool>,
+    /// Missing before GitLab 15.2.
+    #[serde(default)]
+    kas: Option<Kas>,
+}
+
+#[derive(Serialize)]
+struct Feature {
+    name: &'static str,
+    since: String,
+    available: bool,
+}
+
+#[derive(Serialize)]
+struct Check {
+    name: &'static str,
+    result: String,
+}
+
+#[derive(Serialize)]
+struct ClusterInfo {
+    server: String,
+    metadata: Metadata,
+    user: Option<CurrentUser>,
+    features: Vec<Feature>,
+    checks: Vec<Check>,
+}
+
+/// What a health check answered with `status` and `body`, e.g. `ok` or `failed`.
+fn health(status: StatusCode, body: &[u8]) -> String {
+    if status == StatusCode::NOT_FOUND || status == StatusCode::FORBIDDEN {
+        return "not accessible from this IP".to_owned();
+    }
+    let reported = serde_json::from_slice::<Value>(body)
+        .ok()
+        .and_then(|body| body["status"].as_str().map(str::to_owned));
+    match reported {
+        Some(reported) => reported,
+        None if status.is_success() => "ok".to_owned(),
+        None => format!("failed with {}", status),
+    }
+}
+
+/// Runs the health check `name`, which lives next to the API at e.g. `/-/readiness`.
+async fn check(gitlab: &Client, name: &'static str) -> Check {
+    let run = async {
+        let url = gitlab.rest_endpoint(&format!("../../-/{}", name))?;
+        let request = Request::builder().method(Method::GET).uri(url.as_str());
+        let rsp = gitlab.rest_async(request, vec![]).await?;
+        Ok::<_, ApiError>(health(rsp.status(), rsp.body()))
+    };
+    let result = run
+        .await
+        .unwrap_or_else(|e| format!("could not be checked: {}", e));
+    Check { name, result }
+}
+
+fn features(version: &str) -> Vec<Feature> {
+    let version = graphql::parse_version(version);
+    FEATURES
+        .iter()
+        .map(|&(name, since)| Feature {
+            name,
+            since: format!("{}.{}", since.0, since.1),
+            available: version.is_some_and(|version| version >= since),
+        })
+        .collect()
+}
+
+fn print(info: &ClusterInfo) {
+    let edition = match info.metadata.enterprise {
+        Some(true) => ", Enterprise Edition",
+        Some(false) => ", Community Edition",
+        None => "",
+    };
+    println!("Server:   {}", info.server);
+    println!(
+        "Version:  {} (revision {}){}",
+        info.metadata.version.bold(),
+        info.metadata.revision,
+        edition
+    );
+    if let Some(user) = &info.user {
+        let role = if user.is_admin {
+            " (administrator)"
+        } else {
+            ""
+        };
+        println!("User:     {}{}", user.username, role);
+    }
+    if let Some(kas) = &info.metadata.kas {
+        match (kas.enabled, &kas.version) {
+            (true, Some(version)) => println!("KAS:      enabled, {}", version),
+            (true, None) => println!("KAS:      enabled"),
+            (false, _) => println!("KAS:      disabled"),
+        }
+    }
+    println!("\nFEATURES");
+    for feature in &info.features {
+        let mark = if feature.available {
+            "yes".green()
+        } else {
+            "no ".red()
+        };
+        println!("  {}  {} (GitLab {})", mark, feature.name, feature.since);
+    }
+    println!("\nHEALTH");
+    for check in &info.checks {
+        let result = match check.result.as_str() {
+            "ok" => check.result.green(),
+            result if result.starts_with("not accessible") => check.result.dimmed(),
+            _ => check.result.red(),
+        };
+        println!("  {:9}  {}", check.name, result);
+    }
+}
+
+pub async fn run(global: &GlobalArgs, args: &ClusterInfoArgs) -> Result<(), Error> {
+    let (gitlab, context) = commands::connect(global, None).await?;
+    let metadata: Metadata = endpoints::Version
+        .query_async(&gitlab)
+        .await
+        .map_err(ApiError::from)?;
+    // Tokens without the read_user scope cannot tell who they belong to
+    let user = match users::CurrentUser::builder().build() {
+        Ok(endpoint) => endpoint.query_async(&gitlab).await.ok(),
+        Err(_) => None,
+    };
+    let mut checks = vec![];
+    for name in CHECKS {
+        checks.push(check(&gitlab, name).await);
+    }
+    let info = ClusterInfo {
+        server: context.server,
+        features: features(&metadata.version),
+        metadata,
+        user,
+        checks,
+    };
+    match args.output {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&info).unwrap()),
+        OutputFormat::Table => print(&info),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_health() {
+        assert_eq!(health(StatusCode::OK, br#"{"status":"ok"}"#), "ok");
+        assert_eq!(
+            health(StatusCode::SERVICE_UNAVAILABLE, br#"{"status":"failed"}"#),
+            "failed"
+        );
+        assert_eq!(health(StatusCode::OK, b"GitLab is alive"), "ok");
+        assert_eq!(
+            health(StatusCode::BAD_GATEWAY, b""),
+            "failed with 502 Bad Gateway"
+        );
+        assert_eq!(
+            health(StatusCode::NOT_FOUND, b""),
+            "not accessible from this IP"
+        );
+    }
+
+    #[test]
+    fn test_features() {
+        let available = |version: &str| -> Vec<bool> {
+            features(version).iter().map(|f| f.available).collect()
+        };
+        assert_eq!(available("16.4.1-ee"), vec![true, true, true]);
+        assert_eq!(available("14.0.0"), vec![true, false, false]);
+        assert_eq!(available("unknown"), vec![false, false, false]);
+    }
+}
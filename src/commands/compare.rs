@@ -0,0 +1,34 @@
+use gitlabctl::compare::{self, release_notes};
+use gitlabctl::environments::get_project;
+use gitlabctl::error::Error;
+
+use crate::cli::{CompareArgs, CompareFormat, GlobalArgs};
+use crate::commands;
+
+pub async fn run(global: &GlobalArgs, args: &CompareArgs) -> Result<(), Error> {
+    let (gitlab, context) = commands::connect(global, None).await?;
+    let project = commands::project_or_pick(&gitlab, args.project.as_deref(), &context).await?;
+    let (_, project_id) = get_project(&gitlab, &project).await?;
+    let changes = compare::changes(&gitlab, project_id, &args.from, &args.to).await?;
+    match args.format {
+        CompareFormat::Json => println!("{}", serde_json::to_string_pretty(&changes).unwrap()),
+        CompareFormat::Markdown => print!("{}", release_notes(&args.from, &args.to, &changes)),
+        CompareFormat::Text => {
+            for change in &changes {
+                let merge_requests: Vec<String> = change
+                    .merge_requests
+                    .iter()
+                    .map(|mr| format!("!{}", mr.iid))
+                    .collect();
+                println!(
+                    "{}  {}  {}  {}",
+                    change.commit.short_id,
+                    change.commit.author_name,
+                    change.commit.title,
+                    merge_requests.join(" ")
+                );
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,24 @@
+use clap::builder::PossibleValuesParser;
+use clap::CommandFactory;
+use clap_complete::Shell;
+use std::io;
+
+use gitlabctl::config::Config;
+
+use crate::cli::Cli;
+
+/// Prints the completion script for `shell`. Resources are subcommands and complete by
+/// themselves, the configured contexts are baked into the script when it is generated.
+pub fn run(shell: Shell) {
+    let mut command = Cli::command();
+    let contexts: Vec<String> = Config::parse_from_disk_or_default()
+        .map(|config| config.contexts.into_keys().collect())
+        .unwrap_or_default();
+    if !contexts.is_empty() {
+        command = command.mut_arg("context", |arg| {
+            arg.value_parser(PossibleValuesParser::new(contexts))
+        });
+    }
+    let name = command.get_name().to_owned();
+    clap_complete::generate(shell, &mut command, name, &mut io::stdout());
+}
@@ -0,0 +1,117 @@
+use colored::*;
+use futures::future::join_all;
+use gitlab::api::{users, AsyncQuery};
+use tokio::task;
+
+use gitlabctl::config::{Config, Context};
+use gitlabctl::error::{ApiError, ConfigError, Error};
+use gitlabctl::import;
+use gitlabctl::types::CurrentUser;
+
+use crate::cli::{ConfigCommand, GlobalArgs};
+use crate::commands;
+
+/// The user that the token of `context` belongs to.
+async fn sign_in(global: &GlobalArgs, config: &Config, context: &Context) -> Result<String, Error> {
+    let gitlab = commands::client(global, config, context, None).await?;
+    let user: CurrentUser = users::CurrentUser::builder()
+        .build()
+        .map_err(ApiError::builder)?
+        .query_async(&gitlab)
+        .await
+        .map_err(ApiError::from)?;
+    Ok(user.username)
+}
+
+/// Prints every problem of the config file with its line, and with `live` whether the token
+/// of every context works.
+async fn validate(global: &GlobalArgs, live: bool) -> Result<(), Error> {
+    let (path, text) = Config::read_from_disk()?;
+    let config: Config = toml::from_str(&text).map_err(ConfigError::Parse)?;
+    let path = path.display();
+    let mut count = 0;
+    let problems = config.problems();
+    for problem in &problems {
+        let location = match problem.line(&text) {
+            Some(line) => format!("{}:{}", path, line),
+            None => path.to_string(),
+        };
+        println!(
+            "{}: {}: {}",
+            location,
+            problem.field().bold(),
+            problem.message
+        );
+        count += 1;
+    }
+    if live {
+        // Signing in with a context that has problems would only repeat them
+        let contexts: Vec<(Option<&str>, Context)> = config
+            .all_contexts()
+            .into_iter()
+            .filter(|(name, _)| {
+                let table = name.map(|name| format!("contexts.{}", name));
+                !problems.iter().any(|problem| problem.table == table)
+            })
+            .collect();
+        let results = join_all(
+            contexts
+                .iter()
+                .map(|(_, context)| sign_in(global, &config, context)),
+        )
+        .await;
+        for ((name, context), result) in contexts.iter().zip(results) {
+            let name = name.unwrap_or("the top-level context");
+            match result {
+                Ok(username) => println!(
+                    "{} {}: signed in to {} as {}",
+                    "✓".green(),
+                    name,
+                    context.server,
+                    username
+                ),
+                Err(e) => {
+                    println!("{} {}: {}", "✗".red(), name, e);
+                    count += 1;
+                }
+            }
+        }
+    }
+    if count > 0 {
+        return Err(ConfigError::Invalid(count).into());
+    }
+    println!("{} is valid", path);
+    Ok(())
+}
+
+pub async fn run(global: &GlobalArgs, command: &ConfigCommand) -> Result<(), Error> {
+    match command {
+        ConfigCommand::Init => {
+            let config_path = task::spawn_blocking(Config::init_interactively).await??;
+            println!("Wrote {:?}", config_path);
+        }
+        ConfigCommand::Import { from, machine } => {
+            let imported = import::import(from, machine.as_deref())?;
+            if imported.is_empty() {
+                println!("Found no credentials to import from {}", from);
+                return Ok(());
+            }
+            let mut config = Config::parse_from_disk_or_default()?;
+            for (name, context) in imported {
+                if config.contexts.contains_key(&name) {
+                    println!("Skipping context {} as it already exists", name);
+                    continue;
+                }
+                println!("Imported context {} for {}", name, context.server);
+                if config.current_context.is_none() && config.server.is_empty() {
+                    config.current_context = Some(name.clone());
+                }
+                config.contexts.insert(name, context);
+            }
+            let config_path = config.write_to_disk()?;
+            println!("Wrote {:?}", config_path);
+        }
+        ConfigCommand::Validate { live } => validate(global, *live).await?,
+    }
+    Ok(())
+}
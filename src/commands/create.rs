@@ -0,0 +1,51 @@
+use gitlab::api::AsyncQuery;
+use serde::Deserialize;
+use std::io::{self, IsTerminal, Read};
+
+use gitlabctl::endpoints::CreateSnippet;
+use gitlabctl::error::{ApiError, ConfigError, Error};
+
+use crate::cli::{CreateSnippetArgs, GlobalArgs, Visibility};
+use crate::commands;
+
+#[derive(Deserialize)]
+struct Snippet {
+    web_url: String,
+}
+
+fn visibility(visibility: Visibility) -> &'static str {
+    match visibility {
+        Visibility::Private => "private",
+        Visibility::Internal => "internal",
+        Visibility::Public => "public",
+    }
+}
+
+pub async fn snippet(global: &GlobalArgs, args: &CreateSnippetArgs) -> Result<(), Error> {
+    if io::stdin().is_terminal() {
+        return Err(Error::Usage(
+            "Pipe the content of the snippet to stdin".to_string(),
+        ));
+    }
+    let mut content = String::new();
+    io::stdin()
+        .read_to_string(&mut content)
+        .map_err(ConfigError::Terminal)?;
+    if content.trim().is_empty() {
+        return Err(Error::Usage("The snippet is empty".to_string()));
+    }
+    let (gitlab, _) = commands::connect(global, None).await?;
+    let endpoint = CreateSnippet {
+        project: args.project.clone(),
+        title: args.title.clone(),
+        file_name: args.file_name.clone(),
+        content,
+        visibility: visibility(args.visibility),
+    };
+    let snippet: Snippet = endpoint
+        .query_async(&gitlab)
+        .await
+        .map_err(ApiError::from)?;
+    println!("{}", snippet.web_url);
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use gitlab::api::{self, AsyncQuery};
+use tracing::warn;
+
+use gitlabctl::client::Client;
+use gitlabctl::endpoints::{DeleteEnvironment, StopEnvironment};
+use gitlabctl::environments::{get_projects_for_namespace, list_environments};
+use gitlabctl::error::{ApiError, Error};
+use gitlabctl::timings::Timings;
+use gitlabctl::types::ProjectId;
+
+use crate::cli::{DeleteEnvironmentsArgs, GlobalArgs};
+use crate::commands;
+
+/// GitLab only deletes stopped environments, so stop them first.
+async fn delete_environment(
+    gitlab: &Client,
+    project: ProjectId,
+    environment: u64,
+) -> Result<(), ApiError> {
+    api::ignore(StopEnvironment {
+        project,
+        environment,
+    })
+    .query_async(gitlab)
+    .await?;
+    api::ignore(DeleteEnvironment {
+        project,
+        environment,
+    })
+    .query_async(gitlab)
+    .await?;
+    Ok(())
+}
+
+pub async fn environments(global: &GlobalArgs, args: &DeleteEnvironmentsArgs) -> Result<(), Error> {
+    let (gitlab, context) = commands::connect(global, None).await?;
+    let namespace = args
+        .namespace
+        .clone()
+        .or(context.namespace)
+        .unwrap_or_default();
+    let timings = Timings::new(false);
+    let projects = get_projects_for_namespace(&gitlab, &namespace, None, &timings, |_| ()).await?;
+    let mut matching = vec![];
+    for (project_name, project_id) in projects {
+        for env in list_environments(&gitlab, project_id, None).await? {
+            if args.names.iter().any(|filter| filter.matches(&env.name)) {
+                matching.push((project_name.clone(), project_id, env));
+            }
+        }
+    }
+    if matching.is_empty() {
+        println!("No environments match");
+        return Ok(());
+    }
+
+    for (project_name, _, env) in &matching {
+        println!("{}  {}", project_name, env.name);
+    }
+    let question = format!(
+        "Delete {} environments in group {}?",
+        matching.len(),
+        namespace
+    );
+    if !commands::confirm(global, &question)? {
+        println!("Nothing was deleted");
+        return Ok(());
+    }
+    let mut deleted = 0;
+    for (project_name, project_id, env) in &matching {
+        match delete_environment(&gitlab, *project_id, env.id).await {
+            Ok(()) => deleted += 1,
+            Err(e) => warn!("could not delete {} of {}: {}", env.name, project_name, e),
+        }
+    }
+    println!("Deleted {} of {} environments", deleted, matching.len());
+    Ok(())
+}
@@ -0,0 +1,207 @@
+use gitlab::api::projects::{jobs, pipelines};
+use gitlab::api::{self, AsyncQuery};
+use serde::Deserialize;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+
+use gitlabctl::client::Client;
+use gitlabctl::endpoints::{JobTrace, PlayJob};
+use gitlabctl::environments::{deployment_history, get_project, get_project_rows, NameFilter};
+use gitlabctl::error::{ApiError, Error, RenderError};
+use gitlabctl::table::{self, Widths};
+use gitlabctl::timings::Timings;
+use gitlabctl::types::{DeploymentJob, PastDeployment, ProjectId};
+
+use crate::cli::{DeployArgs, GlobalArgs};
+use crate::commands::{self, wait::FINAL_STATUSES};
+
+/// How long GitLab may take to create the deploy job of a new pipeline.
+const JOB_TIMEOUT: Duration = Duration::from_secs(60);
+/// The pause between two requests while waiting for the job or its log.
+const INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Deserialize)]
+struct Pipeline {
+    id: u64,
+    web_url: String,
+}
+
+#[derive(Deserialize)]
+struct Job {
+    status: String,
+}
+
+/// The job of `pipeline` among the latest deployments of an environment.
+fn job_of_pipeline(deployments: Vec<PastDeployment>, pipeline: u64) -> Option<DeploymentJob> {
+    deployments
+        .into_iter()
+        .filter_map(|deployment| deployment.deployable)
+        .find(|job| job.pipeline.id == pipeline)
+}
+
+/// Waits for the pipeline to create its deployment to `environment`.
+async fn deploy_job(
+    gitlab: &Client,
+    project: ProjectId,
+    environment: &str,
+    pipeline: u64,
+) -> Result<DeploymentJob, Error> {
+    let start = Instant::now();
+    loop {
+        let deployments = deployment_history(gitlab, project, environment, 20).await?;
+        if let Some(job) = job_of_pipeline(deployments, pipeline) {
+            return Ok(job);
+        }
+        if start.elapsed() >= JOB_TIMEOUT {
+            return Err(Error::Unmet(format!(
+                "Pipeline {} has no job that deploys to {}",
+                pipeline, environment
+            )));
+        }
+        tokio::time::sleep(INTERVAL).await;
+    }
+}
+
+/// Prints the log of `job` while it runs and returns the status it finished with.
+async fn follow(gitlab: &Client, project: ProjectId, job: u64) -> Result<String, Error> {
+    let mut printed = 0;
+    loop {
+        // The status is checked before the log, so that the last log is complete
+        let endpoint = jobs::Job::builder()
+            .project(project)
+            .job(job)
+            .build()
+            .map_err(ApiError::builder)?;
+        let status: Job = endpoint.query_async(gitlab).await.map_err(ApiError::from)?;
+        let trace = api::raw(JobTrace { project, job })
+            .query_async(gitlab)
+            .await
+            .map_err(ApiError::from)?;
+        if trace.len() > printed {
+            let mut stdout = io::stdout().lock();
+            stdout
+                .write_all(&trace[printed..])
+                .and_then(|_| stdout.flush())
+                .map_err(RenderError::from)?;
+            printed = trace.len();
+        }
+        if FINAL_STATUSES.contains(&status.status.as_str()) {
+            return Ok(status.status);
+        }
+        tokio::time::sleep(INTERVAL).await;
+    }
+}
+
+/// Prints the row of `environment` like `get environments` does.
+async fn print_environment(
+    gitlab: &Client,
+    project: (String, ProjectId),
+    environment: &str,
+) -> Result<(), Error> {
+    let filter = NameFilter::Exact(environment.to_owned());
+    let fetched = get_project_rows(
+        gitlab,
+        &Semaphore::new(1),
+        project,
+        Some(&filter),
+        1,
+        &Timings::new(false),
+    )
+    .await;
+    for e in &fetched.errors {
+        eprintln!("{}", e);
+    }
+    let rows = fetched.rows;
+    let widths = Widths::of(&rows);
+    table::print_header(&widths)?;
+    table::print_group(&rows, &widths, None)?;
+    Ok(())
+}
+
+pub async fn run(global: &GlobalArgs, args: &DeployArgs) -> Result<(), Error> {
+    let (gitlab, context) = commands::connect(global, None).await?;
+    let path = commands::project_or_pick(&gitlab, args.project.as_deref(), &context).await?;
+    let project = get_project(&gitlab, &path).await?;
+    let endpoint = pipelines::CreatePipeline::builder()
+        .project(project.1)
+        .ref_(args.ref_.as_str())
+        .build()
+        .map_err(ApiError::builder)?;
+    let pipeline: Pipeline = endpoint
+        .query_async(&gitlab)
+        .await
+        .map_err(ApiError::from)?;
+    eprintln!("Started pipeline {} for {}", pipeline.web_url, args.ref_);
+
+    let job = deploy_job(&gitlab, project.1, &args.env, pipeline.id).await?;
+    if job.status == "manual" {
+        api::ignore(PlayJob {
+            project: project.1,
+            job: job.id,
+        })
+        .query_async(&gitlab)
+        .await
+        .map_err(ApiError::from)?;
+        eprintln!(
+            "Started the manual job {} to deploy to {}",
+            job.id, args.env
+        );
+    } else {
+        eprintln!(
+            "Job {} deploys to {} once the pipeline gets to it",
+            job.id, args.env
+        );
+    }
+    if !args.follow {
+        return Ok(());
+    }
+
+    let status = follow(&gitlab, project.1, job.id).await?;
+    println!();
+    print_environment(&gitlab, project, &args.env).await?;
+    if status == "success" {
+        Ok(())
+    } else {
+        Err(Error::Unmet(format!(
+            "Deploy job {} to {} is {}",
+            job.id, args.env, status
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use gitlabctl::types::{JobPipeline, User};
+
+    fn deployment(pipeline: u64, job: u64) -> PastDeployment {
+        PastDeployment {
+            id: job,
+            iid: job,
+            created_at: Utc::now(),
+            status: "created".to_string(),
+            sha: "abc123".to_string(),
+            user: User {
+                username: "jane".to_string(),
+            },
+            deployable: Some(DeploymentJob {
+                id: job,
+                status: "manual".to_string(),
+                duration: None,
+                pipeline: JobPipeline { id: pipeline },
+            }),
+        }
+    }
+
+    #[test]
+    fn test_job_of_pipeline() {
+        let deployments = vec![deployment(2, 20), deployment(1, 10)];
+        assert_eq!(
+            job_of_pipeline(deployments.clone(), 1).map(|job| job.id),
+            Some(10)
+        );
+        assert!(job_of_pipeline(deployments, 3).is_none());
+    }
+}
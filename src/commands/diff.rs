@@ -0,0 +1,106 @@
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+use gitlabctl::endpoints::Members;
+use gitlabctl::error::Error;
+use gitlabctl::pagination;
+
+use crate::cli::{DiffResource, GlobalArgs};
+use crate::commands;
+
+#[derive(Deserialize)]
+struct Member {
+    username: String,
+    access_level: u64,
+}
+
+fn role(access_level: Option<u64>) -> String {
+    match access_level {
+        None => "-",
+        Some(5) => "minimal",
+        Some(10) => "guest",
+        Some(20) => "reporter",
+        Some(30) => "developer",
+        Some(40) => "maintainer",
+        Some(50) => "owner",
+        Some(level) => return level.to_string(),
+    }
+    .to_owned()
+}
+
+/// The users whose access level differs between `a` and `b`, with `None` for no access.
+fn member_diff(a: &[Member], b: &[Member]) -> Vec<(String, Option<u64>, Option<u64>)> {
+    let levels = |members: &[Member]| -> BTreeMap<String, u64> {
+        members
+            .iter()
+            .map(|m| (m.username.clone(), m.access_level))
+            .collect()
+    };
+    let (a, b) = (levels(a), levels(b));
+    let users: BTreeSet<&String> = a.keys().chain(b.keys()).collect();
+    users
+        .into_iter()
+        .map(|user| (user.clone(), a.get(user).copied(), b.get(user).copied()))
+        .filter(|(_, a, b)| a != b)
+        .collect()
+}
+
+pub async fn run(global: &GlobalArgs, resource: &DiffResource) -> Result<(), Error> {
+    let DiffResource::Members { a, b, projects } = resource;
+    let (gitlab, _) = commands::connect(global, None).await?;
+    let kind = if *projects { "projects" } else { "groups" };
+    let members = |path: &str| Members {
+        kind,
+        path: path.to_owned(),
+    };
+    let a_members: Vec<Member> = pagination::list(&gitlab, &members(a), None, |_| ()).await?;
+    let b_members: Vec<Member> = pagination::list(&gitlab, &members(b), None, |_| ()).await?;
+    let diff = member_diff(&a_members, &b_members);
+    if diff.is_empty() {
+        println!("{} and {} give the same access to everyone", a, b);
+        return Ok(());
+    }
+    let width = diff
+        .iter()
+        .map(|(user, _, _)| user.len())
+        .chain(["USER".len()])
+        .max()
+        .unwrap_or_default();
+    let a_width = a.len().max("maintainer".len());
+    println!("{:width$}  {:a_width$}  {}", "USER", a, b);
+    for (user, a_level, b_level) in &diff {
+        println!(
+            "{:width$}  {:a_width$}  {}",
+            user,
+            role(*a_level),
+            role(*b_level)
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(username: &str, access_level: u64) -> Member {
+        Member {
+            username: username.to_string(),
+            access_level,
+        }
+    }
+
+    #[test]
+    fn test_member_diff() {
+        let a = vec![member("alice", 40), member("bob", 30), member("carol", 30)];
+        let b = vec![member("alice", 40), member("bob", 20), member("dave", 10)];
+        assert_eq!(
+            member_diff(&a, &b),
+            vec![
+                ("bob".to_string(), Some(30), Some(20)),
+                ("carol".to_string(), Some(30), None),
+                ("dave".to_string(), None, Some(10)),
+            ]
+        );
+    }
+}
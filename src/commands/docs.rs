@@ -0,0 +1,46 @@
+use clap::{Command, CommandFactory};
+use clap_mangen::Man;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use gitlabctl::error::{Error, RenderError};
+
+use crate::cli::{Cli, DocsCommand};
+
+/// Writes the page of `command` as `name.1` and those of its subcommands as `name-sub.1`.
+fn write_man_pages(
+    command: &Command,
+    name: &str,
+    dir: &Path,
+    written: &mut Vec<PathBuf>,
+) -> Result<(), RenderError> {
+    let path = dir.join(format!("{}.1", name));
+    let mut file = File::create(&path)?;
+    Man::new(command.clone().name(name.to_owned())).render(&mut file)?;
+    written.push(path);
+    for subcommand in command
+        .get_subcommands()
+        .filter(|sub| !sub.is_hide_set() && sub.get_name() != "help")
+    {
+        let name = format!("{}-{}", name, subcommand.get_name());
+        write_man_pages(subcommand, &name, dir, written)?;
+    }
+    Ok(())
+}
+
+pub fn run(command: &DocsCommand) -> Result<(), Error> {
+    match command {
+        DocsCommand::Man { out_dir } => {
+            fs::create_dir_all(out_dir).map_err(RenderError::from)?;
+            let mut cli = Cli::command();
+            // Propagates the global flags into the subcommands
+            cli.build();
+            let mut written = vec![];
+            write_man_pages(&cli, cli.get_name(), out_dir, &mut written)?;
+            for path in written {
+                println!("Wrote {:?}", path);
+            }
+        }
+    }
+    Ok(())
+}
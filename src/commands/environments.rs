@@ -0,0 +1,896 @@
+use chrono::Local;
+use chrono_humanize::HumanTime;
+use colored::*;
+use futures::future::*;
+use futures::stream::{FuturesOrdered, StreamExt};
+use indicatif::{ProgressBar, ProgressStyle};
+use itertools::Itertools;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::io::{self, IsTerminal};
+use std::process;
+use std::time::{Duration, Instant};
+use tokio::signal;
+use tokio::sync::{watch, Semaphore};
+use tokio::time;
+use tracing::{debug, info, warn};
+
+use gitlabctl::client::Client;
+use gitlabctl::config::Config;
+use gitlabctl::environments::{
+    get_project, get_project_rows, get_projects_for_namespace, FetchError,
+};
+use gitlabctl::error::{ApiError, Error, NotifyError, RenderError, EXIT_INTERRUPTED};
+use gitlabctl::graphql;
+use gitlabctl::notify::{self, Notify};
+use gitlabctl::table::{self, EnvironmentRow, Widths};
+use gitlabctl::timings::{Phase, Timings};
+
+use crate::cli::{EnvironmentsArgs, EnvironmentsFormat, GlobalArgs};
+use crate::commands;
+
+/// Runs `future` but gives up at `deadline`.
+async fn within<F: Future>(deadline: Option<time::Instant>, future: F) -> Option<F::Output> {
+    match deadline {
+        Some(deadline) => time::timeout_at(deadline, future).await.ok(),
+        None => Some(future.await),
+    }
+}
+
+/// Turns the first Ctrl-C into a request to stop fetching and show what was fetched so far,
+/// while a second one exits right away.
+fn handle_interrupts() -> watch::Receiver<bool> {
+    let (sender, receiver) = watch::channel(false);
+    tokio::spawn(async move {
+        if signal::ctrl_c().await.is_ok() {
+            let _ = sender.send(true);
+            if signal::ctrl_c().await.is_ok() {
+                process::exit(EXIT_INTERRUPTED);
+            }
+        }
+    });
+    receiver
+}
+
+/// Runs `future` but gives up when Ctrl-C is pressed.
+async fn unless_interrupted<F: Future>(
+    interrupt: &mut watch::Receiver<bool>,
+    future: F,
+) -> Option<F::Output> {
+    if *interrupt.borrow() {
+        return None;
+    }
+    tokio::select! {
+        output = future => Some(output),
+        // Fails if no handler could be installed, then there is nothing to wait for
+        Ok(()) = interrupt.changed() => None,
+    }
+}
+
+fn print_group(
+    rows: &[EnvironmentRow],
+    widths: &Widths,
+    args: &EnvironmentsArgs,
+    timings: &Timings,
+) -> Result<(), RenderError> {
+    let start = Instant::now();
+    table::print_group(rows, widths, args.stale_after)?;
+    timings.record(Phase::Render, start);
+    Ok(())
+}
+
+fn is_wide(args: &EnvironmentsArgs) -> bool {
+    args.output == EnvironmentsFormat::Wide
+}
+
+/// Adds the optional columns that `args` ask for to `widths`.
+fn columns(args: &EnvironmentsArgs, widths: Widths) -> Widths {
+    widths
+        .with_url(is_wide(args))
+        .with_title(args.show_commit_title)
+}
+
+/// What `-o json` prints.
+#[derive(Serialize)]
+struct JsonOutput<'a> {
+    environments: &'a [EnvironmentRow],
+    errors: &'a [FetchError],
+}
+
+fn print_json(rows: &[EnvironmentRow], errors: &[FetchError]) {
+    let output = JsonOutput {
+        environments: rows,
+        errors,
+    };
+    println!("{}", serde_json::to_string_pretty(&output).unwrap());
+}
+
+/// Prints `rows` as `-o json` or `-o jsonl` asks and returns whether it did, instead of a table.
+/// JSON lines only have rows, their errors are left to the warnings.
+fn print_structured(
+    args: &EnvironmentsArgs,
+    rows: &[EnvironmentRow],
+    errors: &[FetchError],
+) -> Result<bool, RenderError> {
+    match args.output {
+        EnvironmentsFormat::Json => {
+            print_json(rows, errors);
+            Ok(true)
+        }
+        EnvironmentsFormat::Jsonl => {
+            table::print_json_lines(rows)?;
+            Ok(true)
+        }
+        EnvironmentsFormat::Table | EnvironmentsFormat::Wide => Ok(false),
+    }
+}
+
+/// Whether the row has the `--tier` and the labels of `-l`, and failed with `--only-failed`.
+fn is_shown(gitlab: &Client, args: &EnvironmentsArgs, row: &EnvironmentRow) -> bool {
+    args.tier.as_ref().is_none_or(|tier| row.tier == *tier)
+        && (!args.only_failed || row.deployment_status == "failed")
+        && gitlab.project_filter().matches_environment(row)
+}
+
+/// What one round of fetching found.
+struct Fetched {
+    groups: Vec<Vec<EnvironmentRow>>,
+    project_count: usize,
+    timed_out: Vec<String>,
+    /// The projects and environments that failed, which are missing from `groups`.
+    errors: Vec<FetchError>,
+    /// Whether Ctrl-C stopped the fetching, so that `groups` are partial.
+    interrupted: bool,
+}
+
+impl Fetched {
+    fn interrupted() -> Fetched {
+        Fetched {
+            groups: vec![],
+            project_count: 0,
+            timed_out: vec![],
+            errors: vec![],
+            interrupted: true,
+        }
+    }
+}
+
+/// When to stop fetching and show what was fetched so far.
+struct Until {
+    deadline: Option<time::Instant>,
+    /// Set by the first Ctrl-C, see `handle_interrupts`.
+    interrupt: watch::Receiver<bool>,
+}
+
+/// What to fetch the environments of.
+enum Scope {
+    Namespace(String),
+    Project(String),
+}
+
+/// How many environments of each project to fetch at the same time. A single project need
+/// not leave requests to others.
+fn project_concurrency(scope: &Scope, args: &EnvironmentsArgs) -> u16 {
+    match scope {
+        Scope::Project(_) => args.concurrency,
+        Scope::Namespace(_) => args.project_concurrency,
+    }
+}
+
+/// Fetches the deployed environments of all projects in `scope`, calling `on_group` with
+/// the rows of every project as soon as they are known.
+async fn fetch(
+    gitlab: &Client,
+    args: &EnvironmentsArgs,
+    scope: &Scope,
+    until: Until,
+    timings: &Timings,
+    progress: &ProgressBar,
+    mut on_group: impl FnMut(&[EnvironmentRow], &Widths) -> Result<(), RenderError>,
+) -> Result<Fetched, Error> {
+    let Until {
+        deadline,
+        mut interrupt,
+    } = until;
+    let limit = args.list.limit;
+    let namespace = match scope {
+        Scope::Namespace(namespace) => namespace.as_str(),
+        Scope::Project(_) => "",
+    };
+    // A whole group can be fetched with a few GraphQL queries instead of a request per
+    // project and environment, if the instance is recent enough. They are not cached, so
+    // they cannot be answered offline. The projects are only filtered by REST.
+    let graphql_groups = if namespace.is_empty()
+        || args.rest
+        || gitlab.is_offline()
+        || !gitlab.project_filter().is_empty()
+        || !graphql::is_supported(gitlab).await
+    {
+        None
+    } else {
+        let start = Instant::now();
+        let Some(fetched) = unless_interrupted(
+            &mut interrupt,
+            graphql::get_group_rows(
+                gitlab,
+                namespace,
+                limit,
+                args.environment.as_ref(),
+                deadline,
+            ),
+        )
+        .await
+        else {
+            progress.finish_and_clear();
+            return Ok(Fetched::interrupted());
+        };
+        let fetched = fetched
+            .map_err(|e| warn!("falling back to REST as GraphQL failed: {}", e))
+            .ok();
+        timings.record(Phase::Graphql, start);
+        fetched
+    };
+
+    if let Some(fetched) = graphql_groups {
+        progress.finish_and_clear();
+        let mut groups: Vec<Vec<EnvironmentRow>> = fetched
+            .groups
+            .into_iter()
+            .map(|mut rows| {
+                rows.retain(|row| is_shown(gitlab, args, row));
+                rows.sort_by(|a, b| a.environment_name.cmp(&b.environment_name));
+                rows
+            })
+            .filter(|rows| !rows.is_empty())
+            .collect();
+        groups.sort_by(|a, b| a[0].project_name.cmp(&b[0].project_name));
+        let widths = columns(args, Widths::of(&groups.concat()));
+        for rows in &groups {
+            on_group(rows, &widths)?;
+        }
+        // The projects of the pages that were not fetched are not known by name
+        let timed_out = if fetched.timed_out {
+            vec![namespace.to_owned()]
+        } else {
+            vec![]
+        };
+        return Ok(Fetched {
+            project_count: groups.len(),
+            groups,
+            timed_out,
+            errors: fetched.errors,
+            interrupted: false,
+        });
+    }
+
+    let listed = unless_interrupted(&mut interrupt, async {
+        match scope {
+            Scope::Project(path) => Ok(vec![within(deadline, get_project(gitlab, path))
+                .await
+                .ok_or(ApiError::Deadline("projects"))??]),
+            Scope::Namespace(_) => within(
+                deadline,
+                get_projects_for_namespace(gitlab, namespace, limit, timings, |n| {
+                    debug!("retrieved {} projects so far", n);
+                    progress.set_message(format!("projects {}", n));
+                }),
+            )
+            .await
+            .ok_or(ApiError::Deadline("projects"))?,
+        }
+    })
+    .await;
+    let Some(project_names) = listed else {
+        progress.finish_and_clear();
+        return Ok(Fetched::interrupted());
+    };
+    let project_names = project_names?;
+    info!("obtained {} projects", project_names.len());
+    let semaphore = Semaphore::new(args.concurrency.into());
+    let project_count = project_names.len();
+    progress.set_length(project_count as u64);
+    progress.set_style(
+        ProgressStyle::with_template("{spinner} projects {pos}/{len}, environments {msg}").unwrap(),
+    );
+    progress.set_message("0");
+    let widths = columns(args, Widths::streaming(&project_names));
+    let project_concurrency = project_concurrency(scope, args);
+    let mut outstanding: BTreeSet<String> =
+        project_names.iter().map(|(name, _)| name.clone()).collect();
+    // Fetched concurrently, but printed in the order of the projects
+    let mut pending: FuturesOrdered<_> = project_names
+        .into_iter()
+        .map(|project| {
+            let name = project.0.clone();
+            get_project_rows(
+                gitlab,
+                &semaphore,
+                project,
+                args.environment.as_ref(),
+                project_concurrency.into(),
+                timings,
+            )
+            .map(|rows| (name, rows))
+        })
+        .collect();
+
+    let mut groups = vec![];
+    let mut errors = vec![];
+    let mut environment_count = 0;
+    let mut interrupted = false;
+    loop {
+        let next = unless_interrupted(&mut interrupt, within(deadline, pending.next())).await;
+        let Some(next) = next else {
+            interrupted = true;
+            break;
+        };
+        let Some(Some((name, fetched))) = next else {
+            break;
+        };
+        outstanding.remove(&name);
+        errors.extend(fetched.errors);
+        let mut rows = fetched.rows;
+        progress.inc(1);
+        rows.retain(|row| is_shown(gitlab, args, row));
+        if rows.is_empty() {
+            continue;
+        }
+        environment_count += rows.len();
+        progress.set_message(environment_count.to_string());
+        // Hide the bar while printing, so that it is not mixed into the table
+        progress.suspend(|| on_group(&rows, &widths))?;
+        groups.push(rows);
+    }
+    progress.finish_and_clear();
+    Ok(Fetched {
+        groups,
+        project_count,
+        timed_out: outstanding.into_iter().collect(),
+        errors,
+        interrupted,
+    })
+}
+
+/// Says which projects were not fetched, as the deadline passed or Ctrl-C was pressed, and
+/// which failed.
+fn print_unfinished(timed_out: &[String], errors: &[FetchError], interrupted: bool) {
+    if interrupted {
+        let banner = match timed_out.len() {
+            0 => "PARTIAL RESULTS: interrupted".to_owned(),
+            n => format!(
+                "PARTIAL RESULTS: interrupted before {} projects were fetched",
+                n
+            ),
+        };
+        eprintln!("\n{}", banner.yellow().bold());
+        print_warnings(&[], errors);
+        return;
+    }
+    print_warnings(timed_out, errors);
+}
+
+fn print_warnings(timed_out: &[String], errors: &[FetchError]) {
+    if !timed_out.is_empty() || !errors.is_empty() {
+        eprintln!("\nWARNINGS");
+        for name in timed_out {
+            eprintln!("  {}: not finished before the deadline", name);
+        }
+        for e in errors {
+            eprintln!("  {}", e);
+        }
+    }
+}
+
+/// Says how old the data is that was shown offline.
+fn print_data_as_of(gitlab: &Client) {
+    if let Some(at) = gitlab.data_as_of() {
+        eprintln!(
+            "\nOffline, data as of {} ({})",
+            at.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+            HumanTime::from(at)
+        );
+    }
+}
+
+/// The webhook of `--notify`, which has to be configured if the flag is given.
+fn notify_config(args: &EnvironmentsArgs) -> Result<Option<Notify>, Error> {
+    if !args.notify {
+        return Ok(None);
+    }
+    let notify = Config::parse_from_disk()?.notify;
+    Ok(Some(notify.ok_or(NotifyError::NotConfigured)?))
+}
+
+/// Fetches the environments every `interval` and shows them like `watch` does, highlighting
+/// the rows that changed since the previous refresh. Runs until interrupted.
+async fn watch(
+    gitlab: &Client,
+    args: &EnvironmentsArgs,
+    scope: &Scope,
+    deadline: Option<Duration>,
+    notify: Option<&Notify>,
+) -> Result<(), Error> {
+    let interval = args.interval;
+    let timings = Timings::new(false);
+    let mut previous: Option<Vec<EnvironmentRow>> = None;
+    let mut interrupt = handle_interrupts();
+    loop {
+        let deadline = deadline.map(|d| time::Instant::now() + d);
+        let progress = ProgressBar::hidden();
+        match fetch(
+            gitlab,
+            args,
+            scope,
+            Until {
+                deadline,
+                interrupt: interrupt.clone(),
+            },
+            &timings,
+            &progress,
+            |_, _| Ok(()),
+        )
+        .await
+        {
+            // Stop watching instead of showing a partial refresh
+            Ok(fetched) if fetched.interrupted => return Ok(()),
+            Ok(fetched) => {
+                let rows = fetched.groups.concat();
+                // Structured output appends every refresh instead of redrawing the screen
+                if !print_structured(args, &rows, &fetched.errors)? {
+                    let widths = columns(args, Widths::of(&rows));
+                    // Clears the screen and moves the cursor to the top
+                    print!("\x1B[2J\x1B[H");
+                    println!(
+                        "Every {}: gitlabctl get environments    {}\n",
+                        humantime::format_duration(interval),
+                        Local::now().format("%H:%M:%S")
+                    );
+                    table::print_header(&widths)?;
+                    for group in &fetched.groups {
+                        match &previous {
+                            Some(previous) => table::print_changed_group(
+                                group,
+                                &widths,
+                                previous,
+                                args.stale_after,
+                            )?,
+                            None => table::print_group(group, &widths, args.stale_after)?,
+                        }
+                    }
+                }
+                print_warnings(&fetched.timed_out, &fetched.errors);
+                // Only notify when projects start to drift, not on every refresh
+                let drifted = table::drifted_project_names(&rows);
+                if let Some(notify) = notify {
+                    let before = previous
+                        .as_deref()
+                        .map(table::drifted_project_names)
+                        .unwrap_or_default();
+                    if !drifted.is_subset(&before) {
+                        let message = notify::drift_message(&rows, &drifted);
+                        if let Err(e) = notify::send(notify, &message).await {
+                            warn!("{}", e);
+                        }
+                    }
+                }
+                previous = Some(rows);
+            }
+            // Keep watching, the next refresh might work again
+            Err(e) => warn!("refreshing failed: {}", e),
+        }
+        if unless_interrupted(&mut interrupt, time::sleep(interval))
+            .await
+            .is_none()
+        {
+            return Ok(());
+        }
+    }
+}
+
+pub async fn run(global: &GlobalArgs, args: &EnvironmentsArgs) -> Result<(), Error> {
+    if args.all_contexts {
+        let contexts: Vec<String> = Config::parse_from_disk()?.contexts.into_keys().collect();
+        return run_several(global, args, &contexts).await;
+    }
+    if global.context.len() > 1 || args.namespace.len() > 1 {
+        return run_several(global, args, &global.context).await;
+    }
+    let (gitlab, context) = commands::connect(global, args.list.page_size).await?;
+    // In a git checkout, show its project unless a namespace is given
+    let project = match (&args.project, args.namespace.first()) {
+        (Some(project), _) => Some(project.clone()),
+        (None, None) => commands::checkout_project(&context),
+        (None, Some(_)) => None,
+    };
+    let scope = match project {
+        Some(project) => Scope::Project(project),
+        None => Scope::Namespace(
+            args.namespace
+                .first()
+                .cloned()
+                .or_else(|| context.namespace.clone())
+                .unwrap_or_default(),
+        ),
+    };
+    let notify = notify_config(args)?;
+    if args.watch {
+        return watch(&gitlab, args, &scope, global.deadline, notify.as_ref()).await;
+    }
+    let deadline = global.deadline.map(|d| time::Instant::now() + d);
+    let before = Instant::now();
+    let timings = Timings::new(args.timings);
+    let mut printed_header = false;
+    // Shown on stderr while fetching, unless the output is piped or redirected
+    let progress = if io::stdout().is_terminal() && io::stderr().is_terminal() {
+        let progress = ProgressBar::new_spinner();
+        progress.enable_steady_tick(Duration::from_millis(100));
+        progress
+    } else {
+        ProgressBar::hidden()
+    };
+    let fetched = fetch(
+        &gitlab,
+        args,
+        &scope,
+        Until {
+            deadline,
+            interrupt: handle_interrupts(),
+        },
+        &timings,
+        &progress,
+        |rows, widths| {
+            match args.output {
+                // Every project is printed as soon as it is fetched
+                EnvironmentsFormat::Jsonl => return table::print_json_lines(rows),
+                EnvironmentsFormat::Json => return Ok(()),
+                EnvironmentsFormat::Table | EnvironmentsFormat::Wide if args.by_tier => {
+                    return Ok(())
+                }
+                EnvironmentsFormat::Table | EnvironmentsFormat::Wide => {}
+            }
+            if !printed_header {
+                table::print_header(widths)?;
+                printed_header = true;
+            }
+            print_group(rows, widths, args, &timings)
+        },
+    )
+    .await?;
+    let mut results = fetched.groups.concat();
+    info!(
+        "retrieved {} environments of {} projects",
+        results.len(),
+        fetched.project_count
+    );
+    print_unfinished(&fetched.timed_out, &fetched.errors, fetched.interrupted);
+    print_data_as_of(&gitlab);
+    if fetched.interrupted {
+        return print_partial(args, &results, &fetched.errors, &timings);
+    }
+    if let Some(name) = &args.open {
+        open_environment(&results, name)?;
+    }
+    match args.output {
+        EnvironmentsFormat::Json => {
+            print_json(&results, &fetched.errors);
+            timings.print(before.elapsed());
+            return report_drift(args, notify.as_ref(), &results).await;
+        }
+        EnvironmentsFormat::Jsonl => {
+            timings.print(before.elapsed());
+            return report_drift(args, notify.as_ref(), &results).await;
+        }
+        EnvironmentsFormat::Table | EnvironmentsFormat::Wide => {}
+    }
+
+    // Early return if there is nothing to show
+    if results.is_empty() {
+        println!("There is nothing to show");
+        timings.print(before.elapsed());
+        return Ok(());
+    }
+
+    if args.summary {
+        results.sort_by(|a, b| {
+            (&a.project_name, &a.environment_name).cmp(&(&b.project_name, &b.environment_name))
+        });
+        let widths = columns(args, Widths::of(&results));
+        println!();
+        table::print_header(&widths)?;
+        for (_, group) in &results.iter().group_by(|r| r.project_name.clone()) {
+            print_group(&group.cloned().collect::<Vec<_>>(), &widths, args, &timings)?;
+        }
+    }
+    if args.by_tier {
+        print_by_tier(&results, args, &timings)?;
+    }
+    timings.print(before.elapsed());
+    report_drift(args, notify.as_ref(), &results).await
+}
+
+/// Shows what was fetched before Ctrl-C, skipping what needs all rows like drift reports.
+fn print_partial(
+    args: &EnvironmentsArgs,
+    rows: &[EnvironmentRow],
+    errors: &[FetchError],
+    timings: &Timings,
+) -> Result<(), Error> {
+    // The table and JSON lines were already printed while fetching
+    if args.output == EnvironmentsFormat::Json {
+        print_json(rows, errors);
+    } else if args.by_tier && !rows.is_empty() {
+        print_by_tier(rows, args, timings)?;
+    }
+    Err(Error::Interrupted)
+}
+
+/// Opens the URL of the environment `name` in `rows`, letting the user choose the project if
+/// several have one.
+fn open_environment(rows: &[EnvironmentRow], name: &str) -> Result<(), Error> {
+    let found: Vec<&EnvironmentRow> = rows
+        .iter()
+        .filter(|row| row.environment_name == name)
+        .collect();
+    let row = match found.as_slice() {
+        [] => return Err(Error::Usage(format!("No environment {} is shown", name))),
+        [row] => row,
+        _ => {
+            let projects: Vec<String> = found.iter().map(|row| row.project_name.clone()).collect();
+            found[commands::pick("project", &projects)?]
+        }
+    };
+    if row.external_url.is_empty() {
+        return Err(Error::Usage(format!(
+            "The environment {} of {} has no URL",
+            name, row.project_name
+        )));
+    }
+    let url = row.external_url.clone();
+    println!("{}", url);
+    open::that(&url).map_err(|source| Error::Browser { url, source })
+}
+
+/// Prints each tier with its rows by project and a subtotal.
+fn print_by_tier(
+    rows: &[EnvironmentRow],
+    args: &EnvironmentsArgs,
+    timings: &Timings,
+) -> Result<(), RenderError> {
+    let widths = columns(args, Widths::of(rows));
+    table::print_header(&widths)?;
+    for (tier, rows) in table::by_tier(rows) {
+        println!("\n{}", tier.to_uppercase().bold());
+        for (_, group) in &rows.iter().group_by(|r| {
+            (
+                r.instance.clone(),
+                r.namespace.clone(),
+                r.project_name.clone(),
+            )
+        }) {
+            print_group(&group.cloned().collect::<Vec<_>>(), &widths, args, timings)?;
+        }
+        let projects = rows
+            .iter()
+            .map(|r| (&r.instance, &r.project_name))
+            .unique()
+            .count();
+        println!("{} environments of {} projects", rows.len(), projects);
+    }
+    Ok(())
+}
+
+/// Notifies about and exits with the drift of `rows`, if the flags ask for it.
+async fn report_drift(
+    args: &EnvironmentsArgs,
+    notify: Option<&Notify>,
+    rows: &[EnvironmentRow],
+) -> Result<(), Error> {
+    if let Some(notify) = notify {
+        let drifted = table::drifted_project_names(rows);
+        if !drifted.is_empty() {
+            notify::send(notify, &notify::drift_message(rows, &drifted)).await?;
+        }
+    }
+    if args.exit_code {
+        let drifted = table::drifted_projects(rows);
+        if drifted > 0 {
+            return Err(Error::Drift(drifted));
+        }
+    }
+    Ok(())
+}
+
+/// Fetches the environments of all `-n` namespaces with all `contexts`, or the current one if
+/// none are given, at the same time and prints them together. The context is shown in the
+/// INSTANCE column if there are several, and the namespace in the NAMESPACE column.
+async fn run_several(
+    global: &GlobalArgs,
+    args: &EnvironmentsArgs,
+    contexts: &[String],
+) -> Result<(), Error> {
+    if args.watch {
+        return Err(Error::Usage(
+            "--watch only works with a single context and namespace".to_string(),
+        ));
+    }
+    let config = &Config::parse_from_disk()?;
+    let notify = notify_config(args)?;
+    let deadline = global.deadline.map(|d| time::Instant::now() + d);
+    let interrupt = handle_interrupts();
+    let several_contexts = args.all_contexts || contexts.len() > 1;
+    let several_namespaces = args.namespace.len() > 1;
+    let contexts: Vec<Option<&str>> = match contexts {
+        [] => vec![None],
+        contexts => contexts.iter().map(|name| Some(name.as_str())).collect(),
+    };
+    // Each namespace of -n with each context, or the namespace of the context without -n
+    let namespaces: Vec<Option<&String>> = match args.namespace.as_slice() {
+        [] => vec![None],
+        namespaces => namespaces.iter().map(Some).collect(),
+    };
+    let targets: Vec<(Option<&str>, Option<&String>)> =
+        contexts.into_iter().cartesian_product(namespaces).collect();
+    let fetched = join_all(targets.iter().map(|&(name, namespace)| {
+        let interrupt = interrupt.clone();
+        async move {
+            let context = config.context(name)?;
+            let gitlab = commands::client(global, config, &context, args.list.page_size).await?;
+            let namespace = namespace.cloned().or(context.namespace).unwrap_or_default();
+            let scope = Scope::Namespace(namespace.clone());
+            let timings = Timings::new(false);
+            let progress = ProgressBar::hidden();
+            let fetched = fetch(
+                &gitlab,
+                args,
+                &scope,
+                Until {
+                    deadline,
+                    interrupt,
+                },
+                &timings,
+                &progress,
+                |_, _| Ok(()),
+            )
+            .await?;
+            Ok::<_, Error>((namespace, fetched))
+        }
+    }))
+    .await;
+
+    let mut rows = vec![];
+    let mut timed_out = vec![];
+    let mut errors = vec![];
+    let mut interrupted = false;
+    for ((name, namespace), fetched) in targets.into_iter().zip(fetched) {
+        let instance = name.filter(|_| several_contexts).unwrap_or_default();
+        let label = match (name, namespace) {
+            (Some(name), Some(namespace)) => format!("{} {}", name, namespace),
+            (Some(name), None) => name.to_owned(),
+            (None, Some(namespace)) => namespace.clone(),
+            (None, None) => String::new(),
+        };
+        match fetched {
+            Ok((namespace, fetched)) => {
+                interrupted |= fetched.interrupted;
+                let namespace = if several_namespaces {
+                    namespace
+                } else {
+                    String::new()
+                };
+                rows.extend(
+                    fetched
+                        .groups
+                        .concat()
+                        .into_iter()
+                        .map(|row| EnvironmentRow {
+                            instance: instance.to_owned(),
+                            namespace: namespace.clone(),
+                            ..row
+                        }),
+                );
+                timed_out.extend(
+                    fetched
+                        .timed_out
+                        .iter()
+                        .map(|p| format!("{}: {}", label, p)),
+                );
+                errors.extend(fetched.errors.into_iter().map(|e| FetchError {
+                    project: format!("{}: {}", label, e.project),
+                    ..e
+                }));
+            }
+            Err(e) => errors.push(FetchError {
+                project: label,
+                environment: None,
+                message: e.to_string(),
+            }),
+        }
+    }
+    rows.sort_by(|a, b| {
+        (
+            &a.instance,
+            &a.namespace,
+            &a.project_name,
+            &a.environment_name,
+        )
+            .cmp(&(
+                &b.instance,
+                &b.namespace,
+                &b.project_name,
+                &b.environment_name,
+            ))
+    });
+    if interrupted {
+        print_unfinished(&timed_out, &errors, true);
+    }
+    if print_structured(args, &rows, &errors)? {
+        if interrupted {
+            return Err(Error::Interrupted);
+        }
+        print_warnings(&timed_out, &errors);
+        return report_drift(args, notify.as_ref(), &rows).await;
+    }
+    if rows.is_empty() {
+        println!("There is nothing to show");
+        return if interrupted {
+            Err(Error::Interrupted)
+        } else {
+            Ok(())
+        };
+    }
+    if args.by_tier {
+        print_by_tier(&rows, args, &Timings::new(false))?;
+    } else {
+        let widths = columns(args, Widths::of(&rows));
+        table::print_header(&widths)?;
+        for (_, group) in &rows.iter().group_by(|r| {
+            (
+                r.instance.clone(),
+                r.namespace.clone(),
+                r.project_name.clone(),
+            )
+        }) {
+            table::print_group(
+                &group.cloned().collect::<Vec<_>>(),
+                &widths,
+                args.stale_after,
+            )?;
+        }
+    }
+    if interrupted {
+        return Err(Error::Interrupted);
+    }
+    print_warnings(&timed_out, &errors);
+    report_drift(args, notify.as_ref(), &rows).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    use crate::cli::{Cli, Commands, GetResource};
+
+    #[test]
+    fn test_project_concurrency() {
+        let cli = Cli::parse_from([
+            "gitlabctl",
+            "get",
+            "environments",
+            "--concurrency",
+            "10",
+            "--project-concurrency",
+            "2",
+        ]);
+        let Commands::Get {
+            resource: GetResource::Environments(args),
+        } = cli.command
+        else {
+            unreachable!()
+        };
+        let group = Scope::Namespace("group".to_owned());
+        let project = Scope::Project("group/app".to_owned());
+        assert_eq!(project_concurrency(&group, &args), 2);
+        assert_eq!(project_concurrency(&project, &args), 10);
+    }
+}
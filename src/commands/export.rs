@@ -0,0 +1,27 @@
+use gitlabctl::error::Error;
+use gitlabctl::export::{self, ProjectConfig};
+
+use crate::cli::{ExportConfigArgs, ExportFormat, GlobalArgs};
+use crate::commands;
+
+pub async fn config(global: &GlobalArgs, args: &ExportConfigArgs) -> Result<(), Error> {
+    let (gitlab, context) = commands::connect(global, None).await?;
+    let namespace = args
+        .namespace
+        .clone()
+        .or(context.namespace)
+        .unwrap_or_default();
+    let (projects, failures) =
+        commands::for_each_project(&gitlab, &namespace, args.concurrency, |id| {
+            export::project_config(&gitlab, id)
+        })
+        .await?;
+    let mut projects: Vec<ProjectConfig> = projects.into_iter().map(|(_, config)| config).collect();
+    projects.sort_by(|a, b| a.path_with_namespace.cmp(&b.path_with_namespace));
+    match args.output {
+        ExportFormat::Hcl => print!("{}", export::to_hcl(&projects)),
+        ExportFormat::Yaml => print!("{}", export::to_yaml(&projects)),
+    }
+    eprintln!("Exported {} projects", projects.len());
+    failures.finish()
+}
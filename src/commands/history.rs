@@ -0,0 +1,56 @@
+use colored::*;
+use std::time::Duration;
+
+use gitlabctl::environments::deployment_history;
+use gitlabctl::error::Error;
+use gitlabctl::types::PastDeployment;
+
+use crate::cli::{EnvironmentHistoryArgs, GlobalArgs};
+use crate::commands;
+
+fn status(deployment: &PastDeployment) -> ColoredString {
+    let status = format!("{:8}", deployment.status);
+    match deployment.status.as_str() {
+        "success" => status.green(),
+        "failed" => status.red(),
+        "canceled" => status.yellow(),
+        _ => status.normal(),
+    }
+}
+
+fn duration(deployment: &PastDeployment) -> String {
+    match deployment.deployable.as_ref().and_then(|job| job.duration) {
+        Some(seconds) => {
+            humantime::format_duration(Duration::from_secs(seconds.round() as u64)).to_string()
+        }
+        None => "-".to_string(),
+    }
+}
+
+pub async fn environment(global: &GlobalArgs, args: &EnvironmentHistoryArgs) -> Result<(), Error> {
+    let (gitlab, _) = commands::connect(global, None).await?;
+    let (project_id, env) =
+        commands::find_environment(&gitlab, args.project.as_deref(), &args.target).await?;
+    let deployments = deployment_history(&gitlab, project_id, &env.name, args.limit).await?;
+    if deployments.is_empty() {
+        println!("{} was never deployed", env.name);
+        return Ok(());
+    }
+    let width = deployments
+        .iter()
+        .map(|d| d.user.username.len())
+        .max()
+        .unwrap_or_default();
+    for deployment in &deployments {
+        println!(
+            "{}  #{:<5} {}  {:width$}  {}  {}",
+            deployment.created_at.format("%Y-%m-%d %H:%M"),
+            deployment.iid,
+            status(deployment),
+            deployment.user.username,
+            &deployment.sha[..deployment.sha.len().min(8)],
+            duration(deployment),
+        );
+    }
+    Ok(())
+}
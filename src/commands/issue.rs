@@ -0,0 +1,39 @@
+use gitlab::api::projects::issues;
+use gitlab::api::AsyncQuery;
+use serde::Deserialize;
+
+use gitlabctl::error::{ApiError, Error};
+
+use crate::cli::{GlobalArgs, IssueCommand, IssueCreateArgs};
+use crate::commands;
+
+/// An issue or merge request that was just created.
+#[derive(Deserialize)]
+pub struct Created {
+    pub iid: u64,
+    pub web_url: String,
+}
+
+async fn create(global: &GlobalArgs, args: &IssueCreateArgs) -> Result<(), Error> {
+    let (gitlab, context) = commands::connect(global, None).await?;
+    let project = commands::project_or_pick(&gitlab, args.project.as_deref(), &context).await?;
+    let description = commands::description(&gitlab, &project, "issues", &args.description).await?;
+    let endpoint = issues::CreateIssue::builder()
+        .project(project.as_str())
+        .title(args.title.as_str())
+        .description(description)
+        .build()
+        .map_err(ApiError::builder)?;
+    let issue: Created = endpoint
+        .query_async(&gitlab)
+        .await
+        .map_err(ApiError::from)?;
+    println!("Created #{} {}", issue.iid, issue.web_url);
+    Ok(())
+}
+
+pub async fn run(global: &GlobalArgs, command: &IssueCommand) -> Result<(), Error> {
+    match command {
+        IssueCommand::Create(args) => create(global, args).await,
+    }
+}
@@ -0,0 +1,150 @@
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::routing::post;
+use axum::Router;
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpListener;
+use tokio::process::Command;
+use tracing::{info, warn};
+
+use gitlabctl::error::Error;
+
+use crate::cli::ListenArgs;
+use crate::commands::serve::listen_address;
+
+/// Compares the tokens in constant time, so that the secret cannot be guessed from timings.
+fn token_matches(secret: &str, token: &str) -> bool {
+    secret.len() == token.len()
+        && secret
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Whether the hook with `token` is accepted, which is any hook if there is no secret.
+fn is_authorized(secret: Option<&str>, token: Option<&str>) -> bool {
+    match secret {
+        Some(secret) => token.is_some_and(|token| token_matches(secret, token)),
+        None => true,
+    }
+}
+
+struct Listener {
+    secret: Option<String>,
+    events: Vec<String>,
+    exec: Option<PathBuf>,
+}
+
+impl Listener {
+    fn wants(&self, kind: &str) -> bool {
+        self.events.is_empty() || self.events.iter().any(|event| event == kind)
+    }
+
+    /// Passes the payload to `--exec` on stdin, with the event in `GITLAB_EVENT`.
+    async fn exec(&self, program: &Path, kind: &str, body: &[u8]) {
+        let child = Command::new(program)
+            .env("GITLAB_EVENT", kind)
+            .stdin(Stdio::piped())
+            .spawn();
+        let mut child = match child {
+            Ok(child) => child,
+            Err(e) => {
+                warn!("could not run {:?}: {}", program, e);
+                return;
+            }
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            if let Err(e) = stdin.write_all(body).await {
+                warn!("could not pass the {} event to {:?}: {}", kind, program, e);
+            }
+        }
+        match child.wait().await {
+            Ok(status) if !status.success() => {
+                warn!("{:?} failed on a {} event: {}", program, kind, status)
+            }
+            Ok(_) => {}
+            Err(e) => warn!("could not wait for {:?}: {}", program, e),
+        }
+    }
+}
+
+async fn receive(
+    State(listener): State<Arc<Listener>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> StatusCode {
+    let token = headers
+        .get("x-gitlab-token")
+        .and_then(|token| token.to_str().ok());
+    if !is_authorized(listener.secret.as_deref(), token) {
+        warn!("rejected a webhook with a wrong token");
+        return StatusCode::UNAUTHORIZED;
+    }
+    let payload: Value = match serde_json::from_slice(&body) {
+        Ok(payload) => payload,
+        Err(_) => return StatusCode::BAD_REQUEST,
+    };
+    let kind = payload["object_kind"].as_str().unwrap_or_default();
+    info!("received a {} event", kind);
+    if !listener.wants(kind) {
+        return StatusCode::OK;
+    }
+    match &listener.exec {
+        Some(program) => listener.exec(program, kind, &body).await,
+        None => println!("{}", json!({"event": kind, "payload": payload})),
+    }
+    StatusCode::OK
+}
+
+pub async fn run(args: &ListenArgs) -> Result<(), Error> {
+    let address = listen_address(&args.listen);
+    let serve_error = |source| Error::Serve {
+        address: address.clone(),
+        source,
+    };
+    let listener = TcpListener::bind(&address).await.map_err(serve_error)?;
+    info!("listening for webhooks on http://{}", address);
+    let state = Arc::new(Listener {
+        secret: args.secret.clone(),
+        events: args.events.clone(),
+        exec: args.exec.clone(),
+    });
+    let app = Router::new().route("/", post(receive)).with_state(state);
+    axum::serve(listener, app).await.map_err(serve_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_authorized() {
+        assert!(is_authorized(None, None));
+        assert!(is_authorized(Some("secret"), Some("secret")));
+        assert!(!is_authorized(Some("secret"), Some("secreT")));
+        assert!(!is_authorized(Some("secret"), Some("secret2")));
+        assert!(!is_authorized(Some("secret"), None));
+    }
+
+    #[test]
+    fn test_wants() {
+        let listener = Listener {
+            secret: None,
+            events: vec!["pipeline".to_string(), "deployment".to_string()],
+            exec: None,
+        };
+        assert!(listener.wants("deployment"));
+        assert!(!listener.wants("push"));
+        let listener = Listener {
+            events: vec![],
+            ..listener
+        };
+        assert!(listener.wants("push"));
+    }
+}
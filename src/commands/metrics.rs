@@ -0,0 +1,106 @@
+use chrono::Utc;
+use serde::Serialize;
+
+use gitlabctl::dora::{self, Dora};
+use gitlabctl::environments::get_namespace_rows;
+use gitlabctl::error::Error;
+use gitlabctl::metrics;
+
+use crate::cli::{DoraArgs, GlobalArgs, MetricsArgs, MetricsCommand, OutputFormat};
+use crate::commands::stats::format_seconds;
+use crate::commands::{self, serve};
+
+#[derive(Serialize)]
+struct ProjectDora {
+    project: String,
+    #[serde(flatten)]
+    dora: Dora,
+}
+
+fn format_row(project: &str, dora: &Dora, width: usize) -> String {
+    let rate = dora
+        .change_failure_rate
+        .map_or("-".to_string(), |rate| format!("{:.0}%", rate * 100.0));
+    format!(
+        "{:width$}  {:>11.2}  {:>14}  {:>14}  {:>14}",
+        project,
+        dora.deployment_frequency,
+        format_seconds(dora.lead_time_seconds),
+        rate,
+        format_seconds(dora.mttr_seconds),
+    )
+}
+
+async fn run_dora(global: &GlobalArgs, args: &DoraArgs) -> Result<(), Error> {
+    let (gitlab, context) = commands::connect(global, None).await?;
+    let namespace = args
+        .namespace
+        .clone()
+        .or(context.namespace)
+        .unwrap_or_default();
+    let window = chrono::Duration::from_std(args.since)
+        .map_err(|_| Error::Usage(format!("--since {:?} is too long", args.since)))?;
+    let since = Utc::now() - window;
+    let days = dora::days(window);
+    let (fetched, failures) =
+        commands::for_each_project(&gitlab, &namespace, args.concurrency, |id| {
+            dora::fetch(&gitlab, id, since)
+        })
+        .await?;
+
+    let projects: Vec<ProjectDora> = fetched
+        .iter()
+        .map(|(project, (deployments, incidents))| ProjectDora {
+            project: project.clone(),
+            dora: dora::compute(deployments, incidents, &args.environment, days),
+        })
+        .collect();
+    let (deployments, incidents): (Vec<_>, Vec<_>) =
+        fetched.into_iter().map(|(_, fetched)| fetched).unzip();
+    let total = dora::compute(
+        &deployments.into_iter().flatten().collect::<Vec<_>>(),
+        &incidents.into_iter().flatten().collect::<Vec<_>>(),
+        &args.environment,
+        days,
+    );
+
+    if args.output == OutputFormat::Json {
+        let output = serde_json::json!({ "projects": projects, "total": total });
+        println!("{}", serde_json::to_string_pretty(&output).unwrap());
+        return failures.finish();
+    }
+    let width = projects
+        .iter()
+        .map(|p| p.project.len())
+        .chain(["PROJECT".len(), "TOTAL".len()])
+        .max()
+        .unwrap_or_default();
+    println!(
+        "{:width$}  {:>11}  {:>14}  {:>14}  {:>14}",
+        "PROJECT", "DEPLOYS/DAY", "LEAD TIME", "CHANGE FAILURE", "MTTR"
+    );
+    for project in projects.iter().filter(|p| p.dora != Dora::default()) {
+        println!("{}", format_row(&project.project, &project.dora, width));
+    }
+    println!("{}", format_row("TOTAL", &total, width));
+    failures.finish()
+}
+
+pub async fn run(global: &GlobalArgs, args: &MetricsArgs) -> Result<(), Error> {
+    if let Some(MetricsCommand::Dora(args)) = &args.command {
+        return run_dora(global, args).await;
+    }
+    if !args.once {
+        return serve::run(global, &args.serve).await;
+    }
+    let (gitlab, context) = commands::connect(global, None).await?;
+    let namespace = args
+        .serve
+        .namespace
+        .clone()
+        .or(context.namespace)
+        .unwrap_or_default();
+    let rows = get_namespace_rows(&gitlab, &namespace, args.serve.concurrency.into()).await?;
+    print!("{}", metrics::render(&rows, Utc::now()));
+    Ok(())
+}
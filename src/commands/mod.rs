@@ -0,0 +1,409 @@
+use dialoguer::{Editor, FuzzySelect};
+use futures::stream::{FuturesUnordered, StreamExt};
+use gitlab::api::AsyncQuery;
+use serde::Deserialize;
+use std::future::Future;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tracing::debug;
+
+use gitlabctl::client::{Client, Options};
+use gitlabctl::config::{Config, Context};
+use gitlabctl::endpoints::Template;
+use gitlabctl::environments::{
+    get_project, get_projects_for_namespace, list_environments, list_projects, ProjectFilter,
+};
+use gitlabctl::error::{ApiError, ConfigError, Error};
+use gitlabctl::git;
+use gitlabctl::timings::Timings;
+use gitlabctl::types::{Environment, ProjectId};
+
+use crate::cli::{DescriptionArgs, GlobalArgs};
+
+pub mod admin;
+pub mod annotate;
+pub mod audit;
+pub mod bulk;
+pub mod cache;
+pub mod ci;
+pub mod cluster_info;
+pub mod compare;
+pub mod completion;
+pub mod config;
+pub mod create;
+pub mod delete;
+pub mod deploy;
+pub mod diff;
+pub mod docs;
+pub mod environments;
+pub mod export;
+pub mod history;
+pub mod issue;
+pub mod listen;
+pub mod metrics;
+pub mod mr;
+pub mod open;
+pub mod pipelines;
+pub mod plugin;
+pub mod protected_environments;
+pub mod prune;
+pub mod ratelimit;
+pub mod review_apps;
+pub mod schedules;
+pub mod search;
+pub mod self_update;
+pub mod serve;
+pub mod stats;
+pub mod test_report;
+pub mod time;
+pub mod todo;
+pub mod ui;
+pub mod wait;
+
+/// Whether `answer` to a `[y/N]` question agrees.
+fn is_yes(answer: &str) -> bool {
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// Asks `question` before destructive changes, unless `--yes` was given. Refuses to guess
+/// when there is no terminal to ask on.
+pub fn confirm(global: &GlobalArgs, question: &str) -> Result<bool, Error> {
+    if global.yes {
+        return Ok(true);
+    }
+    if !io::stdin().is_terminal() {
+        return Err(Error::Usage(format!(
+            "{} Pass --yes to confirm without a terminal.",
+            question
+        )));
+    }
+    print!("{} [y/N] ", question);
+    io::stdout().flush().map_err(ConfigError::Terminal)?;
+    let mut answer = String::new();
+    io::stdin()
+        .lock()
+        .read_line(&mut answer)
+        .map_err(ConfigError::Terminal)?;
+    Ok(is_yes(&answer))
+}
+
+/// The path of the project of the git checkout we run in, if `context` hosts it.
+pub fn checkout_project(context: &Context) -> Option<String> {
+    let (host, path) = git::parse_remote(&git::origin_url()?)?;
+    Some(path).filter(|_| host == git::host(&context.server))
+}
+
+/// Lets the user choose one of `items` by typing parts of it and returns its index.
+pub fn pick(prompt: &str, items: &[String]) -> Result<usize, Error> {
+    if items.is_empty() {
+        return Err(Error::Usage(format!("No {} to choose from", prompt)));
+    }
+    if !io::stdin().is_terminal() {
+        return Err(Error::Usage(format!(
+            "No {} given and no terminal to choose one on",
+            prompt
+        )));
+    }
+    FuzzySelect::new()
+        .with_prompt(prompt)
+        .items(items)
+        .default(0)
+        .interact_opt()
+        .map_err(|dialoguer::Error::IO(e)| ConfigError::Terminal(e))?
+        .ok_or_else(|| Error::Usage("Nothing was chosen".to_string()))
+}
+
+/// The project given with `--project`, or else the one of the git checkout, or else the one
+/// chosen from the projects of the namespace of the context.
+pub async fn project_or_pick(
+    gitlab: &Client,
+    project: Option<&str>,
+    context: &Context,
+) -> Result<String, Error> {
+    if let Some(project) = project
+        .map(String::from)
+        .or_else(|| checkout_project(context))
+    {
+        return Ok(project);
+    }
+    // Choosing from all projects of an instance like gitlab.com would take forever
+    let namespace = context.namespace.as_deref().ok_or_else(|| {
+        Error::Usage("No --project given and not in a checkout of a project".to_string())
+    })?;
+    let mut paths: Vec<String> = list_projects(gitlab, namespace, None, |_| ())
+        .await?
+        .into_iter()
+        .map(|project| project.path_with_namespace)
+        .collect();
+    paths.sort();
+    let chosen = pick("project", &paths)?;
+    Ok(paths.swap_remove(chosen))
+}
+
+#[derive(Deserialize)]
+struct TemplateContent {
+    content: String,
+}
+
+/// The description of a new issue or merge request of `project`, from `--description` or the
+/// `--template` of the `kind` `issues` or `merge_requests`, finished in `$EDITOR` with `--edit`.
+pub async fn description(
+    gitlab: &Client,
+    project: &str,
+    kind: &'static str,
+    args: &DescriptionArgs,
+) -> Result<String, Error> {
+    let description = match &args.template {
+        Some(name) => {
+            let endpoint = Template {
+                project: project.to_owned(),
+                kind,
+                name: name.clone(),
+            };
+            let template: TemplateContent =
+                endpoint.query_async(gitlab).await.map_err(ApiError::from)?;
+            template.content
+        }
+        None => args.description.clone().unwrap_or_default(),
+    };
+    if !args.edit {
+        return Ok(description);
+    }
+    Editor::new()
+        .extension(".md")
+        .edit(&description)
+        .map_err(|dialoguer::Error::IO(e)| ConfigError::Terminal(e))?
+        .ok_or_else(|| Error::Usage("The description was not saved".to_string()))
+}
+
+/// The ways to split `target` into a project path and an environment name, as both can
+/// contain slashes. The deepest project path comes first.
+fn splits(target: &str) -> Vec<(&str, &str)> {
+    target
+        .match_indices('/')
+        .map(|(i, _)| (&target[..i], &target[i + 1..]))
+        .filter(|(project, name)| !project.is_empty() && !name.is_empty())
+        .rev()
+        .collect()
+}
+
+async fn find_named_environment(
+    gitlab: &Client,
+    project: &str,
+    name: &str,
+) -> Result<Option<(ProjectId, Environment)>, ApiError> {
+    let (_, project_id) = get_project(gitlab, project).await?;
+    let environments = list_environments(gitlab, project_id, None).await?;
+    Ok(environments
+        .into_iter()
+        .find(|env| env.name == name)
+        .map(|env| (project_id, env)))
+}
+
+/// Finds the environment `target`, which is `<project>/<environment>` unless the project is
+/// given.
+pub async fn find_environment(
+    gitlab: &Client,
+    project: Option<&str>,
+    target: &str,
+) -> Result<(ProjectId, Environment), Error> {
+    let candidates = match project {
+        Some(project) => vec![(project, target)],
+        None => splits(target),
+    };
+    for (project, name) in candidates {
+        // A wrong split finds no such project or environment, so try the next one
+        match find_named_environment(gitlab, project, name).await {
+            Ok(Some(found)) => return Ok(found),
+            Ok(None) => {}
+            Err(e) => debug!("no environment {} in {}: {}", name, project, e),
+        }
+    }
+    Err(Error::Usage(format!(
+        "Could not find the environment {}",
+        target
+    )))
+}
+
+/// Runs `run` for each of `projects`, `concurrency` at a time, and returns what it returned
+/// by project name.
+pub async fn run_for_projects<T, F, Fut>(
+    projects: Vec<(String, ProjectId)>,
+    concurrency: u16,
+    run: F,
+) -> Vec<(String, Result<T, ApiError>)>
+where
+    F: Fn(ProjectId) -> Fut,
+    Fut: Future<Output = Result<T, ApiError>>,
+{
+    let semaphore = Semaphore::new(concurrency.into());
+    let mut results: Vec<(String, Result<T, ApiError>)> = projects
+        .into_iter()
+        .map(|(name, id)| {
+            let semaphore = &semaphore;
+            let run = &run;
+            async move {
+                let _permit = semaphore.acquire().await.unwrap();
+                (name, run(id).await)
+            }
+        })
+        .collect::<FuturesUnordered<_>>()
+        .collect()
+        .await;
+    results.sort_by(|a, b| a.0.cmp(&b.0));
+    results
+}
+
+/// The projects `for_each_project` failed for, to be reported after what it fetched.
+#[derive(Default)]
+pub struct Failures {
+    failed: Vec<(String, ApiError)>,
+    total: usize,
+}
+
+impl Failures {
+    pub fn extend(&mut self, other: Failures) {
+        self.failed.extend(other.failed);
+        self.total += other.total;
+    }
+
+    /// Reports the projects that failed on stderr, so that the output stays parseable, and
+    /// fails if there were any.
+    pub fn finish(self) -> Result<(), Error> {
+        for (name, e) in &self.failed {
+            eprintln!("{}  failed: {}", name, e);
+        }
+        match self.failed.len() {
+            0 => Ok(()),
+            failed => Err(Error::Partial {
+                failed,
+                total: self.total,
+            }),
+        }
+    }
+}
+
+/// Runs `fetch` for the projects of `namespace`, `concurrency` at a time, and returns what it
+/// returned by project name, apart from the projects it failed for.
+pub async fn for_each_project<T, F, Fut>(
+    gitlab: &Client,
+    namespace: &str,
+    concurrency: u16,
+    fetch: F,
+) -> Result<(Vec<(String, T)>, Failures), Error>
+where
+    F: Fn(ProjectId) -> Fut,
+    Fut: Future<Output = Result<T, ApiError>>,
+{
+    let timings = Timings::new(false);
+    let projects = get_projects_for_namespace(gitlab, namespace, None, &timings, |_| ()).await?;
+    let results = run_for_projects(projects, concurrency, fetch).await;
+    let mut failures = Failures {
+        failed: Vec::new(),
+        total: results.len(),
+    };
+    let mut fetched = Vec::new();
+    for (name, result) in results {
+        match result {
+            Ok(result) => fetched.push((name, result)),
+            Err(e) => failures.failed.push((name, e)),
+        }
+    }
+    Ok((fetched, failures))
+}
+
+/// Connects to the context selected by the global flags.
+pub async fn connect(
+    global: &GlobalArgs,
+    page_size: Option<u8>,
+) -> Result<(Client, Context), Error> {
+    let config = Config::parse_from_disk()?;
+    // In a checkout, connect to the server that hosts it unless a context is given
+    let checkout_context = || {
+        let (host, _) = git::parse_remote(&git::origin_url()?)?;
+        config.context_for_host(&host)
+    };
+    let context = match global.single_context()? {
+        Some(name) => config.context(Some(name))?,
+        None => checkout_context().map_or_else(|| config.context(None), Ok)?,
+    };
+    let gitlab = client(global, &config, &context, page_size).await?;
+    Ok((gitlab, context))
+}
+
+/// Connects to `context` with the options given by the config and the global flags.
+pub async fn client(
+    global: &GlobalArgs,
+    config: &Config,
+    context: &Context,
+    page_size: Option<u8>,
+) -> Result<Client, Error> {
+    let mut retry = config.retry.clone();
+    if let Some(retries) = global.retries {
+        retry.attempts = retries + 1;
+    }
+    let options = Options {
+        use_cache: !global.no_cache,
+        retry,
+        timeout: Some(global.timeout).filter(|t| *t > Duration::ZERO),
+        page_size: page_size.map(usize::from),
+        offline: global.offline,
+        projects: ProjectFilter {
+            name: global.project_filter.clone(),
+            topic: global.topic.clone(),
+            include_archived: global.include_archived,
+            exclude_subgroups: global.no_subgroups,
+            selector: global.selector.clone().unwrap_or_default(),
+        },
+    };
+    Ok(Client::new(context, options).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_yes() {
+        assert!(is_yes("y\n"));
+        assert!(is_yes(" Yes "));
+        assert!(!is_yes("\n"));
+        assert!(!is_yes("no"));
+    }
+
+    #[test]
+    fn test_splits() {
+        assert_eq!(
+            splits("group/app/review/x"),
+            vec![
+                ("group/app/review", "x"),
+                ("group/app", "review/x"),
+                ("group", "app/review/x")
+            ]
+        );
+        assert!(splits("prod").is_empty());
+    }
+    #[test]
+    fn test_failures_fail_the_command() {
+        let mut failures = Failures::default();
+        failures.extend(Failures {
+            failed: vec![],
+            total: 2,
+        });
+        assert!(failures.finish().is_ok());
+
+        let mut failures = Failures::default();
+        failures.extend(Failures {
+            failed: vec![("app".to_owned(), ApiError::Deadline("pipelines"))],
+            total: 3,
+        });
+        assert!(matches!(
+            failures.finish(),
+            Err(Error::Partial {
+                failed: 1,
+                total: 3
+            })
+        ));
+    }
+}
@@ -0,0 +1,189 @@
+use gitlab::api::projects::{self, merge_requests};
+use gitlab::api::AsyncQuery;
+use serde::Deserialize;
+
+use gitlabctl::error::{ApiError, Error};
+use gitlabctl::git;
+use gitlabctl::types::ProjectId;
+
+use crate::cli::{GlobalArgs, MrCheckoutArgs, MrCommand, MrCreateArgs};
+use crate::commands;
+use crate::commands::issue::Created;
+
+#[derive(Deserialize)]
+struct MergeRequest {
+    iid: u64,
+    title: String,
+    source_branch: String,
+    source_project_id: ProjectId,
+    project_id: ProjectId,
+}
+
+#[derive(Deserialize)]
+struct DefaultBranch {
+    default_branch: Option<String>,
+}
+
+/// How to get the source branch of a merge request into the checkout.
+#[derive(Debug, PartialEq)]
+struct Checkout {
+    /// What to fetch from `origin`.
+    refspec: String,
+    /// Where the local branch starts, once fetched.
+    start: String,
+    branch: String,
+    /// Whether the local branch tracks the source branch, so that it can be pushed to.
+    track: bool,
+}
+
+/// Fetches the source branch itself if it is in the project of the checkout. The branches of
+/// forks are not, but GitLab keeps a ref of every merge request in its target project.
+fn checkout(mr: &MergeRequest, branch: Option<&str>) -> Checkout {
+    if mr.source_project_id == mr.project_id {
+        let source = &mr.source_branch;
+        Checkout {
+            refspec: format!("refs/heads/{}:refs/remotes/origin/{}", source, source),
+            start: format!("origin/{}", source),
+            branch: branch.unwrap_or(source.as_str()).to_owned(),
+            track: true,
+        }
+    } else {
+        Checkout {
+            refspec: format!("refs/merge-requests/{}/head", mr.iid),
+            start: "FETCH_HEAD".to_owned(),
+            branch: branch.map_or_else(|| format!("mr/{}", mr.iid), str::to_owned),
+            track: false,
+        }
+    }
+}
+
+/// Runs git with `args`, failing if it does.
+fn run_git(args: &[&str]) -> Result<(), Error> {
+    if git::run(args) {
+        Ok(())
+    } else {
+        Err(Error::Git(args.join(" ")))
+    }
+}
+
+async fn mr_checkout(global: &GlobalArgs, args: &MrCheckoutArgs) -> Result<(), Error> {
+    let (gitlab, context) = commands::connect(global, None).await?;
+    let project = commands::checkout_project(&context).ok_or_else(|| {
+        Error::Usage("Not in a git checkout of a project on the configured server".to_string())
+    })?;
+    let endpoint = merge_requests::MergeRequest::builder()
+        .project(project)
+        .merge_request(args.iid)
+        .build()
+        .map_err(ApiError::builder)?;
+    let mr: MergeRequest = endpoint
+        .query_async(&gitlab)
+        .await
+        .map_err(ApiError::from)?;
+    let checkout = checkout(&mr, args.branch.as_deref());
+    println!(
+        "Checking out !{} {} as {}",
+        mr.iid, mr.title, checkout.branch
+    );
+    run_git(&["fetch", "origin", &checkout.refspec])?;
+    // Keep what is committed on an existing branch, it only moves forward
+    if git::has_branch(&checkout.branch) {
+        run_git(&["checkout", &checkout.branch])?;
+        run_git(&["merge", "--ff-only", &checkout.start])
+    } else if checkout.track {
+        run_git(&[
+            "checkout",
+            "-b",
+            &checkout.branch,
+            "--track",
+            &checkout.start,
+        ])
+    } else {
+        run_git(&["checkout", "-b", &checkout.branch, &checkout.start])
+    }
+}
+
+async fn mr_create(global: &GlobalArgs, args: &MrCreateArgs) -> Result<(), Error> {
+    let (gitlab, context) = commands::connect(global, None).await?;
+    let project = commands::project_or_pick(&gitlab, args.project.as_deref(), &context).await?;
+    let source = args
+        .source
+        .clone()
+        .or_else(git::current_branch)
+        .ok_or_else(|| Error::Usage("No --source given and no branch checked out".to_string()))?;
+    let target = match &args.target {
+        Some(target) => target.clone(),
+        None => {
+            let endpoint = projects::Project::builder()
+                .project(project.as_str())
+                .build()
+                .map_err(ApiError::builder)?;
+            let project: DefaultBranch = endpoint
+                .query_async(&gitlab)
+                .await
+                .map_err(ApiError::from)?;
+            project.default_branch.ok_or_else(|| {
+                Error::Usage("The project has no default branch, give --target".to_string())
+            })?
+        }
+    };
+    let description =
+        commands::description(&gitlab, &project, "merge_requests", &args.description).await?;
+    let endpoint = merge_requests::CreateMergeRequest::builder()
+        .project(project.as_str())
+        .source_branch(source.as_str())
+        .target_branch(target.as_str())
+        .title(args.title.as_str())
+        .description(description)
+        .build()
+        .map_err(ApiError::builder)?;
+    let mr: Created = endpoint
+        .query_async(&gitlab)
+        .await
+        .map_err(ApiError::from)?;
+    println!("Created !{} {}", mr.iid, mr.web_url);
+    Ok(())
+}
+
+pub async fn run(global: &GlobalArgs, command: &MrCommand) -> Result<(), Error> {
+    match command {
+        MrCommand::Checkout(args) => mr_checkout(global, args).await,
+        MrCommand::Create(args) => mr_create(global, args).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checkout() {
+        let mut mr = MergeRequest {
+            iid: 123,
+            title: "Add a feature".to_string(),
+            source_branch: "feature".to_string(),
+            source_project_id: 1,
+            project_id: 1,
+        };
+        assert_eq!(
+            checkout(&mr, None),
+            Checkout {
+                refspec: "refs/heads/feature:refs/remotes/origin/feature".to_string(),
+                start: "origin/feature".to_string(),
+                branch: "feature".to_string(),
+                track: true,
+            }
+        );
+        mr.source_project_id = 2;
+        assert_eq!(
+            checkout(&mr, None),
+            Checkout {
+                refspec: "refs/merge-requests/123/head".to_string(),
+                start: "FETCH_HEAD".to_string(),
+                branch: "mr/123".to_string(),
+                track: false,
+            }
+        );
+        assert_eq!(checkout(&mr, Some("review")).branch, "review");
+    }
+}
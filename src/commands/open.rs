@@ -0,0 +1,107 @@
+use gitlab::api::projects::{self, merge_requests, pipelines};
+use gitlab::api::{AsyncQuery, Endpoint};
+use serde::Deserialize;
+
+use gitlabctl::client::Client;
+use gitlabctl::config::Context;
+use gitlabctl::environments::{environment_url, get_project, list_environments};
+use gitlabctl::error::{ApiError, Error};
+use gitlabctl::pagination;
+use gitlabctl::types::WebUrl;
+
+use crate::cli::{GlobalArgs, OpenResource};
+use crate::commands;
+
+/// Resolves `target`, which is `<project>/<environment>` unless the project is given.
+async fn environment(
+    gitlab: &Client,
+    project: Option<&str>,
+    target: &str,
+) -> Result<String, Error> {
+    let (project_id, env) = commands::find_environment(gitlab, project, target).await?;
+    Ok(environment_url(gitlab, project_id, env.id).await?)
+}
+
+#[derive(Deserialize)]
+struct MergeRequest {
+    iid: u64,
+    title: String,
+}
+
+/// Lets the user choose one of the open merge requests of `project`.
+async fn pick_merge_request(gitlab: &Client, project: &str) -> Result<u64, Error> {
+    let endpoint = merge_requests::MergeRequests::builder()
+        .project(project)
+        .state(merge_requests::MergeRequestState::Opened)
+        .build()
+        .map_err(ApiError::builder)?;
+    let merge_requests: Vec<MergeRequest> =
+        pagination::list(gitlab, &endpoint, None, |_| ()).await?;
+    let items: Vec<String> = merge_requests
+        .iter()
+        .map(|mr| format!("!{} {}", mr.iid, mr.title))
+        .collect();
+    let chosen = commands::pick("merge request", &items)?;
+    Ok(merge_requests[chosen].iid)
+}
+
+/// Lets the user choose the project and then one of its environments.
+async fn pick_environment(
+    gitlab: &Client,
+    project: Option<&str>,
+    context: &Context,
+) -> Result<String, Error> {
+    let project = commands::project_or_pick(gitlab, project, context).await?;
+    let (_, project_id) = get_project(gitlab, &project).await?;
+    let environments = list_environments(gitlab, project_id, None).await?;
+    let names: Vec<String> = environments.iter().map(|env| env.name.clone()).collect();
+    let chosen = commands::pick("environment", &names)?;
+    Ok(environment_url(gitlab, project_id, environments[chosen].id).await?)
+}
+
+/// Queries the `web_url` of what `endpoint` returns.
+async fn web_url<E>(gitlab: &Client, endpoint: Result<E, impl ToString>) -> Result<String, Error>
+where
+    E: Endpoint + Sync,
+{
+    let endpoint = endpoint.map_err(ApiError::builder)?;
+    let resource: WebUrl = endpoint.query_async(gitlab).await.map_err(ApiError::from)?;
+    Ok(resource.web_url)
+}
+
+pub async fn run(global: &GlobalArgs, resource: &OpenResource) -> Result<(), Error> {
+    let (gitlab, context) = commands::connect(global, None).await?;
+    let url = match resource {
+        OpenResource::Mr { iid, project } => {
+            let project = commands::project_or_pick(&gitlab, project.as_deref(), &context).await?;
+            let iid = match iid {
+                Some(iid) => *iid,
+                None => pick_merge_request(&gitlab, &project).await?,
+            };
+            let endpoint = merge_requests::MergeRequest::builder()
+                .project(project)
+                .merge_request(iid)
+                .build();
+            web_url(&gitlab, endpoint).await?
+        }
+        OpenResource::Pipeline { id, project } => {
+            let project = commands::project_or_pick(&gitlab, project.as_deref(), &context).await?;
+            let endpoint = pipelines::Pipeline::builder()
+                .project(project)
+                .pipeline(*id)
+                .build();
+            web_url(&gitlab, endpoint).await?
+        }
+        OpenResource::Environment { target, project } => match target {
+            Some(target) => environment(&gitlab, project.as_deref(), target).await?,
+            None => pick_environment(&gitlab, project.as_deref(), &context).await?,
+        },
+        OpenResource::Project { project } => {
+            let project = commands::project_or_pick(&gitlab, project.as_deref(), &context).await?;
+            let endpoint = projects::Project::builder().project(project).build();
+            web_url(&gitlab, endpoint).await?
+        }
+    };
+    println!("{}", url);
+    open::that(&url).map_err(|source| Error::Browser { url, source })
+}
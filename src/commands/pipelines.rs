@@ -0,0 +1,211 @@
+use chrono::{DateTime, Utc};
+use chrono_humanize::HumanTime;
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use gitlabctl::endpoints::Pipelines;
+use gitlabctl::error::Error;
+use gitlabctl::pagination;
+use gitlabctl::table::status_color;
+
+use crate::cli::{GlobalArgs, OutputFormat, PipelineStatus, PipelinesArgs};
+use crate::commands::stats::window_start;
+use crate::commands::{self, Failures};
+
+#[derive(Deserialize, Serialize)]
+struct Pipeline {
+    id: u64,
+    #[serde(rename = "ref")]
+    ref_: String,
+    status: String,
+    updated_at: DateTime<Utc>,
+    web_url: String,
+}
+
+#[derive(Serialize)]
+struct ProjectPipeline {
+    /// Only set when querying several namespaces.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    namespace: String,
+    project: String,
+    #[serde(flatten)]
+    pipeline: Pipeline,
+}
+
+fn status_name(status: PipelineStatus) -> &'static str {
+    match status {
+        PipelineStatus::Created => "created",
+        PipelineStatus::Pending => "pending",
+        PipelineStatus::Running => "running",
+        PipelineStatus::Success => "success",
+        PipelineStatus::Failed => "failed",
+        PipelineStatus::Canceled => "canceled",
+        PipelineStatus::Skipped => "skipped",
+        PipelineStatus::Manual => "manual",
+        PipelineStatus::Scheduled => "scheduled",
+    }
+}
+
+/// Keeps the newest pipeline of every project and ref, of pipelines that are newest first
+/// within each project.
+fn latest(pipelines: Vec<ProjectPipeline>) -> Vec<ProjectPipeline> {
+    let mut seen = HashSet::new();
+    pipelines
+        .into_iter()
+        .filter(|p| {
+            seen.insert((
+                p.namespace.clone(),
+                p.project.clone(),
+                p.pipeline.ref_.clone(),
+            ))
+        })
+        .collect()
+}
+
+pub async fn run(global: &GlobalArgs, args: &PipelinesArgs) -> Result<(), Error> {
+    let (gitlab, context) = commands::connect(global, None).await?;
+    let namespaces = match args.namespace.as_slice() {
+        [] => vec![context.namespace.unwrap_or_default()],
+        namespaces => namespaces.to_vec(),
+    };
+    let several = namespaces.len() > 1;
+    let since = window_start(args.since)?;
+    let mut pipelines: Vec<ProjectPipeline> = vec![];
+    let mut failures = Failures::default();
+    for namespace in &namespaces {
+        let (fetched, failed) =
+            commands::for_each_project(&gitlab, namespace, args.concurrency, |id| {
+                let endpoint = Pipelines {
+                    project: id,
+                    updated_after: since,
+                    ref_: args.ref_.clone(),
+                    status: args.status.map(status_name),
+                };
+                let gitlab = &gitlab;
+                async move {
+                    pagination::list::<_, Pipeline>(gitlab, &endpoint, Some(args.limit), |_| ())
+                        .await
+                }
+            })
+            .await?;
+        failures.extend(failed);
+        let namespace = if several { namespace.as_str() } else { "" };
+        pipelines.extend(fetched.into_iter().flat_map(|(project, pipelines)| {
+            pipelines.into_iter().map(move |pipeline| ProjectPipeline {
+                namespace: namespace.to_owned(),
+                project: project.clone(),
+                pipeline,
+            })
+        }));
+    }
+    if args.latest {
+        pipelines = latest(pipelines);
+    }
+    pipelines.sort_by(|a, b| {
+        (
+            &a.namespace,
+            &a.project,
+            &a.pipeline.ref_,
+            b.pipeline.updated_at,
+        )
+            .cmp(&(
+                &b.namespace,
+                &b.project,
+                &b.pipeline.ref_,
+                a.pipeline.updated_at,
+            ))
+    });
+
+    if args.output == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&pipelines).unwrap());
+        return failures.finish();
+    }
+    if pipelines.is_empty() {
+        println!(
+            "No pipelines were updated within {}",
+            humantime::format_duration(args.since)
+        );
+        return failures.finish();
+    }
+    let project_width = pipelines
+        .iter()
+        .map(|p| p.project.len())
+        .chain(["PROJECT".len()])
+        .max()
+        .unwrap_or_default();
+    let ref_width = pipelines
+        .iter()
+        .map(|p| p.pipeline.ref_.len())
+        .chain(["REF".len()])
+        .max()
+        .unwrap_or_default();
+    let namespace_width = pipelines
+        .iter()
+        .map(|p| p.namespace.len())
+        .chain(["NAMESPACE".len()])
+        .max()
+        .unwrap_or_default();
+    if several {
+        print!("{:namespace_width$}  ", "NAMESPACE");
+    }
+    println!(
+        "{:project_width$}  {:ref_width$}  {:8}  {:16}  URL",
+        "PROJECT", "REF", "STATUS", "UPDATED"
+    );
+    for p in &pipelines {
+        if several {
+            print!("{:namespace_width$}  ", p.namespace);
+        }
+        let status = format!("{:8}", p.pipeline.status);
+        let status = match status_color(&p.pipeline.status) {
+            Some(color) => status.color(color),
+            None => status.normal(),
+        };
+        println!(
+            "{:project_width$}  {:ref_width$}  {}  {:16}  {}",
+            p.project,
+            p.pipeline.ref_,
+            status,
+            HumanTime::from(p.pipeline.updated_at).to_string(),
+            p.pipeline.web_url
+        );
+    }
+    let failed = pipelines
+        .iter()
+        .filter(|p| p.pipeline.status == "failed")
+        .count();
+    println!("{} of {} pipelines failed", failed, pipelines.len());
+    failures.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pipeline(project: &str, id: u64, ref_: &str) -> ProjectPipeline {
+        ProjectPipeline {
+            namespace: String::new(),
+            project: project.to_string(),
+            pipeline: Pipeline {
+                id,
+                ref_: ref_.to_string(),
+                status: "success".to_string(),
+                updated_at: Utc::now(),
+                web_url: String::new(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_latest() {
+        let pipelines = vec![
+            pipeline("group/app", 3, "main"),
+            pipeline("group/app", 2, "feature"),
+            pipeline("group/app", 1, "main"),
+            pipeline("group/lib", 4, "main"),
+        ];
+        let ids: Vec<u64> = latest(pipelines).iter().map(|p| p.pipeline.id).collect();
+        assert_eq!(ids, vec![3, 2, 4]);
+    }
+}
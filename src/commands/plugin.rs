@@ -0,0 +1,93 @@
+use std::env;
+use std::ffi::OsStr;
+use std::path::PathBuf;
+use std::process;
+use tokio::process::Command;
+use tracing::debug;
+
+use gitlabctl::config::Config;
+use gitlabctl::error::Error;
+
+use crate::cli::GlobalArgs;
+
+/// Returns the first executable called `name` in the directories of `path`.
+fn find_on_path(name: &str, path: &OsStr) -> Option<PathBuf> {
+    env::split_paths(path)
+        .map(|dir| dir.join(name))
+        .find(|candidate| is_executable(candidate))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &std::path::Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &std::path::Path) -> bool {
+    path.with_extension("exe").is_file() || path.is_file()
+}
+
+/// Runs `gitlabctl-<name>` from the PATH with the remaining arguments, kubectl plugin style,
+/// and exits with its exit code. The resolved context is passed on in environment variables.
+pub async fn run(global: &GlobalArgs, args: &[String]) -> Result<(), Error> {
+    let Some((name, args)) = args.split_first() else {
+        return Err(Error::Usage("Missing the plugin to run".to_owned()));
+    };
+    let executable = format!("gitlabctl-{}", name);
+    let path = env::var_os("PATH").unwrap_or_default();
+    let plugin = find_on_path(&executable, &path).ok_or_else(|| {
+        Error::Usage(format!(
+            "Unknown command `{}` and there is no `{}` plugin on the PATH",
+            name, executable
+        ))
+    })?;
+
+    let mut command = Command::new(plugin);
+    command.args(args);
+    // Plugins that do not talk to GitLab work without a config
+    let context_name = global.single_context()?;
+    let config = Config::parse_from_disk();
+    // Like for our own commands, the config can turn read-only mode on as well
+    let read_only = global.read_only || config.as_ref().is_ok_and(|config| config.read_only);
+    match config.and_then(|config| config.context(context_name)) {
+        Ok(context) => {
+            command
+                .env("GITLABCTL_SERVER", &context.server)
+                .env("GITLABCTL_TOKEN", &context.access_token)
+                .env("GITLABCTL_NAMESPACE", context.namespace.unwrap_or_default());
+        }
+        Err(e) => debug!("running {} without a context: {}", executable, e),
+    }
+    if let Some(context) = context_name {
+        command.env("GITLABCTL_CONTEXT", context);
+    }
+    if read_only {
+        command.env("GITLABCTL_READ_ONLY", "1");
+    }
+    let status = command
+        .status()
+        .await
+        .map_err(|e| Error::Usage(format!("Could not run {}: {}", executable, e)))?;
+    process::exit(status.code().unwrap_or(1))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn test_find_on_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let plugin = dir.path().join("gitlabctl-hello");
+        fs::write(&plugin, "#!/bin/sh\n").unwrap();
+        let path = env::join_paths([dir.path()]).unwrap();
+        assert_eq!(find_on_path("gitlabctl-hello", &path), None);
+        fs::set_permissions(&plugin, fs::Permissions::from_mode(0o755)).unwrap();
+        assert_eq!(find_on_path("gitlabctl-hello", &path), Some(plugin));
+        assert_eq!(find_on_path("gitlabctl-other", &path), None);
+    }
+}
@@ -0,0 +1,226 @@
+use gitlab::api::{self, AsyncQuery};
+use serde::Deserialize;
+
+use gitlabctl::client::Client;
+use gitlabctl::endpoints::{
+    Group, ProtectEnvironment, ProtectedEnvironments, UnprotectEnvironment,
+    UpdateProtectedEnvironment, UsersByUsername,
+};
+use gitlabctl::environments::get_project;
+use gitlabctl::error::{ApiError, Error};
+use gitlabctl::pagination;
+use gitlabctl::types::{Deployer, ProjectId, ProtectedEnvironment};
+
+use crate::cli::{DeleteProtectedEnvironmentArgs, GlobalArgs, ProtectedEnvironmentArgs};
+use crate::commands;
+
+#[derive(Deserialize)]
+struct Id {
+    id: u64,
+}
+
+/// Who may deploy and how many approvals are needed, e.g. `Maintainers and alice, 2 approvals`.
+fn describe(protected: &ProtectedEnvironment) -> String {
+    let deployers: Vec<&str> = protected
+        .deploy_access_levels
+        .iter()
+        .map(|level| level.access_level_description.as_str())
+        .collect();
+    let deployers = match deployers.as_slice() {
+        [] => "nobody".to_owned(),
+        [deployer] => deployer.to_string(),
+        [rest @ .., last] => format!("{} and {}", rest.join(", "), last),
+    };
+    match protected.required_approval_count {
+        0 => deployers,
+        1 => format!("{}, 1 approval", deployers),
+        count => format!("{}, {} approvals", deployers, count),
+    }
+}
+
+/// The deployers that `args` names, with the IDs of the users and groups looked up.
+async fn deployers(
+    gitlab: &Client,
+    args: &ProtectedEnvironmentArgs,
+) -> Result<Vec<Deployer>, Error> {
+    let mut deployers: Vec<Deployer> = args
+        .access_level
+        .iter()
+        .map(|access| Deployer::AccessLevel(access.level()))
+        .collect();
+    for username in &args.user {
+        let endpoint = UsersByUsername {
+            username: username.clone(),
+        };
+        let users: Vec<Id> = endpoint.query_async(gitlab).await.map_err(ApiError::from)?;
+        let user = users
+            .first()
+            .ok_or_else(|| Error::Usage(format!("There is no user {}", username)))?;
+        deployers.push(Deployer::User(user.id));
+    }
+    for path in &args.group {
+        let endpoint = Group {
+            group: path.clone(),
+        };
+        let group: Id = endpoint.query_async(gitlab).await.map_err(ApiError::from)?;
+        deployers.push(Deployer::Group(group.id));
+    }
+    Ok(deployers)
+}
+
+/// The project of `project`, or of the git checkout, by path and ID.
+async fn project(
+    global: &GlobalArgs,
+    project: Option<&str>,
+) -> Result<(Client, String, ProjectId), Error> {
+    let (gitlab, context) = commands::connect(global, None).await?;
+    let path = commands::project_or_pick(&gitlab, project, &context).await?;
+    let (_, id) = get_project(&gitlab, &path).await?;
+    Ok((gitlab, path, id))
+}
+
+pub async fn create(global: &GlobalArgs, args: &ProtectedEnvironmentArgs) -> Result<(), Error> {
+    let (gitlab, path, project) = project(global, args.project.as_deref()).await?;
+    let deployers = deployers(&gitlab, args).await?;
+    if deployers.is_empty() {
+        return Err(Error::Usage(
+            "Name who may deploy with --access-level, --user or --group".to_string(),
+        ));
+    }
+    let endpoint = ProtectEnvironment {
+        project,
+        name: args.name.clone(),
+        deployers,
+        required_approvals: args.required_approvals,
+    };
+    let protected: ProtectedEnvironment = endpoint
+        .query_async(&gitlab)
+        .await
+        .map_err(ApiError::from)?;
+    println!(
+        "Protected {} of {}: {}",
+        protected.name,
+        path,
+        describe(&protected)
+    );
+    Ok(())
+}
+
+pub async fn update(global: &GlobalArgs, args: &ProtectedEnvironmentArgs) -> Result<(), Error> {
+    let (gitlab, path, project) = project(global, args.project.as_deref()).await?;
+    let deployers = deployers(&gitlab, args).await?;
+    if deployers.is_empty() && args.required_approvals.is_none() {
+        return Err(Error::Usage(
+            "Nothing to update, pass --access-level, --user, --group or --required-approvals"
+                .to_string(),
+        ));
+    }
+    let protected: Vec<ProtectedEnvironment> =
+        pagination::list(&gitlab, &ProtectedEnvironments { project }, None, |_| ()).await?;
+    let existing = protected
+        .iter()
+        .find(|p| p.name == args.name)
+        .ok_or_else(|| {
+            Error::Usage(format!(
+                "{} of {} is not protected, create the protection first",
+                args.name, path
+            ))
+        })?;
+    // The deployers that are given replace the current ones, otherwise they are kept
+    let (add, remove) = if deployers.is_empty() {
+        (vec![], vec![])
+    } else {
+        let current: Vec<Option<Deployer>> = existing
+            .deploy_access_levels
+            .iter()
+            .map(|level| level.deployer())
+            .collect();
+        let add = deployers
+            .iter()
+            .filter(|deployer| !current.contains(&Some(**deployer)))
+            .copied()
+            .collect();
+        let remove = existing
+            .deploy_access_levels
+            .iter()
+            .filter(|level| !level.deployer().is_some_and(|d| deployers.contains(&d)))
+            .map(|level| level.id)
+            .collect();
+        (add, remove)
+    };
+    let endpoint = UpdateProtectedEnvironment {
+        project,
+        name: args.name.clone(),
+        add,
+        remove,
+        required_approvals: args.required_approvals,
+    };
+    let protected: ProtectedEnvironment = endpoint
+        .query_async(&gitlab)
+        .await
+        .map_err(ApiError::from)?;
+    println!(
+        "Updated {} of {}, was {}, now {}",
+        protected.name,
+        path,
+        describe(existing),
+        describe(&protected)
+    );
+    Ok(())
+}
+
+pub async fn delete(
+    global: &GlobalArgs,
+    args: &DeleteProtectedEnvironmentArgs,
+) -> Result<(), Error> {
+    let (gitlab, path, project) = project(global, args.project.as_deref()).await?;
+    let question = format!(
+        "Remove the protection of {} of {}, so that anyone with write access may deploy?",
+        args.name, path
+    );
+    if !commands::confirm(global, &question)? {
+        return Ok(());
+    }
+    let endpoint = UnprotectEnvironment {
+        project,
+        name: args.name.clone(),
+    };
+    api::ignore(endpoint)
+        .query_async(&gitlab)
+        .await
+        .map_err(ApiError::from)?;
+    println!("Removed the protection of {} of {}", args.name, path);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gitlabctl::types::DeployAccessLevel;
+
+    fn level(description: &str) -> DeployAccessLevel {
+        DeployAccessLevel {
+            id: 1,
+            access_level: Some(40),
+            user_id: None,
+            group_id: None,
+            access_level_description: description.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_describe() {
+        let mut protected = ProtectedEnvironment {
+            name: "production".to_string(),
+            deploy_access_levels: vec![level("Maintainers")],
+            required_approval_count: 0,
+        };
+        assert_eq!(describe(&protected), "Maintainers");
+        protected.deploy_access_levels = vec![level("Maintainers"), level("alice"), level("ops")];
+        protected.required_approval_count = 2;
+        assert_eq!(
+            describe(&protected),
+            "Maintainers, alice and ops, 2 approvals"
+        );
+    }
+}
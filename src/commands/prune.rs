@@ -0,0 +1,125 @@
+use chrono::{DateTime, Utc};
+use gitlab::api::{self, AsyncQuery};
+use serde::Deserialize;
+use tracing::warn;
+
+use gitlabctl::endpoints::{Branches, DeleteBranch};
+use gitlabctl::environments::get_project;
+use gitlabctl::error::Error;
+use gitlabctl::pagination;
+
+use crate::cli::{GlobalArgs, PruneBranchesArgs};
+use crate::commands::{self, stats};
+
+#[derive(Deserialize)]
+struct BranchCommit {
+    committed_date: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+struct Branch {
+    name: String,
+    merged: bool,
+    protected: bool,
+    default: bool,
+    commit: BranchCommit,
+}
+
+/// The branches to delete: never protected ones or the default branch, and only merged ones
+/// or ones without commits since `cutoff` if asked for.
+fn prunable(branches: &[Branch], merged: bool, cutoff: Option<DateTime<Utc>>) -> Vec<&Branch> {
+    branches
+        .iter()
+        .filter(|b| !b.protected && !b.default)
+        .filter(|b| !merged || b.merged)
+        .filter(|b| cutoff.is_none_or(|cutoff| b.commit.committed_date < cutoff))
+        .collect()
+}
+
+pub async fn branches(global: &GlobalArgs, args: &PruneBranchesArgs) -> Result<(), Error> {
+    let (gitlab, context) = commands::connect(global, None).await?;
+    let project = commands::project_or_pick(&gitlab, args.project.as_deref(), &context).await?;
+    let (_, project_id) = get_project(&gitlab, &project).await?;
+    let cutoff = args.older_than.map(stats::window_start).transpose()?;
+    let all: Vec<Branch> = pagination::list(
+        &gitlab,
+        &Branches {
+            project: project_id,
+        },
+        None,
+        |_| (),
+    )
+    .await?;
+    let branches = prunable(&all, args.merged, cutoff);
+    if branches.is_empty() {
+        println!("No branches to delete");
+        return Ok(());
+    }
+
+    for branch in &branches {
+        println!(
+            "{}  {}{}",
+            branch.commit.committed_date.format("%Y-%m-%d"),
+            branch.name,
+            if branch.merged { "  (merged)" } else { "" }
+        );
+    }
+    if args.dry_run {
+        println!("Would delete {} branches", branches.len());
+        return Ok(());
+    }
+    let question = format!("Delete {} branches of {}?", branches.len(), project);
+    if !commands::confirm(global, &question)? {
+        println!("Nothing was deleted");
+        return Ok(());
+    }
+    let mut deleted = 0;
+    for branch in &branches {
+        let delete = DeleteBranch {
+            project: project_id,
+            branch: branch.name.clone(),
+        };
+        match api::ignore(delete).query_async(&gitlab).await {
+            Ok(()) => deleted += 1,
+            Err(e) => warn!("could not delete {}: {}", branch.name, e),
+        }
+    }
+    println!("Deleted {} of {} branches", deleted, branches.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prunable() {
+        let now = Utc::now();
+        let branch = |name: &str, merged, protected, days| Branch {
+            name: name.to_string(),
+            merged,
+            protected,
+            default: name == "main",
+            commit: BranchCommit {
+                committed_date: now - chrono::Duration::days(days),
+            },
+        };
+        let branches = vec![
+            branch("main", true, true, 100),
+            branch("release", true, true, 100),
+            branch("old-merged", true, false, 100),
+            branch("new-merged", true, false, 1),
+            branch("old-open", false, false, 100),
+        ];
+        let names = |merged, cutoff| -> Vec<String> {
+            prunable(&branches, merged, cutoff)
+                .iter()
+                .map(|b| b.name.clone())
+                .collect()
+        };
+        let cutoff = Some(now - chrono::Duration::days(90));
+        assert_eq!(names(true, None), vec!["old-merged", "new-merged"]);
+        assert_eq!(names(false, cutoff), vec!["old-merged", "old-open"]);
+        assert_eq!(names(true, cutoff), vec!["old-merged"]);
+    }
+}
@@ -0,0 +1,54 @@
+use chrono::{Local, Utc};
+use chrono_humanize::HumanTime;
+use gitlab::api::{AsyncClient, RestClient};
+use http::{Method, Request};
+
+use gitlabctl::client::RateLimit;
+use gitlabctl::error::{ApiError, Error};
+
+use crate::cli::{GlobalArgs, OutputFormat, RateLimitArgs};
+use crate::commands;
+
+fn print(rate_limit: &RateLimit) {
+    if !rate_limit.is_limited() {
+        println!("GitLab sent no rate limit headers, so it does not seem to limit this token");
+        return;
+    }
+    let number = |n: Option<i64>| n.map_or("unknown".to_owned(), |n| n.to_string());
+    println!("Limit:      {} requests", number(rate_limit.limit));
+    if rate_limit.observed.is_some() {
+        println!("Observed:   {} requests", number(rate_limit.observed));
+    }
+    println!("Remaining:  {} requests", number(rate_limit.remaining));
+    if let Some(reset) = rate_limit.reset {
+        println!(
+            "Resets:     {} ({})",
+            HumanTime::from(reset.signed_duration_since(Utc::now())),
+            reset.with_timezone(&Local).format("%H:%M:%S")
+        );
+    }
+}
+
+pub async fn run(global: &GlobalArgs, args: &RateLimitArgs) -> Result<(), Error> {
+    let (gitlab, _) = commands::connect(global, None).await?;
+    // HEAD is cheap and never answered from the cache, which would not have the headers
+    let url = gitlab.rest_endpoint("version").map_err(ApiError::from)?;
+    let request = Request::builder().method(Method::HEAD).uri(url.as_str());
+    let rsp = gitlab
+        .rest_async(request, vec![])
+        .await
+        .map_err(ApiError::from)?;
+    if !rsp.status().is_success() {
+        return Err(ApiError::Status {
+            endpoint: "version".to_owned(),
+            status: rsp.status(),
+        }
+        .into());
+    }
+    let rate_limit = RateLimit::from_headers(rsp.headers());
+    match args.output {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&rate_limit).unwrap()),
+        OutputFormat::Table => print(&rate_limit),
+    }
+    Ok(())
+}
@@ -0,0 +1,173 @@
+use chrono_humanize::HumanTime;
+use colored::*;
+use futures::stream::{self, StreamExt};
+use serde::Serialize;
+use std::time::Duration;
+
+use gitlabctl::environments::list_environments;
+use gitlabctl::error::Error;
+
+use crate::cli::{GlobalArgs, OutputFormat, ReviewAppsArgs};
+use crate::commands;
+
+/// How long to wait for a review app to answer.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Serialize)]
+struct ReviewApp {
+    project: String,
+    environment: String,
+    url: Option<String>,
+    /// When it was last deployed, e.g. `3 days ago`.
+    deployed: Option<String>,
+    /// What the URL answered with `--check`, e.g. `200` or `timed out`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    check: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dead: Option<bool>,
+}
+
+/// Whether a review app that answers with `status` is gone. Apps behind a login or that only
+/// refuse HEAD requests still run.
+fn is_dead(status: u16) -> bool {
+    status >= 400 && !matches!(status, 401 | 403 | 405)
+}
+
+/// What `url` answers to a HEAD request, and whether that means the app is dead.
+async fn check(client: &reqwest::Client, url: &str) -> (String, bool) {
+    match client.head(url).send().await {
+        Ok(response) => {
+            let status = response.status().as_u16();
+            (status.to_string(), is_dead(status))
+        }
+        Err(e) if e.is_timeout() => ("timed out".to_owned(), true),
+        Err(e) if e.is_connect() => ("could not connect".to_owned(), true),
+        Err(e) => (e.to_string(), true),
+    }
+}
+
+pub async fn run(global: &GlobalArgs, args: &ReviewAppsArgs) -> Result<(), Error> {
+    let (gitlab, context) = commands::connect(global, None).await?;
+    let namespace = args
+        .namespace
+        .clone()
+        .or(context.namespace)
+        .unwrap_or_default();
+    let (projects, failures) =
+        commands::for_each_project(&gitlab, &namespace, args.concurrency, |id| {
+            list_environments(&gitlab, id, Some(&args.environment))
+        })
+        .await?;
+    let mut apps: Vec<ReviewApp> = projects
+        .into_iter()
+        .flat_map(|(project, environments)| {
+            environments
+                .into_iter()
+                .filter(|env| env.state.as_deref() != Some("stopped"))
+                .map(move |env| ReviewApp {
+                    project: project.clone(),
+                    environment: env.name,
+                    url: env.external_url.filter(|url| !url.is_empty()),
+                    deployed: env
+                        .last_deployment
+                        .map(|deployment| HumanTime::from(deployment.created_at).to_string()),
+                    check: None,
+                    dead: None,
+                })
+        })
+        .collect();
+    apps.sort_by(|a, b| (&a.project, &a.environment).cmp(&(&b.project, &b.environment)));
+
+    if args.check {
+        let client = reqwest::Client::builder()
+            .timeout(CHECK_TIMEOUT)
+            .build()
+            .map_err(|e| Error::Usage(format!("Could not create an HTTP client: {}", e)))?;
+        let checks: Vec<Option<(String, bool)>> = stream::iter(&apps)
+            .map(|app| {
+                let client = &client;
+                async move {
+                    match &app.url {
+                        Some(url) => Some(check(client, url).await),
+                        None => None,
+                    }
+                }
+            })
+            .buffered(args.concurrency.into())
+            .collect()
+            .await;
+        for (app, result) in apps.iter_mut().zip(checks) {
+            if let Some((check, dead)) = result {
+                app.check = Some(check);
+                app.dead = Some(dead);
+            }
+        }
+    }
+
+    if args.output == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&apps).unwrap());
+        return failures.finish();
+    }
+    if apps.is_empty() {
+        println!("There are no review apps");
+        return failures.finish();
+    }
+    let width = |cell: fn(&ReviewApp) -> usize, header: &str| {
+        apps.iter()
+            .map(cell)
+            .chain([header.len()])
+            .max()
+            .unwrap_or_default()
+    };
+    let project_width = width(|app| app.project.len(), "PROJECT");
+    let environment_width = width(|app| app.environment.len(), "ENVIRONMENT");
+    let deployed_width = width(
+        |app| app.deployed.as_deref().map_or(1, str::len),
+        "DEPLOYED",
+    );
+    if args.check {
+        print!("{:17}  ", "CHECK");
+    }
+    println!(
+        "{:project_width$}  {:environment_width$}  {:deployed_width$}  URL",
+        "PROJECT", "ENVIRONMENT", "DEPLOYED"
+    );
+    for app in &apps {
+        if args.check {
+            let check = format!("{:17}", app.check.as_deref().unwrap_or("-"));
+            let check = match app.dead {
+                Some(true) => check.red(),
+                Some(false) => check.green(),
+                None => check.normal(),
+            };
+            print!("{}  ", check);
+        }
+        println!(
+            "{:project_width$}  {:environment_width$}  {:deployed_width$}  {}",
+            app.project,
+            app.environment,
+            app.deployed.as_deref().unwrap_or("-"),
+            app.url.as_deref().unwrap_or("-")
+        );
+    }
+    if args.check {
+        let dead = apps.iter().filter(|app| app.dead == Some(true)).count();
+        println!("{} of {} review apps do not respond", dead, apps.len());
+    }
+    failures.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_dead() {
+        assert!(!is_dead(200));
+        assert!(!is_dead(302));
+        assert!(!is_dead(401));
+        assert!(!is_dead(405));
+        assert!(is_dead(404));
+        assert!(is_dead(502));
+    }
+}
@@ -0,0 +1,112 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+
+use gitlabctl::endpoints::PipelineSchedules;
+use gitlabctl::error::Error;
+use gitlabctl::pagination;
+
+use crate::cli::{GlobalArgs, SchedulesArgs};
+use crate::commands;
+
+#[derive(Deserialize)]
+struct Schedule {
+    description: String,
+    #[serde(rename = "ref")]
+    ref_: String,
+    cron: String,
+    next_run_at: Option<DateTime<Utc>>,
+    active: bool,
+}
+
+/// The width of the timeline, one character per slot.
+const TIMELINE_WIDTH: i64 = 48;
+
+/// Marks when `at` is on a timeline from `start` over `window`.
+fn timeline(at: DateTime<Utc>, start: DateTime<Utc>, window: Duration) -> String {
+    let slot = ((at - start).num_seconds() * TIMELINE_WIDTH / window.num_seconds().max(1))
+        .clamp(0, TIMELINE_WIDTH - 1);
+    (0..TIMELINE_WIDTH)
+        .map(|i| if i == slot { '█' } else { '·' })
+        .collect()
+}
+
+pub async fn run(global: &GlobalArgs, args: &SchedulesArgs) -> Result<(), Error> {
+    let (gitlab, context) = commands::connect(global, None).await?;
+    let namespace = args
+        .namespace
+        .clone()
+        .or(context.namespace)
+        .unwrap_or_default();
+    let window = Duration::from_std(args.within)
+        .map_err(|_| Error::Usage(format!("--within {:?} is too long", args.within)))?;
+    let now = Utc::now();
+    let (schedules, failures) =
+        commands::for_each_project(&gitlab, &namespace, args.concurrency, |id| {
+            let endpoint = PipelineSchedules { project: id };
+            let gitlab = &gitlab;
+            async move { pagination::list::<_, Schedule>(gitlab, &endpoint, None, |_| ()).await }
+        })
+        .await?;
+    let mut upcoming: Vec<(String, Schedule, DateTime<Utc>)> = schedules
+        .into_iter()
+        .flat_map(|(project, schedules)| {
+            schedules
+                .into_iter()
+                .map(move |schedule| (project.clone(), schedule))
+        })
+        .filter(|(_, schedule)| schedule.active)
+        .filter_map(|(project, schedule)| {
+            let next = schedule.next_run_at?;
+            Some((project, schedule, next))
+        })
+        .filter(|(_, _, next)| *next <= now + window)
+        .collect();
+    upcoming.sort_by_key(|(_, _, next)| *next);
+    if upcoming.is_empty() {
+        println!(
+            "No schedules run within {}",
+            humantime::format_duration(args.within)
+        );
+        return failures.finish();
+    }
+
+    let width = upcoming
+        .iter()
+        .map(|(project, schedule, _)| project.len() + schedule.description.len() + 2)
+        .chain(["SCHEDULE".len()])
+        .max()
+        .unwrap_or_default();
+    println!(
+        "{:16}  {:width$}  {:w$}  CRON",
+        "NEXT RUN",
+        "SCHEDULE",
+        format!("NOW  -> {}", humantime::format_duration(args.within)),
+        w = TIMELINE_WIDTH as usize
+    );
+    for (project, schedule, next) in &upcoming {
+        println!(
+            "{:16}  {:width$}  {}  {} ({})",
+            next.format("%Y-%m-%d %H:%M"),
+            format!("{}: {}", project, schedule.description),
+            timeline(*next, now, window),
+            schedule.cron,
+            schedule.ref_
+        );
+    }
+    failures.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timeline() {
+        let start = Utc::now();
+        let window = Duration::hours(24);
+        let line = timeline(start + Duration::hours(12), start, window);
+        assert_eq!(line.chars().position(|c| c == '█'), Some(24));
+        let line = timeline(start + Duration::hours(30), start, window);
+        assert_eq!(line.chars().position(|c| c == '█'), Some(47));
+    }
+}
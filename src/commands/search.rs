@@ -0,0 +1,204 @@
+use chrono::{DateTime, Utc};
+use colored::*;
+use futures::future::try_join_all;
+use gitlab::api::{projects, AsyncQuery};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+
+use gitlabctl::client::Client;
+use gitlabctl::endpoints::Search;
+use gitlabctl::error::{ApiError, Error};
+use gitlabctl::pagination;
+use gitlabctl::types::{Project, ProjectId};
+
+use crate::cli::{GlobalArgs, OutputFormat, SearchArgs, SearchScope};
+use crate::commands;
+
+#[derive(Deserialize)]
+struct FoundProject {
+    path_with_namespace: String,
+    description: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct References {
+    full: String,
+}
+
+/// An issue or merge request.
+#[derive(Deserialize)]
+struct Issuable {
+    title: String,
+    state: String,
+    references: References,
+    web_url: String,
+}
+
+#[derive(Deserialize)]
+struct Blob {
+    project_id: ProjectId,
+    path: String,
+    startline: u64,
+    data: String,
+}
+
+#[derive(Deserialize)]
+struct FoundCommit {
+    project_id: ProjectId,
+    short_id: String,
+    title: String,
+    author_name: String,
+    created_at: DateTime<Utc>,
+}
+
+/// Where `query` occurs in `text`, ignoring ASCII case like GitLab does.
+fn occurrences(text: &str, query: &str) -> Vec<(usize, usize)> {
+    if query.is_empty() {
+        return vec![];
+    }
+    let query = query.to_ascii_lowercase();
+    text.to_ascii_lowercase()
+        .match_indices(&query)
+        .map(|(start, _)| (start, start + query.len()))
+        .collect()
+}
+
+/// Marks every occurrence of `query` in `text`.
+fn highlight(text: &str, query: &str) -> String {
+    let mut highlighted = String::new();
+    let mut rest = 0;
+    for (start, end) in occurrences(text, query) {
+        highlighted.push_str(&text[rest..start]);
+        highlighted.push_str(&text[start..end].red().bold().to_string());
+        rest = end;
+    }
+    highlighted.push_str(&text[rest..]);
+    highlighted
+}
+
+fn state(state: &str) -> ColoredString {
+    let padded = format!("{:8}", state);
+    match state {
+        "opened" => padded.green(),
+        "merged" => padded.blue(),
+        "closed" => padded.red(),
+        _ => padded.normal(),
+    }
+}
+
+/// The paths of the projects that blobs and commits only name by their ID.
+async fn project_paths(
+    gitlab: &Client,
+    ids: BTreeSet<ProjectId>,
+) -> Result<HashMap<ProjectId, String>, ApiError> {
+    try_join_all(ids.into_iter().map(|id| async move {
+        let endpoint = projects::Project::builder()
+            .project(id)
+            .build()
+            .map_err(ApiError::builder)?;
+        let project: Project = endpoint.query_async(gitlab).await?;
+        Ok::<_, ApiError>((id, project.path_with_namespace))
+    }))
+    .await
+    .map(|paths| paths.into_iter().collect())
+}
+
+async fn find<T: DeserializeOwned>(
+    gitlab: &Client,
+    endpoint: &Search,
+    limit: usize,
+) -> Result<Vec<T>, ApiError> {
+    pagination::list(gitlab, endpoint, Some(limit), |_| ()).await
+}
+
+pub async fn run(global: &GlobalArgs, args: &SearchArgs) -> Result<(), Error> {
+    let (gitlab, context) = commands::connect(global, None).await?;
+    let endpoint = Search {
+        group: args
+            .namespace
+            .clone()
+            .or(context.namespace)
+            .unwrap_or_default(),
+        scope: args.scope.name(),
+        search: args.query.clone(),
+    };
+    if args.output == OutputFormat::Json {
+        let results: Vec<Value> = find(&gitlab, &endpoint, args.limit).await?;
+        println!("{}", serde_json::to_string_pretty(&results).unwrap());
+        return Ok(());
+    }
+
+    match args.scope {
+        SearchScope::Projects => {
+            for project in find::<FoundProject>(&gitlab, &endpoint, args.limit).await? {
+                println!(
+                    "{}  {}",
+                    highlight(&project.path_with_namespace, &args.query).bold(),
+                    project.description.unwrap_or_default()
+                );
+            }
+        }
+        SearchScope::Issues | SearchScope::MergeRequests => {
+            for found in find::<Issuable>(&gitlab, &endpoint, args.limit).await? {
+                println!(
+                    "{}  {}  {}  {}",
+                    found.references.full.cyan(),
+                    state(&found.state),
+                    highlight(&found.title, &args.query),
+                    found.web_url.dimmed()
+                );
+            }
+        }
+        SearchScope::Blobs => {
+            let blobs: Vec<Blob> = find(&gitlab, &endpoint, args.limit).await?;
+            let paths =
+                project_paths(&gitlab, blobs.iter().map(|b| b.project_id).collect()).await?;
+            for blob in &blobs {
+                println!(
+                    "{} {}:{}",
+                    paths[&blob.project_id].cyan(),
+                    blob.path.bold(),
+                    blob.startline
+                );
+                for (i, line) in blob.data.lines().enumerate() {
+                    let number = format!("{:>5}", blob.startline + i as u64);
+                    println!("{}  {}", number.dimmed(), highlight(line, &args.query));
+                }
+                println!();
+            }
+        }
+        SearchScope::Commits => {
+            let commits: Vec<FoundCommit> = find(&gitlab, &endpoint, args.limit).await?;
+            let paths =
+                project_paths(&gitlab, commits.iter().map(|c| c.project_id).collect()).await?;
+            for commit in &commits {
+                println!(
+                    "{}  {}  {}  {}  {}",
+                    commit.created_at.format("%Y-%m-%d"),
+                    paths[&commit.project_id].cyan(),
+                    commit.short_id.yellow(),
+                    commit.author_name,
+                    highlight(&commit.title, &args.query)
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_occurrences() {
+        assert_eq!(
+            occurrences("a Config and a config", "CONFIG"),
+            vec![(2, 8), (15, 21)]
+        );
+        assert_eq!(occurrences("kind: Service", "config"), vec![]);
+        assert_eq!(occurrences("anything", ""), vec![]);
+    }
+}
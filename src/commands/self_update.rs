@@ -0,0 +1,155 @@
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use gitlabctl::error::{Error, UpdateError};
+
+use crate::cli::SelfUpdateArgs;
+
+const LATEST_RELEASE: &str = "https://api.github.com/repos/bijancn/gitlabctl/releases/latest";
+
+#[derive(Deserialize)]
+struct Release {
+    tag_name: String,
+    assets: Vec<Asset>,
+}
+
+#[derive(Deserialize)]
+struct Asset {
+    name: String,
+    browser_download_url: String,
+}
+
+/// The name of the release binary for the platform we run on, e.g. `gitlabctl-x86_64-linux`.
+fn asset_name() -> String {
+    let name = format!("gitlabctl-{}-{}", env::consts::ARCH, env::consts::OS);
+    if cfg!(windows) {
+        name + ".exe"
+    } else {
+        name
+    }
+}
+
+/// Parses `v1.2.3` or `1.2.3` into comparable numbers.
+fn parse_version(version: &str) -> Vec<u64> {
+    version
+        .trim_start_matches('v')
+        .split('.')
+        .map(|part| part.parse().unwrap_or_default())
+        .collect()
+}
+
+/// Reads the checksum from a `sha256sum` line, which is followed by the file name.
+fn parse_checksum(file: &str) -> Option<&str> {
+    file.split_whitespace().next()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+async fn download(client: &reqwest::Client, url: &str) -> Result<Vec<u8>, UpdateError> {
+    let rsp = client.get(url).send().await?.error_for_status()?;
+    Ok(rsp.bytes().await?.to_vec())
+}
+
+/// Replaces the executable at `exe` by writing next to it and renaming, so that it is never
+/// left half written.
+fn install(exe: &Path, binary: &[u8]) -> Result<(), UpdateError> {
+    let install_error = |source| UpdateError::Install {
+        path: exe.to_path_buf(),
+        source,
+    };
+    let new = exe.with_extension("new");
+    fs::write(&new, binary).map_err(install_error)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&new, fs::Permissions::from_mode(0o755)).map_err(install_error)?;
+    }
+    // Windows does not allow replacing a running executable, but allows renaming it
+    if cfg!(windows) {
+        fs::rename(exe, exe.with_extension("old")).map_err(install_error)?;
+    }
+    fs::rename(&new, exe).map_err(install_error)
+}
+
+pub async fn run(args: &SelfUpdateArgs) -> Result<(), Error> {
+    let client = reqwest::Client::builder()
+        .user_agent(concat!("gitlabctl/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .map_err(UpdateError::from)?;
+    let release: Release = client
+        .get(LATEST_RELEASE)
+        .send()
+        .await
+        .and_then(|rsp| rsp.error_for_status())
+        .map_err(UpdateError::from)?
+        .json()
+        .await
+        .map_err(UpdateError::from)?;
+    let current = env!("CARGO_PKG_VERSION");
+    if parse_version(&release.tag_name) <= parse_version(current) {
+        println!("gitlabctl {} is the latest version", current);
+        return Ok(());
+    }
+    if args.check {
+        println!(
+            "gitlabctl {} is available, this is {}",
+            release.tag_name, current
+        );
+        return Ok(());
+    }
+
+    let name = asset_name();
+    let find = |name: &str| {
+        release
+            .assets
+            .iter()
+            .find(|asset| asset.name == name)
+            .ok_or_else(|| UpdateError::NoAsset(name.to_owned()))
+    };
+    let binary = download(&client, &find(&name)?.browser_download_url).await?;
+    let checksum = download(
+        &client,
+        &find(&format!("{}.sha256", name))?.browser_download_url,
+    )
+    .await?;
+    let expected = parse_checksum(&String::from_utf8_lossy(&checksum))
+        .unwrap_or_default()
+        .to_lowercase();
+    let actual = hex(&Sha256::digest(&binary));
+    if expected != actual {
+        return Err(UpdateError::Checksum { expected, actual }.into());
+    }
+    let exe = env::current_exe().map_err(|source| UpdateError::Install {
+        path: "gitlabctl".into(),
+        source,
+    })?;
+    install(&exe, &binary)?;
+    println!("Updated gitlabctl from {} to {}", current, release.tag_name);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_version() {
+        assert!(parse_version("v0.2.0") > parse_version("0.1.0"));
+        assert!(parse_version("0.10.0") > parse_version("0.9.1"));
+        assert_eq!(parse_version("v0.1.0"), parse_version("0.1.0"));
+    }
+
+    #[test]
+    fn test_checksum() {
+        let sum = "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9  gitlabctl\n";
+        assert_eq!(
+            parse_checksum(sum),
+            Some(&hex(&Sha256::digest(b"hello world"))[..])
+        );
+    }
+}
@@ -0,0 +1,109 @@
+use axum::extract::State;
+use axum::http::header;
+use axum::routing::get;
+use axum::{Json, Router};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::{Arc, RwLock};
+use tokio::net::TcpListener;
+use tokio::time;
+use tracing::{info, warn};
+
+use gitlabctl::client::Client;
+use gitlabctl::environments::get_namespace_rows;
+use gitlabctl::error::Error;
+use gitlabctl::metrics;
+use gitlabctl::table::EnvironmentRow;
+
+use crate::cli::{GlobalArgs, ServeArgs};
+use crate::commands;
+
+/// The state that is served, refreshed in the background.
+#[derive(Clone, Default, Serialize)]
+struct Snapshot {
+    /// When the environments were last fetched successfully.
+    updated_at: Option<DateTime<Utc>>,
+    /// Why the last refresh failed, if it did.
+    error: Option<String>,
+    environments: Vec<EnvironmentRow>,
+}
+
+type Shared = Arc<RwLock<Snapshot>>;
+
+/// Accepts `:9100` to listen on all interfaces, like Prometheus exporters do.
+pub fn listen_address(listen: &str) -> String {
+    if listen.starts_with(':') {
+        format!("0.0.0.0{}", listen)
+    } else {
+        listen.to_string()
+    }
+}
+
+async fn environments(State(snapshot): State<Shared>) -> Json<Snapshot> {
+    Json(snapshot.read().unwrap().clone())
+}
+
+async fn prometheus(
+    State(snapshot): State<Shared>,
+) -> ([(header::HeaderName, &'static str); 1], String) {
+    let body = metrics::render(&snapshot.read().unwrap().environments, Utc::now());
+    ([(header::CONTENT_TYPE, metrics::CONTENT_TYPE)], body)
+}
+
+/// Fetches the environments every `--interval`, keeping the previous ones if that fails.
+async fn refresh(gitlab: &Client, args: &ServeArgs, namespace: &str, snapshot: &Shared) {
+    loop {
+        match get_namespace_rows(gitlab, namespace, args.concurrency.into()).await {
+            Ok(rows) => {
+                info!("refreshed {} environments", rows.len());
+                let mut snapshot = snapshot.write().unwrap();
+                snapshot.updated_at = Some(Utc::now());
+                snapshot.error = None;
+                snapshot.environments = rows;
+            }
+            Err(e) => {
+                warn!("refreshing failed: {}", e);
+                snapshot.write().unwrap().error = Some(e.to_string());
+            }
+        }
+        time::sleep(args.interval).await;
+    }
+}
+
+pub async fn run(global: &GlobalArgs, args: &ServeArgs) -> Result<(), Error> {
+    let (gitlab, context) = commands::connect(global, None).await?;
+    let namespace = args
+        .namespace
+        .clone()
+        .or(context.namespace)
+        .unwrap_or_default();
+    let address = listen_address(&args.listen);
+    let serve_error = |source| Error::Serve {
+        address: address.clone(),
+        source,
+    };
+    let listener = TcpListener::bind(&address).await.map_err(serve_error)?;
+    info!("serving on http://{}", address);
+
+    let snapshot = Shared::default();
+    let app = Router::new()
+        .route("/environments", get(environments))
+        .route("/metrics", get(prometheus))
+        .route("/healthz", get(|| async { "ok" }))
+        .with_state(snapshot.clone());
+    tokio::select! {
+        _ = refresh(&gitlab, args, &namespace, &snapshot) => Ok(()),
+        result = axum::serve(listener, app) => result.map_err(serve_error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_listen_address() {
+        assert_eq!(listen_address(":9100"), "0.0.0.0:9100");
+        assert_eq!(listen_address("127.0.0.1:8080"), "127.0.0.1:8080");
+    }
+}
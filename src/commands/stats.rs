@@ -0,0 +1,457 @@
+use chrono::Utc;
+use std::cmp::Reverse;
+use std::time::Duration;
+
+use gitlabctl::environments::get_project;
+use gitlabctl::error::Error;
+use gitlabctl::stats::{self, format_size, FlakyJob, Storage};
+
+use crate::cli::{
+    ContributorsArgs, CoverageArgs, FailuresArgs, FlakyArgs, GlobalArgs, IssueStatsArgs,
+    MergeRequestStatsArgs, OutputFormat, StatsCommand, StorageArgs, StorageSort, TimeStatsArgs,
+};
+use crate::commands::{self, Failures};
+
+/// Formats seconds to the minute, as more precision makes columns long.
+pub fn format_seconds(seconds: Option<i64>) -> String {
+    match seconds {
+        Some(seconds) => {
+            let minutes = seconds.max(0) as u64 / 60 * 60;
+            humantime::format_duration(Duration::from_secs(minutes)).to_string()
+        }
+        None => "-".to_string(),
+    }
+}
+
+/// The start of a `--since` window.
+pub fn window_start(since: Duration) -> Result<chrono::DateTime<Utc>, Error> {
+    let window = chrono::Duration::from_std(since)
+        .map_err(|_| Error::Usage(format!("--since {:?} is too long", since)))?;
+    Ok(Utc::now() - window)
+}
+
+async fn flaky(global: &GlobalArgs, args: &FlakyArgs) -> Result<(), Error> {
+    let (gitlab, context) = commands::connect(global, None).await?;
+    let namespace = args
+        .namespace
+        .clone()
+        .or(context.namespace)
+        .unwrap_or_default();
+    let since = window_start(args.since)?;
+    let (fetched, failures) =
+        commands::for_each_project(&gitlab, &namespace, args.concurrency, |id| {
+            stats::pipeline_jobs(&gitlab, id, since, args.pipelines)
+        })
+        .await?;
+    let mut jobs: Vec<FlakyJob> = fetched
+        .iter()
+        .flat_map(|(project, pipelines)| stats::flaky_jobs(project, pipelines))
+        .collect();
+    jobs.sort_by_key(|job| Reverse(job.flaky));
+    jobs.truncate(args.top);
+
+    if args.output == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&jobs).unwrap());
+        return failures.finish();
+    }
+    if jobs.is_empty() {
+        println!("No flaky jobs");
+        return failures.finish();
+    }
+    let project_width = jobs
+        .iter()
+        .map(|j| j.project.len())
+        .chain(["PROJECT".len()])
+        .max()
+        .unwrap_or_default();
+    let job_width = jobs
+        .iter()
+        .map(|j| j.job.len())
+        .chain(["JOB".len()])
+        .max()
+        .unwrap_or_default();
+    println!(
+        "{:project_width$}  {:job_width$}  {:>5}  {:>9}",
+        "PROJECT", "JOB", "FLAKY", "PIPELINES"
+    );
+    for job in &jobs {
+        println!(
+            "{:project_width$}  {:job_width$}  {:>5}  {:>9}",
+            job.project, job.job, job.flaky, job.pipelines
+        );
+    }
+    failures.finish()
+}
+
+async fn coverage(global: &GlobalArgs, args: &CoverageArgs) -> Result<(), Error> {
+    let (gitlab, context) = commands::connect(global, None).await?;
+    let project = commands::project_or_pick(&gitlab, args.project.as_deref(), &context).await?;
+    let (_, project_id) = get_project(&gitlab, &project).await?;
+    let since = window_start(args.since)?;
+    let coverage = stats::coverage(
+        &gitlab,
+        project_id,
+        args.ref_.as_deref(),
+        since,
+        args.pipelines,
+    )
+    .await?;
+
+    if args.output == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&coverage).unwrap());
+        return Ok(());
+    }
+    if coverage.is_empty() {
+        println!("No pipelines of {} reported coverage", project);
+        return Ok(());
+    }
+    println!(
+        "{:16}  {:>10}  {:>8}  REF",
+        "CREATED", "PIPELINE", "COVERAGE"
+    );
+    for c in &coverage {
+        println!(
+            "{:16}  {:>10}  {:>7.2}%  {}",
+            c.created_at.format("%Y-%m-%d %H:%M"),
+            c.pipeline,
+            c.coverage,
+            c.ref_
+        );
+    }
+    let values: Vec<f64> = coverage.iter().map(|c| c.coverage).collect();
+    println!(
+        "{} {:.2}% to {:.2}%",
+        stats::sparkline(&values),
+        values[0],
+        values[values.len() - 1]
+    );
+    Ok(())
+}
+
+async fn merge_requests(global: &GlobalArgs, args: &MergeRequestStatsArgs) -> Result<(), Error> {
+    let (gitlab, context) = commands::connect(global, None).await?;
+    let namespace = args
+        .namespace
+        .clone()
+        .or(context.namespace)
+        .ok_or_else(|| Error::Usage("Merge request statistics need a --namespace".to_string()))?;
+    let since = window_start(args.since)?;
+    let activities =
+        stats::merge_request_activities(&gitlab, &namespace, since, args.concurrency.into())
+            .await?;
+    let stats = stats::merge_request_stats(&activities, args.top);
+
+    if args.output == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&stats).unwrap());
+        return Ok(());
+    }
+    println!(
+        "{} merge requests opened, {} of them merged",
+        stats.merge_requests, stats.merged
+    );
+    println!(
+        "Median time to first review: {}",
+        format_seconds(stats.time_to_first_review_seconds)
+    );
+    println!(
+        "Median time to merge:        {}",
+        format_seconds(stats.time_to_merge_seconds)
+    );
+    println!("\nFILES  MERGE REQUESTS");
+    for (size, count) in &stats.sizes {
+        println!("{:5}  {:>14}", size, count);
+    }
+    if !stats.reviewers.is_empty() {
+        let width = stats
+            .reviewers
+            .iter()
+            .map(|(name, _)| name.len())
+            .chain(["REVIEWER".len()])
+            .max()
+            .unwrap_or_default();
+        println!("\n{:width$}  {:>8}", "REVIEWER", "REVIEWED");
+        for (name, count) in &stats.reviewers {
+            println!("{:width$}  {:>8}", name, count);
+        }
+    }
+    Ok(())
+}
+
+async fn issues(global: &GlobalArgs, args: &IssueStatsArgs) -> Result<(), Error> {
+    let (gitlab, context) = commands::connect(global, None).await?;
+    let namespace = args
+        .namespace
+        .clone()
+        .or(context.namespace)
+        .ok_or_else(|| Error::Usage("Issue statistics need a --namespace".to_string()))?;
+    let issues = stats::milestone_issues(&gitlab, &namespace, &args.milestone).await?;
+    let days = stats::burndown(&issues, Utc::now().date_naive());
+
+    if args.output == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&days).unwrap());
+        return Ok(());
+    }
+    if days.is_empty() {
+        println!("No issues in milestone {}", args.milestone);
+        return Ok(());
+    }
+    println!(
+        "{:10}  {:>6}  {:>6}  {:>4}  {:>16}",
+        "DATE", "OPENED", "CLOSED", "OPEN", "REMAINING WEIGHT"
+    );
+    for day in &days {
+        println!(
+            "{:10}  {:>6}  {:>6}  {:>4}  {:>16}",
+            day.date,
+            day.opened,
+            day.closed,
+            day.opened - day.closed,
+            day.remaining_weight
+        );
+    }
+    Ok(())
+}
+
+async fn time(global: &GlobalArgs, args: &TimeStatsArgs) -> Result<(), Error> {
+    let (gitlab, context) = commands::connect(global, None).await?;
+    let namespace = args
+        .namespace
+        .clone()
+        .or(context.namespace)
+        .ok_or_else(|| Error::Usage("Time statistics need a --namespace".to_string()))?;
+    let issues = stats::milestone_time(&gitlab, &namespace, &args.milestone).await?;
+    let times = stats::time_spent(&issues);
+
+    if args.output == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&times).unwrap());
+        return Ok(());
+    }
+    if times.is_empty() {
+        println!("No issues in milestone {}", args.milestone);
+        return Ok(());
+    }
+    let name = |assignee: &str| {
+        if assignee.is_empty() {
+            "(unassigned)".to_string()
+        } else {
+            assignee.to_string()
+        }
+    };
+    let width = times
+        .iter()
+        .map(|t| name(&t.assignee).len())
+        .max()
+        .unwrap_or(0)
+        .max(8);
+    println!(
+        "{:width$}  {:>6}  {:>10}  {:>10}",
+        "ASSIGNEE",
+        "ISSUES",
+        "ESTIMATE",
+        "SPENT",
+        width = width
+    );
+    for time in &times {
+        println!(
+            "{:width$}  {:>6}  {:>10}  {:>10}",
+            name(&time.assignee),
+            time.issues,
+            format_seconds(Some(time.estimate)),
+            format_seconds(Some(time.spent)),
+            width = width
+        );
+    }
+    let estimate: i64 = times.iter().map(|t| t.estimate).sum();
+    let spent: i64 = times.iter().map(|t| t.spent).sum();
+    println!(
+        "{:width$}  {:>6}  {:>10}  {:>10}",
+        "TOTAL",
+        issues.len(),
+        format_seconds(Some(estimate)),
+        format_seconds(Some(spent)),
+        width = width
+    );
+    Ok(())
+}
+
+async fn contributors(global: &GlobalArgs, args: &ContributorsArgs) -> Result<(), Error> {
+    let (gitlab, context) = commands::connect(global, None).await?;
+    let since = window_start(args.since)?;
+    let (contributions, failures) = match &args.namespace {
+        Some(namespace) => {
+            commands::for_each_project(&gitlab, namespace, args.concurrency, |id| {
+                stats::contributions(&gitlab, id, since)
+            })
+            .await?
+        }
+        None => {
+            let project =
+                commands::project_or_pick(&gitlab, args.project.as_deref(), &context).await?;
+            let (name, id) = get_project(&gitlab, &project).await?;
+            let contributions = stats::contributions(&gitlab, id, since).await?;
+            (vec![(name, contributions)], Failures::default())
+        }
+    };
+    let (commits, merge_requests): (Vec<_>, Vec<_>) =
+        contributions.into_iter().map(|(_, c)| c).unzip();
+    let contributors = stats::contributors(
+        &commits.into_iter().flatten().collect::<Vec<_>>(),
+        &merge_requests.into_iter().flatten().collect::<Vec<_>>(),
+    );
+
+    if args.output == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&contributors).unwrap());
+        return failures.finish();
+    }
+    let width = contributors
+        .iter()
+        .map(|c| c.name.len())
+        .chain(["NAME".len()])
+        .max()
+        .unwrap_or_default();
+    println!(
+        "{:width$}  {:>7}  {:>14}  {:>7}",
+        "NAME", "COMMITS", "MERGE REQUESTS", "REVIEWS"
+    );
+    for c in &contributors {
+        println!(
+            "{:width$}  {:>7}  {:>14}  {:>7}",
+            c.name, c.commits, c.merge_requests, c.reviews
+        );
+    }
+    failures.finish()
+}
+
+async fn failures(global: &GlobalArgs, args: &FailuresArgs) -> Result<(), Error> {
+    let (gitlab, context) = commands::connect(global, None).await?;
+    let namespace = args
+        .namespace
+        .clone()
+        .or(context.namespace)
+        .unwrap_or_default();
+    let since = window_start(args.since)?;
+    let (jobs, failed) = commands::for_each_project(&gitlab, &namespace, args.concurrency, |id| {
+        stats::failed_jobs(&gitlab, id, since, args.jobs)
+    })
+    .await?;
+    let mut failures = stats::failures(&jobs);
+    failures.truncate(args.top);
+
+    if args.output == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&failures).unwrap());
+        return failed.finish();
+    }
+    if failures.is_empty() {
+        println!("No jobs failed");
+        return failed.finish();
+    }
+    let job_width = failures
+        .iter()
+        .map(|f| f.job.len())
+        .chain(["JOB".len()])
+        .max()
+        .unwrap_or_default();
+    let reason_width = failures
+        .iter()
+        .map(|f| f.reason.len())
+        .chain(["REASON".len()])
+        .max()
+        .unwrap_or_default();
+    println!(
+        "{:>5}  {:job_width$}  {:reason_width$}  {:>8}  LAST",
+        "COUNT", "JOB", "REASON", "PROJECTS"
+    );
+    for f in &failures {
+        println!(
+            "{:>5}  {:job_width$}  {:reason_width$}  {:>8}  {}",
+            f.count,
+            f.job,
+            f.reason,
+            f.projects.len(),
+            f.last.format("%Y-%m-%d %H:%M")
+        );
+    }
+    failed.finish()
+}
+
+fn sort_key(storage: &Storage, sort: StorageSort) -> u64 {
+    match sort {
+        StorageSort::Total => storage.storage_size,
+        StorageSort::Repository => storage.repository_size,
+        StorageSort::Artifacts => storage.job_artifacts_size,
+        StorageSort::Lfs => storage.lfs_objects_size,
+        StorageSort::Packages => storage.packages_size,
+        StorageSort::Registry => storage.container_registry_size,
+    }
+}
+
+async fn storage(global: &GlobalArgs, args: &StorageArgs) -> Result<(), Error> {
+    let (gitlab, context) = commands::connect(global, None).await?;
+    let namespace = args
+        .namespace
+        .clone()
+        .or(context.namespace)
+        .ok_or_else(|| Error::Usage("Storage statistics need a --namespace".to_string()))?;
+    let mut projects = stats::group_storage(&gitlab, &namespace).await?;
+    projects.sort_by_key(|(_, storage)| std::cmp::Reverse(sort_key(storage, args.sort_by)));
+    if let Some(top) = args.top {
+        projects.truncate(top);
+    }
+
+    if args.output == OutputFormat::Json {
+        let projects: Vec<_> = projects
+            .iter()
+            .map(|(project, storage)| serde_json::json!({ "project": project, "storage": storage }))
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&projects).unwrap());
+        return Ok(());
+    }
+    let width = projects
+        .iter()
+        .map(|(project, _)| project.len())
+        .chain(["PROJECT".len()])
+        .max()
+        .unwrap_or_default();
+    println!(
+        "{:width$}  {:>10}  {:>10}  {:>10}  {:>10}  {:>10}  {:>10}",
+        "PROJECT", "TOTAL", "REPOSITORY", "ARTIFACTS", "LFS", "PACKAGES", "REGISTRY"
+    );
+    for (project, s) in &projects {
+        println!(
+            "{:width$}  {:>10}  {:>10}  {:>10}  {:>10}  {:>10}  {:>10}",
+            project,
+            format_size(s.storage_size),
+            format_size(s.repository_size),
+            format_size(s.job_artifacts_size),
+            format_size(s.lfs_objects_size),
+            format_size(s.packages_size),
+            format_size(s.container_registry_size),
+        );
+    }
+    Ok(())
+}
+
+pub async fn run(global: &GlobalArgs, command: &StatsCommand) -> Result<(), Error> {
+    match command {
+        StatsCommand::Flaky(args) => flaky(global, args).await,
+        StatsCommand::Coverage(args) => coverage(global, args).await,
+        StatsCommand::Mrs(args) => merge_requests(global, args).await,
+        StatsCommand::Issues(args) => issues(global, args).await,
+        StatsCommand::Contributors(args) => contributors(global, args).await,
+        StatsCommand::Failures(args) => failures(global, args).await,
+        StatsCommand::Storage(args) => storage(global, args).await,
+        StatsCommand::Time(args) => time(global, args).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_seconds() {
+        assert_eq!(format_seconds(Some(2 * 3600 + 90)), "2h 1m");
+        assert_eq!(format_seconds(Some(-5)), "0s");
+        assert_eq!(format_seconds(None), "-");
+    }
+}
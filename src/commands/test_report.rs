@@ -0,0 +1,58 @@
+use gitlabctl::environments::get_project;
+use gitlabctl::error::Error;
+use gitlabctl::test_report::{self, newly_failing, FailedTest};
+
+use crate::cli::{GlobalArgs, OutputFormat, TestReportArgs};
+use crate::commands;
+
+fn print_failed(tests: &[FailedTest]) {
+    for test in tests {
+        println!(
+            "{} {} {} ({:.2}s)",
+            test.case.status.to_uppercase(),
+            test.suite,
+            if test.case.classname.is_empty() {
+                test.case.name.clone()
+            } else {
+                format!("{}.{}", test.case.classname, test.case.name)
+            },
+            test.case.execution_time,
+        );
+        if let Some(output) = test.case.system_output.as_deref() {
+            for line in output.lines() {
+                println!("    {}", line);
+            }
+        }
+    }
+}
+
+pub async fn run(global: &GlobalArgs, args: &TestReportArgs) -> Result<(), Error> {
+    let (gitlab, context) = commands::connect(global, None).await?;
+    let project = commands::project_or_pick(&gitlab, args.project.as_deref(), &context).await?;
+    let (_, project_id) = get_project(&gitlab, &project).await?;
+    let report = test_report::get(&gitlab, project_id, args.pipeline).await?;
+    let failed = match args.diff {
+        Some(base) => newly_failing(&report, &test_report::get(&gitlab, project_id, base).await?),
+        None => report.failed(),
+    };
+
+    if args.output == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&failed).unwrap());
+        return Ok(());
+    }
+    print_failed(&failed);
+    match args.diff {
+        Some(base) => println!(
+            "{} tests fail in pipeline {} that did not in {}",
+            failed.len(),
+            args.pipeline,
+            base
+        ),
+        None => println!(
+            "{} of {} tests failed",
+            report.failed_count + report.error_count,
+            report.total_count
+        ),
+    }
+    Ok(())
+}
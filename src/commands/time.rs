@@ -0,0 +1,82 @@
+use gitlab::api::AsyncQuery;
+use serde::Deserialize;
+
+use gitlabctl::endpoints::{AddSpentTime, SetTimeEstimate};
+use gitlabctl::error::{ApiError, Error};
+
+use crate::cli::{GlobalArgs, TimeArgs};
+use crate::commands;
+
+/// The tracked time, as GitLab formats it.
+#[derive(Deserialize)]
+struct TimeStats {
+    human_time_estimate: Option<String>,
+    human_total_time_spent: Option<String>,
+}
+
+impl TimeStats {
+    fn describe(&self) -> String {
+        let spent = self.human_total_time_spent.as_deref().unwrap_or("0m");
+        match &self.human_time_estimate {
+            Some(estimate) => format!("{} spent of {} estimated", spent, estimate),
+            None => format!("{} spent, nothing estimated", spent),
+        }
+    }
+}
+
+/// The issue or merge request of `args`, as `issues` or `merge_requests` with its IID and
+/// its reference like `#42`.
+fn issuable(args: &TimeArgs) -> (&'static str, u64, String) {
+    match (args.issue, args.mr) {
+        (Some(iid), _) => ("issues", iid, format!("#{}", iid)),
+        (None, Some(iid)) => ("merge_requests", iid, format!("!{}", iid)),
+        // clap requires one of them
+        (None, None) => unreachable!(),
+    }
+}
+
+pub async fn spend(global: &GlobalArgs, args: &TimeArgs) -> Result<(), Error> {
+    let (gitlab, context) = commands::connect(global, None).await?;
+    let project = commands::project_or_pick(&gitlab, args.project.as_deref(), &context).await?;
+    let (issuable, iid, reference) = issuable(args);
+    let endpoint = AddSpentTime {
+        project,
+        issuable,
+        iid,
+        duration: args.duration.clone(),
+    };
+    let time: TimeStats = endpoint
+        .query_async(&gitlab)
+        .await
+        .map_err(ApiError::from)?;
+    println!(
+        "Added {} to {}, {}",
+        args.duration,
+        reference,
+        time.describe()
+    );
+    Ok(())
+}
+
+pub async fn estimate(global: &GlobalArgs, args: &TimeArgs) -> Result<(), Error> {
+    let (gitlab, context) = commands::connect(global, None).await?;
+    let project = commands::project_or_pick(&gitlab, args.project.as_deref(), &context).await?;
+    let (issuable, iid, reference) = issuable(args);
+    let endpoint = SetTimeEstimate {
+        project,
+        issuable,
+        iid,
+        duration: args.duration.clone(),
+    };
+    let time: TimeStats = endpoint
+        .query_async(&gitlab)
+        .await
+        .map_err(ApiError::from)?;
+    println!(
+        "Estimated {} for {}, {}",
+        args.duration,
+        reference,
+        time.describe()
+    );
+    Ok(())
+}
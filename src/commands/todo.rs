@@ -0,0 +1,138 @@
+use colored::*;
+use gitlab::api::{self, AsyncQuery};
+use serde::{Deserialize, Serialize};
+
+use gitlabctl::endpoints::{MarkTodoDone, Todos};
+use gitlabctl::error::{ApiError, Error};
+use gitlabctl::pagination;
+
+use crate::cli::{GlobalArgs, OutputFormat, TodoCommand, TodoDoneArgs, TodoListArgs};
+use crate::commands;
+
+#[derive(Deserialize, Serialize)]
+struct TodoProject {
+    path_with_namespace: String,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Target {
+    iid: Option<u64>,
+    title: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Todo {
+    id: u64,
+    project: Option<TodoProject>,
+    /// E.g. `assigned`, `mentioned` or `review_requested`.
+    action_name: String,
+    /// E.g. `Issue` or `MergeRequest`.
+    target_type: String,
+    target: Option<Target>,
+    target_url: Option<String>,
+}
+
+impl Todo {
+    /// E.g. `group/app#42` or `group/app!7`.
+    fn reference(&self) -> String {
+        let project = self
+            .project
+            .as_ref()
+            .map_or("", |p| p.path_with_namespace.as_str());
+        let sigil = match self.target_type.as_str() {
+            "MergeRequest" => "!",
+            "Issue" => "#",
+            _ => " ",
+        };
+        match self.target.as_ref().and_then(|t| t.iid) {
+            Some(iid) => format!("{}{}{}", project, sigil, iid),
+            None => project.to_owned(),
+        }
+    }
+}
+
+async fn list(global: &GlobalArgs, args: &TodoListArgs) -> Result<(), Error> {
+    let (gitlab, _) = commands::connect(global, None).await?;
+    let todos: Vec<Todo> = pagination::list(&gitlab, &Todos, None, |_| ()).await?;
+    if args.output == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&todos).unwrap());
+        return Ok(());
+    }
+    if todos.is_empty() {
+        println!("Nothing to do");
+        return Ok(());
+    }
+    let references: Vec<String> = todos.iter().map(Todo::reference).collect();
+    let width = references.iter().map(String::len).max().unwrap_or(0);
+    for (todo, reference) in todos.iter().zip(&references) {
+        let title = todo
+            .target
+            .as_ref()
+            .and_then(|t| t.title.as_deref())
+            .unwrap_or_default();
+        println!(
+            "{:>8}  {}  {:16}  {}",
+            todo.id,
+            format!("{:width$}", reference, width = width).cyan(),
+            todo.action_name,
+            title
+        );
+    }
+    Ok(())
+}
+
+async fn done(global: &GlobalArgs, args: &TodoDoneArgs) -> Result<(), Error> {
+    let (gitlab, _) = commands::connect(global, None).await?;
+    if args.all {
+        let endpoint = MarkTodoDone { todo: None };
+        api::ignore(endpoint)
+            .query_async(&gitlab)
+            .await
+            .map_err(ApiError::from)?;
+        println!("Marked all to-do items as done");
+        return Ok(());
+    }
+    for id in &args.ids {
+        let endpoint = MarkTodoDone { todo: Some(*id) };
+        api::ignore(endpoint)
+            .query_async(&gitlab)
+            .await
+            .map_err(ApiError::from)?;
+        println!("Marked {} as done", id);
+    }
+    Ok(())
+}
+
+pub async fn run(global: &GlobalArgs, command: &TodoCommand) -> Result<(), Error> {
+    match command {
+        TodoCommand::List(args) => list(global, args).await,
+        TodoCommand::Done(args) => done(global, args).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reference() {
+        let mut todo = Todo {
+            id: 1,
+            project: Some(TodoProject {
+                path_with_namespace: "group/app".to_string(),
+            }),
+            action_name: "assigned".to_string(),
+            target_type: "MergeRequest".to_string(),
+            target: Some(Target {
+                iid: Some(7),
+                title: None,
+            }),
+            target_url: None,
+        };
+        assert_eq!(todo.reference(), "group/app!7");
+        todo.target_type = "Issue".to_string();
+        assert_eq!(todo.reference(), "group/app#7");
+        todo.target = None;
+        assert_eq!(todo.reference(), "group/app");
+    }
+}
@@ -0,0 +1,692 @@
+use chrono::{DateTime, Duration, Utc};
+use chrono_humanize::HumanTime;
+use crossterm::event::{Event, EventStream, KeyCode, KeyEvent, KeyEventKind};
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use futures::stream::StreamExt;
+use gitlab::api::projects::pipelines::{CancelPipeline, RetryPipeline};
+use gitlab::api::{self, AsyncQuery};
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Modifier, Style};
+use ratatui::widgets::{Block, Clear, Paragraph, Row, Table, TableState};
+use ratatui::{Frame, Terminal};
+use serde::Deserialize;
+use std::io::{self, Stdout};
+use tracing::warn;
+
+use gitlabctl::client::Client;
+use gitlabctl::config::Config;
+use gitlabctl::endpoints::{Pipelines, RetryJob, StopEnvironment};
+use gitlabctl::environments::{environment_url, get_namespace_rows, get_projects_for_namespace};
+use gitlabctl::error::{ApiError, Error, RenderError};
+use gitlabctl::pagination;
+use gitlabctl::table::EnvironmentRow;
+use gitlabctl::timings::Timings;
+use gitlabctl::types::ProjectId;
+
+use crate::cli::{GlobalArgs, UiArgs};
+use crate::commands;
+
+const HELP: &str =
+    "q quit  tab switch view  / filter  enter describe  o open  r refresh  R retry  s stop";
+
+/// How many of the pipelines of the last week the pipelines view shows per project.
+const PIPELINES_PER_PROJECT: usize = 20;
+
+/// What the main loop should do after a key press.
+#[derive(Debug, PartialEq, Eq)]
+enum Action {
+    Nothing,
+    Quit,
+    Refresh,
+    Open,
+    Retry,
+    Stop,
+}
+
+/// Which rows the dashboard shows, switched with `Tab`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum View {
+    #[default]
+    Environments,
+    Pipelines,
+}
+
+/// A row of the pipelines view.
+#[derive(Clone, Deserialize)]
+struct PipelineRow {
+    #[serde(skip)]
+    project_id: ProjectId,
+    #[serde(skip)]
+    project_name: String,
+    id: u64,
+    #[serde(rename = "ref")]
+    ref_: String,
+    status: String,
+    updated_at: DateTime<Utc>,
+    web_url: String,
+}
+
+/// The row under the selection, in either view.
+#[derive(Clone)]
+enum Selected {
+    Environment(Box<EnvironmentRow>),
+    Pipeline(PipelineRow),
+}
+
+impl Selected {
+    /// What `action` would change, to ask for a confirmation, or why there is nothing to do.
+    fn change(&self, action: &Action) -> Result<String, String> {
+        match (self, action) {
+            (Selected::Environment(row), Action::Retry) => match row.job_id {
+                Some(job) => Ok(format!("retry job {} of {}", job, row.project_name)),
+                None => Err(format!(
+                    "{} of {} has no job to retry",
+                    row.environment_name, row.project_name
+                )),
+            },
+            (Selected::Environment(row), _) => Ok(format!(
+                "stop {} of {}",
+                row.environment_name, row.project_name
+            )),
+            (Selected::Pipeline(row), Action::Retry) => {
+                Ok(format!("retry pipeline {} of {}", row.id, row.project_name))
+            }
+            (Selected::Pipeline(row), _) => Ok(format!(
+                "cancel pipeline {} of {}",
+                row.id, row.project_name
+            )),
+        }
+    }
+}
+
+/// The state of the dashboard.
+#[derive(Default)]
+struct App {
+    view: View,
+    rows: Vec<EnvironmentRow>,
+    pipelines: Vec<PipelineRow>,
+    /// The pipelines are only fetched once the pipelines view is shown the first time
+    pipelines_loaded: bool,
+    filter: String,
+    editing_filter: bool,
+    describing: bool,
+    /// The key that was pressed once, as retrying and stopping need a second press to confirm
+    confirming: Option<char>,
+    table: TableState,
+    status: String,
+}
+
+/// Whether any of `names` contains `filter`, ignoring case.
+fn any_contains(filter: &str, names: [&str; 2]) -> bool {
+    let filter = filter.to_lowercase();
+    names
+        .iter()
+        .any(|name| name.to_lowercase().contains(&filter))
+}
+
+impl App {
+    /// The environments whose project or environment contains the filter.
+    fn visible(&self) -> Vec<&EnvironmentRow> {
+        self.rows
+            .iter()
+            .filter(|row| any_contains(&self.filter, [&row.project_name, &row.environment_name]))
+            .collect()
+    }
+
+    /// The pipelines whose project or ref contains the filter.
+    fn visible_pipelines(&self) -> Vec<&PipelineRow> {
+        self.pipelines
+            .iter()
+            .filter(|row| any_contains(&self.filter, [&row.project_name, &row.ref_]))
+            .collect()
+    }
+
+    fn visible_len(&self) -> usize {
+        match self.view {
+            View::Environments => self.visible().len(),
+            View::Pipelines => self.visible_pipelines().len(),
+        }
+    }
+
+    fn selected(&self) -> Option<Selected> {
+        let selected = self.table.selected()?;
+        match self.view {
+            View::Environments => self
+                .visible()
+                .get(selected)
+                .map(|row| Selected::Environment(Box::new((*row).clone()))),
+            View::Pipelines => self
+                .visible_pipelines()
+                .get(selected)
+                .map(|row| Selected::Pipeline((*row).clone())),
+        }
+    }
+
+    fn set_rows(&mut self, rows: Vec<EnvironmentRow>) {
+        self.rows = rows;
+        self.clamp_selection();
+    }
+
+    fn set_pipelines(&mut self, pipelines: Vec<PipelineRow>) {
+        self.pipelines = pipelines;
+        self.pipelines_loaded = true;
+        self.clamp_selection();
+    }
+
+    /// Keeps the selection on a visible row after the rows or the filter changed.
+    fn clamp_selection(&mut self) {
+        let len = self.visible_len();
+        let selected = match self.table.selected() {
+            _ if len == 0 => None,
+            Some(selected) => Some(selected.min(len - 1)),
+            None => Some(0),
+        };
+        self.table.select(selected);
+    }
+
+    fn move_selection(&mut self, by: isize) {
+        let len = self.visible_len();
+        if len == 0 {
+            return;
+        }
+        let selected = self.table.selected().unwrap_or(0) as isize + by;
+        self.table
+            .select(Some(selected.clamp(0, len as isize - 1) as usize));
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> Action {
+        if self.editing_filter {
+            match key.code {
+                KeyCode::Enter | KeyCode::Esc => self.editing_filter = false,
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                }
+                KeyCode::Char(c) => self.filter.push(c),
+                _ => {}
+            }
+            self.clamp_selection();
+            return Action::Nothing;
+        }
+        let confirming = self.confirming.take();
+        if confirming.is_some() {
+            self.status.clear();
+        }
+        if self.describing {
+            self.describing = false;
+            return Action::Nothing;
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Action::Quit,
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::PageDown => self.move_selection(10),
+            KeyCode::PageUp => self.move_selection(-10),
+            KeyCode::Char('/') => self.editing_filter = true,
+            KeyCode::Enter | KeyCode::Char('d') => self.describing = self.selected().is_some(),
+            KeyCode::Char('o') => return Action::Open,
+            KeyCode::Char('r') => return Action::Refresh,
+            KeyCode::Tab => {
+                self.view = match self.view {
+                    View::Environments => View::Pipelines,
+                    View::Pipelines => View::Environments,
+                };
+                self.table.select(Some(0));
+                self.clamp_selection();
+                if self.view == View::Pipelines && !self.pipelines_loaded {
+                    return Action::Refresh;
+                }
+            }
+            KeyCode::Char(key @ ('R' | 's')) => {
+                let action = if key == 'R' {
+                    Action::Retry
+                } else {
+                    Action::Stop
+                };
+                if confirming == Some(key) {
+                    return action;
+                }
+                if let Some(selected) = self.selected() {
+                    match selected.change(&action) {
+                        Ok(change) => {
+                            self.status = format!("Press {} again to {}", key, change);
+                            self.confirming = Some(key);
+                        }
+                        Err(reason) => self.status = reason,
+                    }
+                }
+            }
+            _ => {}
+        }
+        Action::Nothing
+    }
+}
+
+fn draw(frame: &mut Frame, app: &mut App) {
+    let [main, footer] =
+        Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.size());
+    let (name, header, widths, rows): (_, _, _, Vec<Row>) = match app.view {
+        View::Environments => (
+            "environments",
+            vec!["PROJECT", "ENVIRONMENT", "DEPLOYMENT", "COMMIT", "UPDATED"],
+            vec![
+                Constraint::Percentage(30),
+                Constraint::Percentage(25),
+                Constraint::Percentage(20),
+                Constraint::Length(10),
+                Constraint::Min(15),
+            ],
+            app.visible()
+                .iter()
+                .map(|row| {
+                    Row::new(vec![
+                        row.project_name.clone(),
+                        row.environment_name.clone(),
+                        row.deployment_by.clone(),
+                        row.commit_sha.clone(),
+                        row.updated.clone(),
+                    ])
+                })
+                .collect(),
+        ),
+        View::Pipelines => (
+            "pipelines",
+            vec!["PROJECT", "PIPELINE", "REF", "STATUS", "UPDATED"],
+            vec![
+                Constraint::Percentage(30),
+                Constraint::Length(10),
+                Constraint::Percentage(25),
+                Constraint::Length(10),
+                Constraint::Min(15),
+            ],
+            app.visible_pipelines()
+                .iter()
+                .map(|row| {
+                    Row::new(vec![
+                        row.project_name.clone(),
+                        row.id.to_string(),
+                        row.ref_.clone(),
+                        row.status.clone(),
+                        HumanTime::from(row.updated_at).to_string(),
+                    ])
+                })
+                .collect(),
+        ),
+    };
+    let title = if app.filter.is_empty() {
+        format!(" {} ({}) ", name, rows.len())
+    } else {
+        format!(" {} ({}) /{} ", name, rows.len(), app.filter)
+    };
+    let description = app
+        .selected()
+        .filter(|_| app.describing)
+        .map(|selected| describe(&selected));
+    let table = Table::new(rows, widths)
+        .header(Row::new(header).style(Style::default().add_modifier(Modifier::BOLD)))
+        .block(Block::bordered().title(title))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(table, main, &mut app.table);
+
+    let status = if app.editing_filter {
+        format!("/{}", app.filter)
+    } else if app.status.is_empty() {
+        HELP.to_string()
+    } else {
+        app.status.clone()
+    };
+    frame.render_widget(Paragraph::new(status), footer);
+
+    if let Some(description) = description {
+        let area = centered(main, 60, 9);
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Paragraph::new(description).block(Block::bordered().title(" describe ")),
+            area,
+        );
+    }
+}
+
+fn describe(selected: &Selected) -> String {
+    match selected {
+        Selected::Environment(row) => format!(
+            "project:     {} ({})\nenvironment: {} ({})\ndeployment:  {}\ncommit:      {}\nupdated:     {}\njob:         {}",
+            row.project_name,
+            row.project_id,
+            row.environment_name,
+            row.environment_id,
+            row.deployment_by,
+            row.commit_sha,
+            row.updated,
+            row.job_id.map(|id| id.to_string()).unwrap_or_default()
+        ),
+        Selected::Pipeline(row) => format!(
+            "project:  {} ({})\npipeline: {}\nref:      {}\nstatus:   {}\nupdated:  {}\nurl:      {}",
+            row.project_name,
+            row.project_id,
+            row.id,
+            row.ref_,
+            row.status,
+            HumanTime::from(row.updated_at),
+            row.web_url
+        ),
+    }
+}
+
+/// A rectangle of at most `width` x `height` in the middle of `area`.
+fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+/// Runs the action on the selected row and returns what to show in the status line.
+async fn act(gitlab: &Client, action: &Action, selected: &Selected, read_only: bool) -> String {
+    let mutating = matches!(action, Action::Retry | Action::Stop);
+    if mutating && read_only {
+        return "Refusing to change anything in read-only mode".to_string();
+    }
+    let result = match (selected, action) {
+        (Selected::Environment(row), Action::Open) => open_in_browser(gitlab, row).await,
+        (Selected::Pipeline(row), Action::Open) => Ok(open_url(&row.web_url)),
+        (Selected::Environment(row), Action::Retry) => match row.job_id {
+            Some(job) => api::ignore(RetryJob {
+                project: row.project_id,
+                job,
+            })
+            .query_async(gitlab)
+            .await
+            .map(|_| format!("Retried job {} of {}", job, row.project_name))
+            .map_err(ApiError::from),
+            None => Ok(format!(
+                "{} of {} has no job to retry",
+                row.environment_name, row.project_name
+            )),
+        },
+        (Selected::Environment(row), Action::Stop) => api::ignore(StopEnvironment {
+            project: row.project_id,
+            environment: row.environment_id,
+        })
+        .query_async(gitlab)
+        .await
+        .map(|_| format!("Stopped {} of {}", row.environment_name, row.project_name))
+        .map_err(ApiError::from),
+        (Selected::Pipeline(row), Action::Retry) => match RetryPipeline::builder()
+            .project(row.project_id)
+            .pipeline(row.id)
+            .build()
+        {
+            Ok(endpoint) => api::ignore(endpoint)
+                .query_async(gitlab)
+                .await
+                .map(|_| format!("Retried pipeline {} of {}", row.id, row.project_name))
+                .map_err(ApiError::from),
+            Err(e) => Err(ApiError::builder(e)),
+        },
+        (Selected::Pipeline(row), Action::Stop) => match CancelPipeline::builder()
+            .project(row.project_id)
+            .pipeline(row.id)
+            .build()
+        {
+            Ok(endpoint) => api::ignore(endpoint)
+                .query_async(gitlab)
+                .await
+                .map(|_| format!("Canceled pipeline {} of {}", row.id, row.project_name))
+                .map_err(ApiError::from),
+            Err(e) => Err(ApiError::builder(e)),
+        },
+        _ => Ok(String::new()),
+    };
+    result.unwrap_or_else(|e| format!("Error: {}", e))
+}
+
+async fn open_in_browser(gitlab: &Client, row: &EnvironmentRow) -> Result<String, ApiError> {
+    let url = environment_url(gitlab, row.project_id, row.environment_id).await?;
+    Ok(open_url(&url))
+}
+
+fn open_url(url: &str) -> String {
+    match open::that(url) {
+        Ok(()) => format!("Opened {}", url),
+        Err(e) => format!("Could not open {}: {}", url, e),
+    }
+}
+
+/// The pipelines of the last week of the projects of `namespace`, newest first.
+async fn get_pipeline_rows(
+    gitlab: &Client,
+    namespace: &str,
+    concurrency: u16,
+) -> Result<Vec<PipelineRow>, ApiError> {
+    let timings = Timings::new(false);
+    let projects = get_projects_for_namespace(gitlab, namespace, None, &timings, |_| ()).await?;
+    let since = Utc::now() - Duration::days(7);
+    let fetched = commands::run_for_projects(projects, concurrency, |project| async move {
+        let endpoint = Pipelines {
+            project,
+            updated_after: since,
+            ref_: None,
+            status: None,
+        };
+        let pipelines: Vec<PipelineRow> =
+            pagination::list(gitlab, &endpoint, Some(PIPELINES_PER_PROJECT), |_| ()).await?;
+        Ok(pipelines
+            .into_iter()
+            .map(|row| PipelineRow {
+                project_id: project,
+                ..row
+            })
+            .collect::<Vec<_>>())
+    })
+    .await;
+    let mut rows = vec![];
+    for (name, result) in fetched {
+        match result {
+            Ok(pipelines) => rows.extend(pipelines.into_iter().map(|row| PipelineRow {
+                project_name: name.clone(),
+                ..row
+            })),
+            Err(e) => warn!("could not fetch the pipelines of {}: {}", name, e),
+        }
+    }
+    rows.sort_by_key(|row| std::cmp::Reverse(row.updated_at));
+    Ok(rows)
+}
+
+async fn event_loop(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    gitlab: &Client,
+    args: &UiArgs,
+    namespace: &str,
+    read_only: bool,
+) -> Result<(), Error> {
+    let mut app = App::default();
+    let mut events = EventStream::new();
+    let mut refresh = true;
+    loop {
+        if refresh {
+            app.status = "Loading…".to_string();
+            terminal
+                .draw(|frame| draw(frame, &mut app))
+                .map_err(RenderError::from)?;
+            // Keep showing the previous rows if refreshing fails
+            let loaded = match app.view {
+                View::Environments => {
+                    get_namespace_rows(gitlab, namespace, args.concurrency.into())
+                        .await
+                        .map(|rows| app.set_rows(rows))
+                }
+                View::Pipelines => get_pipeline_rows(gitlab, namespace, args.concurrency)
+                    .await
+                    .map(|pipelines| app.set_pipelines(pipelines)),
+            };
+            app.status = match loaded {
+                Ok(()) => String::new(),
+                Err(e) => format!("Error: {}", e),
+            };
+            refresh = false;
+        }
+        terminal
+            .draw(|frame| draw(frame, &mut app))
+            .map_err(RenderError::from)?;
+        let key = match events.next().await {
+            Some(Ok(Event::Key(key))) if key.kind == KeyEventKind::Press => key,
+            Some(Ok(_)) => continue,
+            Some(Err(e)) => return Err(RenderError::from(e).into()),
+            None => return Ok(()),
+        };
+        match app.handle_key(key) {
+            Action::Nothing => {}
+            Action::Quit => return Ok(()),
+            Action::Refresh => refresh = true,
+            action => {
+                if let Some(selected) = app.selected() {
+                    app.status = act(gitlab, &action, &selected, read_only).await;
+                }
+            }
+        }
+    }
+}
+
+pub async fn run(global: &GlobalArgs, args: &UiArgs) -> Result<(), Error> {
+    let read_only = global.read_only || Config::parse_from_disk()?.read_only;
+    let (gitlab, context) = commands::connect(global, None).await?;
+    let namespace = args
+        .namespace
+        .clone()
+        .or(context.namespace)
+        .unwrap_or_default();
+
+    enable_raw_mode().map_err(RenderError::from)?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen).map_err(RenderError::from)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout)).map_err(RenderError::from)?;
+    let result = event_loop(&mut terminal, &gitlab, args, &namespace, read_only).await;
+    // Give the terminal back even if the dashboard failed
+    disable_raw_mode().map_err(RenderError::from)?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen).map_err(RenderError::from)?;
+    terminal.show_cursor().map_err(RenderError::from)?;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyModifiers;
+
+    fn row(project: &str, environment: &str) -> EnvironmentRow {
+        EnvironmentRow {
+            instance: String::new(),
+            namespace: String::new(),
+            commit_title: String::new(),
+            project_id: 1,
+            environment_id: 2,
+            job_id: Some(3),
+            project_name: project.to_string(),
+            environment_name: environment.to_string(),
+            tier: String::new(),
+            deployment_by: "1 by bijancn".to_string(),
+            deployment_status: String::new(),
+            ref_: String::new(),
+            commit_sha: "63c3655f".to_string(),
+            pipeline_status: String::new(),
+            deployed_at: None,
+            updated: "now".to_string(),
+            external_url: String::new(),
+        }
+    }
+
+    fn pipeline(project: &str, ref_: &str) -> PipelineRow {
+        PipelineRow {
+            project_id: 1,
+            project_name: project.to_string(),
+            id: 4,
+            ref_: ref_.to_string(),
+            status: "failed".to_string(),
+            updated_at: Utc::now(),
+            web_url: String::new(),
+        }
+    }
+
+    fn press(app: &mut App, code: KeyCode) -> Action {
+        app.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    #[test]
+    fn test_filter_keeps_selection_visible() {
+        let mut app = App::default();
+        app.set_rows(vec![
+            row("service-a", "prod"),
+            row("service-b", "prod"),
+            row("service-b", "Review/x"),
+        ]);
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Down);
+        assert_eq!(app.table.selected(), Some(2));
+        press(&mut app, KeyCode::Char('/'));
+        for c in "review".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.visible().len(), 1);
+        assert!(matches!(
+            app.selected(),
+            Some(Selected::Environment(row)) if row.environment_name == "Review/x"
+        ));
+        assert_eq!(press(&mut app, KeyCode::Char('q')), Action::Quit);
+    }
+
+    #[test]
+    fn test_stop_needs_confirmation() {
+        let mut app = App::default();
+        app.set_rows(vec![row("service-a", "prod")]);
+        assert_eq!(press(&mut app, KeyCode::Char('s')), Action::Nothing);
+        assert_eq!(press(&mut app, KeyCode::Char('s')), Action::Stop);
+        press(&mut app, KeyCode::Char('s'));
+        press(&mut app, KeyCode::Down);
+        assert_eq!(press(&mut app, KeyCode::Char('s')), Action::Nothing);
+    }
+    #[test]
+    fn test_retry_needs_confirmation() {
+        let mut app = App::default();
+        app.set_rows(vec![row("service-a", "prod")]);
+        assert_eq!(press(&mut app, KeyCode::Char('R')), Action::Nothing);
+        assert_eq!(app.status, "Press R again to retry job 3 of service-a");
+        assert_eq!(press(&mut app, KeyCode::Char('s')), Action::Nothing);
+        assert_eq!(press(&mut app, KeyCode::Char('R')), Action::Nothing);
+        assert_eq!(press(&mut app, KeyCode::Char('R')), Action::Retry);
+    }
+
+    #[test]
+    fn test_pipelines_view() {
+        let mut app = App::default();
+        app.set_rows(vec![row("service-a", "prod")]);
+        assert_eq!(press(&mut app, KeyCode::Tab), Action::Refresh);
+        app.set_pipelines(vec![
+            pipeline("service-a", "main"),
+            pipeline("service-b", "main"),
+        ]);
+        press(&mut app, KeyCode::Down);
+        assert!(matches!(
+            app.selected(),
+            Some(Selected::Pipeline(row)) if row.project_name == "service-b"
+        ));
+        assert_eq!(press(&mut app, KeyCode::Char('s')), Action::Nothing);
+        assert_eq!(
+            app.status,
+            "Press s again to cancel pipeline 4 of service-b"
+        );
+        assert_eq!(press(&mut app, KeyCode::Char('s')), Action::Stop);
+        // The pipelines are not fetched again when switching back and forth
+        assert_eq!(press(&mut app, KeyCode::Tab), Action::Nothing);
+        assert_eq!(press(&mut app, KeyCode::Tab), Action::Nothing);
+    }
+}
@@ -0,0 +1,222 @@
+use gitlab::api::projects::pipelines;
+use gitlab::api::AsyncQuery;
+use serde::Deserialize;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use gitlabctl::client::Client;
+use gitlabctl::environments::{deployment_history, get_project};
+use gitlabctl::error::{ApiError, Error};
+use gitlabctl::types::ProjectId;
+
+use crate::cli::{Condition, GlobalArgs, WaitArgs, WaitResource};
+use crate::commands;
+
+/// The statuses that a deployment or pipeline does not leave again.
+pub const FINAL_STATUSES: [&str; 4] = ["success", "failed", "canceled", "skipped"];
+
+/// What a check of the resource found, with a description of its state.
+enum Check {
+    Met(String),
+    Pending(String),
+    /// The resource is in a state that will not meet the conditions anymore.
+    Unmet(String),
+}
+
+/// Whether the `fields` of a resource meet all `conditions`. Commits match by prefix, so
+/// short SHAs work.
+fn meets(conditions: &[Condition], fields: &[(&str, &str)]) -> bool {
+    conditions.iter().all(|condition| {
+        fields.iter().any(|(field, value)| {
+            *field == condition.field
+                && match *field {
+                    "sha" => value.starts_with(&condition.value),
+                    _ => *value == condition.value,
+                }
+        })
+    })
+}
+
+fn describe(conditions: &[Condition]) -> String {
+    let conditions: Vec<String> = conditions
+        .iter()
+        .map(|c| format!("{}={}", c.field, c.value))
+        .collect();
+    conditions.join(" ")
+}
+
+/// Runs `check` until it is met, unmet or `wait.timeout` passed, backing off between checks.
+async fn poll<F, Fut>(wait: &WaitArgs, condition: String, mut check: F) -> Result<(), Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<Check, Error>>,
+{
+    let start = Instant::now();
+    let mut interval = Duration::from_secs(2).min(wait.max_interval);
+    let mut last = String::new();
+    loop {
+        match check().await? {
+            Check::Met(state) => {
+                eprintln!("{}", state);
+                return Ok(());
+            }
+            Check::Unmet(state) => return Err(Error::Unmet(state)),
+            Check::Pending(state) => {
+                if state != last {
+                    eprintln!("{}", state);
+                    last = state;
+                }
+            }
+        }
+        let elapsed = start.elapsed();
+        if elapsed >= wait.timeout {
+            return Err(Error::Timeout {
+                condition,
+                timeout: humantime::format_duration(wait.timeout).to_string(),
+            });
+        }
+        tokio::time::sleep(interval.min(wait.timeout - elapsed)).await;
+        interval = (interval * 2).min(wait.max_interval);
+    }
+}
+
+async fn check_deployment(
+    gitlab: &Client,
+    project: ProjectId,
+    environment: &str,
+    conditions: &[Condition],
+) -> Result<Check, Error> {
+    let Some(deployment) = deployment_history(gitlab, project, environment, 1)
+        .await?
+        .into_iter()
+        .next()
+    else {
+        return Ok(Check::Pending(format!(
+            "{} was never deployed",
+            environment
+        )));
+    };
+    let fields = [
+        ("status", deployment.status.as_str()),
+        ("sha", deployment.sha.as_str()),
+    ];
+    let state = format!(
+        "Deployment #{} of {} to {} is {}",
+        deployment.iid, deployment.sha, environment, deployment.status
+    );
+    if meets(conditions, &fields) {
+        return Ok(Check::Met(state));
+    }
+    // Only the deployment asked for ending up in the wrong status is final, another commit
+    // may still be deployed
+    let others: Vec<Condition> = conditions
+        .iter()
+        .filter(|c| c.field != "status")
+        .cloned()
+        .collect();
+    if FINAL_STATUSES.contains(&fields[0].1) && !others.is_empty() && meets(&others, &fields) {
+        return Ok(Check::Unmet(state));
+    }
+    Ok(Check::Pending(state))
+}
+
+#[derive(Deserialize)]
+struct Pipeline {
+    status: String,
+    sha: String,
+}
+
+async fn check_pipeline(
+    gitlab: &Client,
+    project: &str,
+    id: u64,
+    conditions: &[Condition],
+) -> Result<Check, Error> {
+    let endpoint = pipelines::Pipeline::builder()
+        .project(project)
+        .pipeline(id)
+        .build()
+        .map_err(ApiError::builder)?;
+    let pipeline: Pipeline = endpoint.query_async(gitlab).await.map_err(ApiError::from)?;
+    let state = format!("Pipeline {} is {}", id, pipeline.status);
+    let fields = [
+        ("status", pipeline.status.as_str()),
+        ("sha", pipeline.sha.as_str()),
+    ];
+    if meets(conditions, &fields) {
+        Ok(Check::Met(state))
+    } else if FINAL_STATUSES.contains(&fields[0].1) {
+        Ok(Check::Unmet(state))
+    } else {
+        Ok(Check::Pending(state))
+    }
+}
+
+pub async fn run(global: &GlobalArgs, resource: &WaitResource) -> Result<(), Error> {
+    let (gitlab, context) = commands::connect(global, None).await?;
+    match resource {
+        WaitResource::Deployment {
+            target,
+            project,
+            conditions,
+            wait,
+        } => {
+            let (project_id, env) =
+                commands::find_environment(&gitlab, project.as_deref(), target).await?;
+            let mut conditions = conditions.clone();
+            if !conditions.iter().any(|c| c.field == "status") {
+                conditions.push(Condition {
+                    field: "status".to_owned(),
+                    value: "success".to_owned(),
+                });
+            }
+            let description = format!("{} to have {}", target, describe(&conditions));
+            poll(wait, description, || {
+                check_deployment(&gitlab, project_id, &env.name, &conditions)
+            })
+            .await
+        }
+        WaitResource::Pipeline {
+            id,
+            project,
+            conditions,
+            wait,
+        } => {
+            let project = commands::project_or_pick(&gitlab, project.as_deref(), &context).await?;
+            // Fail early on a project that does not exist
+            get_project(&gitlab, &project).await?;
+            let description = format!("pipeline {} to have {}", id, describe(conditions));
+            poll(wait, description, || {
+                check_pipeline(&gitlab, &project, *id, conditions)
+            })
+            .await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn condition(field: &str, value: &str) -> Condition {
+        Condition {
+            field: field.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_meets() {
+        let fields = [("status", "success"), ("sha", "abc123def")];
+        assert!(meets(&[condition("sha", "abc123")], &fields));
+        assert!(meets(
+            &[
+                condition("sha", "abc123def"),
+                condition("status", "success")
+            ],
+            &fields
+        ));
+        assert!(!meets(&[condition("status", "failed")], &fields));
+        assert!(!meets(&[condition("ref", "main")], &fields));
+    }
+}
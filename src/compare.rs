@@ -0,0 +1,128 @@
+use futures::stream::{self, StreamExt, TryStreamExt};
+use gitlab::api::AsyncQuery;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use crate::client::Client;
+use crate::endpoints::{CommitMergeRequests, Compare};
+use crate::error::ApiError;
+use crate::types::ProjectId;
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Commit {
+    pub id: String,
+    pub short_id: String,
+    pub title: String,
+    pub author_name: String,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct MergeRequest {
+    pub iid: u64,
+    pub title: String,
+    pub web_url: String,
+}
+
+#[derive(Deserialize)]
+struct Comparison {
+    commits: Vec<Commit>,
+}
+
+/// A commit between the refs with the merge requests that introduced it.
+#[derive(Serialize)]
+pub struct Change {
+    #[serde(flatten)]
+    pub commit: Commit,
+    pub merge_requests: Vec<MergeRequest>,
+}
+
+/// The commits from `from` to `to`, oldest first, with their merge requests.
+pub async fn changes(
+    gitlab: &Client,
+    project: ProjectId,
+    from: &str,
+    to: &str,
+) -> Result<Vec<Change>, ApiError> {
+    let endpoint = Compare {
+        project,
+        from: from.to_owned(),
+        to: to.to_owned(),
+    };
+    let comparison: Comparison = endpoint.query_async(gitlab).await?;
+    stream::iter(comparison.commits)
+        .map(|commit| async move {
+            let endpoint = CommitMergeRequests {
+                project,
+                sha: commit.id.clone(),
+            };
+            let merge_requests = endpoint.query_async(gitlab).await?;
+            Ok::<_, ApiError>(Change {
+                commit,
+                merge_requests,
+            })
+        })
+        .buffered(8)
+        .try_collect()
+        .await
+}
+
+/// Release notes in markdown that list every merge request once, and the commits that were
+/// pushed without one.
+pub fn release_notes(from: &str, to: &str, changes: &[Change]) -> String {
+    let mut notes = format!("## Changes from {} to {}\n\n", from, to);
+    let mut listed = BTreeSet::new();
+    for change in changes {
+        if change.merge_requests.is_empty() {
+            writeln!(
+                notes,
+                "- {} ({})",
+                change.commit.title, change.commit.short_id
+            )
+            .unwrap();
+        }
+        for mr in &change.merge_requests {
+            if listed.insert(mr.iid) {
+                writeln!(notes, "- {} ([!{}]({}))", mr.title, mr.iid, mr.web_url).unwrap();
+            }
+        }
+    }
+    notes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(short_id: &str, title: &str, merge_requests: Vec<MergeRequest>) -> Change {
+        Change {
+            commit: Commit {
+                id: short_id.repeat(5),
+                short_id: short_id.to_string(),
+                title: title.to_string(),
+                author_name: "Alice".to_string(),
+            },
+            merge_requests,
+        }
+    }
+
+    #[test]
+    fn test_release_notes() {
+        let mr = MergeRequest {
+            iid: 7,
+            title: "Add login".to_string(),
+            web_url: "https://gitlab.com/g/app/-/merge_requests/7".to_string(),
+        };
+        let changes = vec![
+            change("aaaa", "Add form", vec![mr.clone()]),
+            change("bbbb", "Add button", vec![mr]),
+            change("cccc", "Fix typo", vec![]),
+        ];
+        assert_eq!(
+            release_notes("v1", "v2", &changes),
+            "## Changes from v1 to v2\n\n\
+             - Add login ([!7](https://gitlab.com/g/app/-/merge_requests/7))\n\
+             - Fix typo (cccc)\n"
+        );
+    }
+}
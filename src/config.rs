@@ -1,21 +1,450 @@
 use dirs::home_dir;
-use serde::Deserialize;
-use std::fs;
+use gitlab::api::{users, Query};
+use gitlab::Gitlab;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, DirBuilder, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
 
-#[derive(Deserialize)]
+use crate::error::ConfigError;
+use crate::git;
+use crate::notify::Notify;
+use crate::types::User;
+
+/// The connection to one GitLab instance.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Context {
+    pub server: String,
+    pub access_token: String,
+    /// The namespace/group used when `--namespace` is not given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+}
+
+/// How requests that failed with a server or connection error are retried.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// How often a request is sent at most, including the first attempt.
+    pub attempts: u32,
+    /// The wait before the first retry, doubled for every further retry.
+    pub backoff_ms: u64,
+    /// The fraction of the wait that is randomly added, so that concurrent retries spread out.
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy {
+            attempts: 3,
+            backoff_ms: 500,
+            jitter: 0.5,
+        }
+    }
+}
+
+impl RetryPolicy {
+    fn is_default(&self) -> bool {
+        *self == RetryPolicy::default()
+    }
+}
+
+#[derive(Default, Deserialize, Serialize)]
 pub struct Config {
+    /// The connection used when no context is selected, kept at the top level of the file
+    /// so that configs written before contexts existed keep working.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub server: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub access_token: String,
+    /// The namespace/group used when `--namespace` is not given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+    /// Refuse to run any command that modifies data on the server.
+    #[serde(default)]
+    pub read_only: bool,
+    /// The context used when `--context` is not given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current_context: Option<String>,
+    #[serde(default, skip_serializing_if = "RetryPolicy::is_default")]
+    pub retry: RetryPolicy,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub contexts: BTreeMap<String, Context>,
+    /// The chat webhook that `--notify` posts to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notify: Option<Notify>,
+}
+
+fn config_path() -> Result<PathBuf, ConfigError> {
+    Ok(home_dir()
+        .ok_or(ConfigError::NoHomeDir)?
+        .join(".config/gitlab.toml"))
+}
+
+/// Asks `question` on the terminal and returns the trimmed answer or `default` if it is empty.
+fn prompt(question: &str, default: Option<&str>) -> Result<String, ConfigError> {
+    match default {
+        Some(default) => print!("{} [{}]: ", question, default),
+        None => print!("{}: ", question),
+    }
+    io::stdout().flush().map_err(ConfigError::Terminal)?;
+    let mut answer = String::new();
+    io::stdin()
+        .read_line(&mut answer)
+        .map_err(ConfigError::Terminal)?;
+    let answer = answer.trim();
+    Ok(if answer.is_empty() {
+        default.unwrap_or_default().to_owned()
+    } else {
+        answer.to_owned()
+    })
+}
+
+/// Strips the scheme and trailing slashes, as `Gitlab::new` expects a plain host.
+pub fn normalize_server(server: &str) -> String {
+    server
+        .trim_start_matches("https://")
+        .trim_start_matches("http://")
+        .trim_end_matches('/')
+        .to_owned()
+}
+
+/// Something wrong with a config that parses, at `key` of `table`.
+#[derive(Debug, PartialEq)]
+pub struct Problem {
+    /// E.g. `contexts.work`, or `None` at the top level of the file.
+    pub table: Option<String>,
+    pub key: &'static str,
+    pub message: String,
+}
+
+impl Problem {
+    /// The path of the field, e.g. `contexts.work.server`.
+    pub fn field(&self) -> String {
+        match &self.table {
+            Some(table) => format!("{}.{}", table, self.key),
+            None => self.key.to_owned(),
+        }
+    }
+
+    /// The line of the field in the config `text`, counted from 1, if the field is there.
+    pub fn line(&self, text: &str) -> Option<usize> {
+        let mut table: Option<String> = None;
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if let Some(header) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                table = Some(header.replace('"', ""));
+            } else if table == self.table
+                && line
+                    .strip_prefix(self.key)
+                    .is_some_and(|rest| rest.trim_start().starts_with('='))
+            {
+                return Some(number + 1);
+            }
+        }
+        None
+    }
+}
+
+/// What is wrong with the `server` of a context, if anything.
+fn server_problem(server: &str) -> Option<String> {
+    if server.is_empty() {
+        return Some("No server is set".to_string());
+    }
+    if server.starts_with("https://") {
+        return Some("Leave out `https://`, e.g. `gitlab.com`".to_string());
+    }
+    let host = server.strip_prefix("http://").unwrap_or(server);
+    match url::Url::parse(&format!("https://{}/api/v4/", host)) {
+        Ok(url) if url.host_str().is_some() && !host.contains(char::is_whitespace) => None,
+        _ => Some(format!("`{}` is not a host like `gitlab.com`", server)),
+    }
+}
+
+/// What is wrong with the `access_token` of a context, if anything.
+fn access_token_problem(access_token: &str) -> Option<String> {
+    if access_token.is_empty() {
+        Some("No access token is set".to_string())
+    } else if access_token.trim() != access_token {
+        Some("The access token has whitespace around it".to_string())
+    } else {
+        None
+    }
 }
 
 impl Config {
-    pub fn parse_from_disk() -> Config {
-        let config_path = home_dir()
-            .expect("Could not find home dir")
-            .join(".config/gitlab.toml");
-        let config_string = fs::read_to_string(&config_path)
-            .unwrap_or_else(|_| panic!("Something went wrong reading the file {:?}", &config_path));
-
-        toml::from_str(&config_string).expect("Could not parse the config")
+    /// The path and the contents of the config file.
+    pub fn read_from_disk() -> Result<(PathBuf, String), ConfigError> {
+        let config_path = config_path()?;
+        if !config_path.exists() {
+            return Err(ConfigError::Missing(config_path));
+        }
+        let config_string =
+            fs::read_to_string(&config_path).map_err(|source| ConfigError::Read {
+                path: config_path.clone(),
+                source,
+            })?;
+        Ok((config_path, config_string))
+    }
+
+    pub fn parse_from_disk() -> Result<Config, ConfigError> {
+        let (_, config_string) = Config::read_from_disk()?;
+        Ok(toml::from_str(&config_string)?)
+    }
+
+    /// All contexts by name, with `None` for the connection at the top level of the file.
+    pub fn all_contexts(&self) -> Vec<(Option<&str>, Context)> {
+        self.top_level_context()
+            .map(|context| (None, context))
+            .into_iter()
+            .chain(
+                self.contexts
+                    .iter()
+                    .map(|(name, context)| (Some(name.as_str()), context.clone())),
+            )
+            .collect()
+    }
+
+    /// Everything that would make commands fail later, without connecting to the servers.
+    pub fn problems(&self) -> Vec<Problem> {
+        let mut problems = vec![];
+        let problem = |table: Option<String>, key, message| Problem {
+            table,
+            key,
+            message,
+        };
+        // Only a token at the top level is probably a server that was forgotten
+        if self.server.is_empty() && !self.access_token.is_empty() {
+            problems.push(problem(None, "server", "No server is set".to_string()));
+        }
+        for (name, context) in self.all_contexts() {
+            let table = name.map(|name| format!("contexts.{}", name));
+            if let Some(message) = server_problem(&context.server) {
+                problems.push(problem(table.clone(), "server", message));
+            }
+            if let Some(message) = access_token_problem(&context.access_token) {
+                problems.push(problem(table, "access_token", message));
+            }
+        }
+        if let Some(name) = &self.current_context {
+            if !self.contexts.contains_key(name) {
+                problems.push(problem(
+                    None,
+                    "current_context",
+                    format!("There is no context `{}`", name),
+                ));
+            }
+        }
+        if self.server.is_empty() && self.current_context.is_none() && !self.contexts.is_empty() {
+            problems.push(problem(
+                None,
+                "current_context",
+                "Neither a server nor a current context is set, so every command needs --context"
+                    .to_string(),
+            ));
+        }
+        if self.retry.attempts == 0 {
+            problems.push(problem(
+                Some("retry".to_string()),
+                "attempts",
+                "At least one attempt is needed to send any request".to_string(),
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.retry.jitter) {
+            problems.push(problem(
+                Some("retry".to_string()),
+                "jitter",
+                "The jitter is a fraction between 0 and 1".to_string(),
+            ));
+        }
+        problems
+    }
+
+    /// Like `parse_from_disk` but starts from an empty config if there is no file yet.
+    pub fn parse_from_disk_or_default() -> Result<Config, ConfigError> {
+        if config_path()?.exists() {
+            Config::parse_from_disk()
+        } else {
+            Ok(Config::default())
+        }
+    }
+
+    /// Resolves the context `name`, falling back to `current_context` and then to the
+    /// connection given at the top level of the file.
+    pub fn context(&self, name: Option<&str>) -> Result<Context, ConfigError> {
+        match name.or(self.current_context.as_deref()) {
+            Some(name) => self
+                .contexts
+                .get(name)
+                .cloned()
+                .ok_or_else(|| ConfigError::UnknownContext(name.to_owned())),
+            None => self.top_level_context().ok_or(ConfigError::NoServer),
+        }
+    }
+
+    fn top_level_context(&self) -> Option<Context> {
+        Some(Context {
+            server: self.server.clone(),
+            access_token: self.access_token.clone(),
+            namespace: self.namespace.clone(),
+        })
+        .filter(|_| !self.server.is_empty())
+    }
+
+    /// The context whose server is `host`, preferring the one given at the top level.
+    pub fn context_for_host(&self, host: &str) -> Option<Context> {
+        self.top_level_context()
+            .into_iter()
+            .chain(self.contexts.values().cloned())
+            .find(|context| git::host(&context.server) == host)
+    }
+
+    pub fn write_to_disk(&self) -> Result<PathBuf, ConfigError> {
+        let config_path = config_path()?;
+        let write_error = |source| ConfigError::Write {
+            path: config_path.clone(),
+            source,
+        };
+        let config_string = toml::to_string(self)?;
+        // The config holds access tokens, so only the current user may read it.
+        let mut builder = DirBuilder::new();
+        builder.recursive(true);
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+            builder.mode(0o700);
+            options.mode(0o600);
+        }
+        if let Some(parent) = config_path.parent() {
+            builder.create(parent).map_err(write_error)?;
+        }
+        let mut file = options.open(&config_path).map_err(write_error)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            // The mode only applies to new files, so tighten an existing one too.
+            file.set_permissions(fs::Permissions::from_mode(0o600))
+                .map_err(write_error)?;
+        }
+        file.write_all(config_string.as_bytes())
+            .map_err(write_error)?;
+        Ok(config_path)
+    }
+
+    /// Interactively asks for the connection details, checks that they work and writes the
+    /// config. This blocks on the terminal and the network.
+    pub fn init_interactively() -> Result<PathBuf, ConfigError> {
+        let server = normalize_server(&prompt("GitLab server", Some("gitlab.com"))?);
+        let access_token = rpassword::read_password_from_tty(Some("Access token: "))
+            .map_err(ConfigError::Terminal)?
+            .trim()
+            .to_owned();
+        if access_token.is_empty() {
+            return Err(ConfigError::NoAccessToken);
+        }
+        let namespace = prompt("Default namespace/group (optional)", None)?;
+
+        println!("Checking connection to {}", server);
+        let connect_error = |message: String| ConfigError::Connect {
+            server: server.clone(),
+            message,
+        };
+        let gitlab =
+            Gitlab::new(&server, &access_token).map_err(|e| connect_error(e.to_string()))?;
+        let user: User = users::CurrentUser::builder()
+            .build()
+            .map_err(|e| connect_error(e.to_string()))?
+            .query(&gitlab)
+            .map_err(|e| connect_error(format!("Could not fetch the current user: {}", e)))?;
+        println!("Authenticated as {}", user.username);
+
+        let mut config = Config::parse_from_disk_or_default()?;
+        config.server = server;
+        config.access_token = access_token;
+        config.namespace = Some(namespace).filter(|ns| !ns.is_empty());
+        config.write_to_disk()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_with_context() -> Config {
+        let mut config = Config {
+            server: "gitlab.com".to_string(),
+            access_token: "top-level".to_string(),
+            ..Config::default()
+        };
+        config.contexts.insert(
+            "work".to_string(),
+            Context {
+                server: "gitlab.example.org".to_string(),
+                access_token: "work-token".to_string(),
+                namespace: None,
+            },
+        );
+        config
+    }
+
+    #[test]
+    fn test_context_resolution() {
+        let mut config = config_with_context();
+        assert_eq!(config.context(None).unwrap().server, "gitlab.com");
+        assert_eq!(
+            config.context(Some("work")).unwrap().server,
+            "gitlab.example.org"
+        );
+        assert!(config.context(Some("missing")).is_err());
+        config.current_context = Some("work".to_string());
+        assert_eq!(config.context(None).unwrap().access_token, "work-token");
+    }
+
+    #[test]
+    fn test_context_for_host() {
+        let config = config_with_context();
+        assert_eq!(
+            config
+                .context_for_host("gitlab.example.org")
+                .unwrap()
+                .access_token,
+            "work-token"
+        );
+        assert_eq!(
+            config.context_for_host("gitlab.com").unwrap().access_token,
+            "top-level"
+        );
+        assert!(config.context_for_host("github.com").is_none());
+    }
+
+    #[test]
+    fn test_problems() {
+        let text = "current_context = \"home\"\n\n[contexts.work]\nserver = \"https://gitlab.example.org\"\naccess_token = \"\"\n";
+        let config: Config = toml::from_str(text).unwrap();
+        let problems = config.problems();
+        let found: Vec<(String, Option<usize>)> = problems
+            .iter()
+            .map(|problem| (problem.field(), problem.line(text)))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("contexts.work.server".to_string(), Some(4)),
+                ("contexts.work.access_token".to_string(), Some(5)),
+                ("current_context".to_string(), Some(1)),
+            ]
+        );
+        assert!(config_with_context().problems().is_empty());
+        assert_eq!(server_problem("http://localhost:8080"), None);
+        assert!(server_problem("gitlab .com").is_some());
+    }
+
+    #[test]
+    fn test_normalize_server() {
+        assert_eq!(normalize_server("https://gitlab.com/"), "gitlab.com");
+        assert_eq!(normalize_server("gitlab.example.org"), "gitlab.example.org");
     }
 }
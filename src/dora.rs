@@ -0,0 +1,185 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::client::Client;
+use crate::endpoints::{Deployments, Incidents};
+use crate::error::ApiError;
+use crate::pagination;
+use crate::stats::median;
+use crate::types::ProjectId;
+
+#[derive(Deserialize)]
+struct DeploymentCommit {
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+struct Deployable {
+    commit: DeploymentCommit,
+}
+
+#[derive(Deserialize)]
+struct DeploymentEnvironment {
+    name: String,
+}
+
+#[derive(Deserialize)]
+pub struct Deployment {
+    status: String,
+    created_at: DateTime<Utc>,
+    environment: DeploymentEnvironment,
+    deployable: Option<Deployable>,
+}
+
+#[derive(Deserialize)]
+pub struct Incident {
+    created_at: DateTime<Utc>,
+    closed_at: Option<DateTime<Utc>>,
+}
+
+/// The four DORA metrics of one project or group, `None` where there is no data for them.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct Dora {
+    pub deployments: usize,
+    /// Successful deployments per day.
+    pub deployment_frequency: f64,
+    /// The median time from a commit to its successful deployment, in seconds.
+    pub lead_time_seconds: Option<i64>,
+    /// The fraction of deployments that failed.
+    pub change_failure_rate: Option<f64>,
+    /// The mean time to close an incident, in seconds.
+    pub mttr_seconds: Option<i64>,
+}
+
+fn mean(values: &[i64]) -> Option<i64> {
+    if values.is_empty() {
+        None
+    } else {
+        Some(values.iter().sum::<i64>() / values.len() as i64)
+    }
+}
+
+/// Computes the metrics from the deployments to the `environments` and the incidents opened
+/// within the `days` of the window.
+pub fn compute(
+    deployments: &[Deployment],
+    incidents: &[Incident],
+    environments: &[String],
+    days: f64,
+) -> Dora {
+    let production: Vec<&Deployment> = deployments
+        .iter()
+        .filter(|d| environments.contains(&d.environment.name))
+        .collect();
+    let succeeded: Vec<&&Deployment> = production
+        .iter()
+        .filter(|d| d.status == "success")
+        .collect();
+    let failed = production.iter().filter(|d| d.status == "failed").count();
+    let finished = succeeded.len() + failed;
+    let lead_times = succeeded
+        .iter()
+        .filter_map(|d| {
+            Some((d.created_at - d.deployable.as_ref()?.commit.created_at).num_seconds())
+        })
+        .collect();
+    let repair_times: Vec<i64> = incidents
+        .iter()
+        .filter_map(|i| Some((i.closed_at? - i.created_at).num_seconds()))
+        .collect();
+    Dora {
+        deployments: succeeded.len(),
+        deployment_frequency: succeeded.len() as f64 / days.max(1.0),
+        lead_time_seconds: median(lead_times),
+        change_failure_rate: Some(failed as f64 / finished as f64).filter(|_| finished > 0),
+        mttr_seconds: mean(&repair_times),
+    }
+}
+
+/// Fetches the deployments and incidents of `project` since `since`.
+pub async fn fetch(
+    gitlab: &Client,
+    project: ProjectId,
+    since: DateTime<Utc>,
+) -> Result<(Vec<Deployment>, Vec<Incident>), ApiError> {
+    let deployments = Deployments {
+        project,
+        updated_after: since,
+    };
+    let incidents = Incidents {
+        project,
+        created_after: since,
+    };
+    let deployments: Vec<Deployment> = pagination::list(gitlab, &deployments, None, |_| ()).await?;
+    // Deployments that were only updated in the window may have been created before
+    let deployments = deployments
+        .into_iter()
+        .filter(|d| d.created_at >= since)
+        .collect();
+    Ok((
+        deployments,
+        pagination::list(gitlab, &incidents, None, |_| ()).await?,
+    ))
+}
+
+/// The number of days in the window, for the deployment frequency.
+pub fn days(window: Duration) -> f64 {
+    window.num_seconds() as f64 / 86400.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deployment(status: &str, environment: &str, lead_time_hours: i64) -> Deployment {
+        let created_at = Utc::now();
+        Deployment {
+            status: status.to_string(),
+            created_at,
+            environment: DeploymentEnvironment {
+                name: environment.to_string(),
+            },
+            deployable: Some(Deployable {
+                commit: DeploymentCommit {
+                    created_at: created_at - Duration::hours(lead_time_hours),
+                },
+            }),
+        }
+    }
+
+    #[test]
+    fn test_compute() {
+        let deployments = vec![
+            deployment("success", "production", 1),
+            deployment("success", "production", 3),
+            deployment("success", "production", 2),
+            deployment("failed", "production", 0),
+            deployment("success", "staging", 100),
+        ];
+        let now = Utc::now();
+        let incidents = vec![
+            Incident {
+                created_at: now - Duration::hours(2),
+                closed_at: Some(now),
+            },
+            Incident {
+                created_at: now,
+                closed_at: None,
+            },
+        ];
+        let dora = compute(&deployments, &incidents, &["production".to_string()], 3.0);
+        assert_eq!(dora.deployments, 3);
+        assert_eq!(dora.deployment_frequency, 1.0);
+        assert_eq!(dora.lead_time_seconds, Some(2 * 3600));
+        assert_eq!(dora.change_failure_rate, Some(0.25));
+        assert_eq!(dora.mttr_seconds, Some(2 * 3600));
+    }
+
+    #[test]
+    fn test_no_data() {
+        let dora = compute(&[], &[], &["production".to_string()], 90.0);
+        assert_eq!(dora.lead_time_seconds, None);
+        assert_eq!(dora.change_failure_rate, None);
+        assert_eq!(dora.mttr_seconds, None);
+    }
+}
@@ -0,0 +1,1311 @@
+use chrono::{DateTime, Utc};
+use gitlab::api::{BodyError, Endpoint, FormParams, Pageable, QueryParams};
+use http::Method;
+use std::borrow::Cow;
+
+use crate::types::{Deployer, ProjectId};
+
+/// Escapes a group or project path to use it in place of an ID.
+fn escaped(path: &str) -> String {
+    url::form_urlencoded::byte_serialize(path.as_bytes()).collect()
+}
+
+/// The version of the GitLab instance.
+pub struct Version;
+
+impl Endpoint for Version {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        "version".into()
+    }
+}
+
+/// Stops an environment, running its stop action if it has one.
+pub struct StopEnvironment {
+    pub project: ProjectId,
+    pub environment: u64,
+}
+
+impl Endpoint for StopEnvironment {
+    fn method(&self) -> Method {
+        Method::POST
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!(
+            "projects/{}/environments/{}/stop",
+            self.project, self.environment
+        )
+        .into()
+    }
+}
+
+/// Runs a job again.
+pub struct RetryJob {
+    pub project: ProjectId,
+    pub job: u64,
+}
+
+impl Endpoint for RetryJob {
+    fn method(&self) -> Method {
+        Method::POST
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!("projects/{}/jobs/{}/retry", self.project, self.job).into()
+    }
+}
+
+/// Starts a manual job.
+pub struct PlayJob {
+    pub project: ProjectId,
+    pub job: u64,
+}
+
+impl Endpoint for PlayJob {
+    fn method(&self) -> Method {
+        Method::POST
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!("projects/{}/jobs/{}/play", self.project, self.job).into()
+    }
+}
+
+/// The log of a job so far, as plain text.
+pub struct JobTrace {
+    pub project: ProjectId,
+    pub job: u64,
+}
+
+impl Endpoint for JobTrace {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!("projects/{}/jobs/{}/trace", self.project, self.job).into()
+    }
+}
+
+/// Deletes a stopped environment.
+pub struct DeleteEnvironment {
+    pub project: ProjectId,
+    pub environment: u64,
+}
+
+impl Endpoint for DeleteEnvironment {
+    fn method(&self) -> Method {
+        Method::DELETE
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!(
+            "projects/{}/environments/{}",
+            self.project, self.environment
+        )
+        .into()
+    }
+}
+
+/// The deployments of a project that were updated since `updated_after`.
+pub struct Deployments {
+    pub project: ProjectId,
+    pub updated_after: DateTime<Utc>,
+}
+
+impl Endpoint for Deployments {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!("projects/{}/deployments", self.project).into()
+    }
+
+    fn parameters(&self) -> QueryParams<'_> {
+        let mut params = QueryParams::default();
+        params
+            .push("updated_after", self.updated_after)
+            .push("order_by", "updated_at");
+        params
+    }
+}
+
+impl Pageable for Deployments {}
+
+/// The incidents of a project that were opened since `created_after`.
+pub struct Incidents {
+    pub project: ProjectId,
+    pub created_after: DateTime<Utc>,
+}
+
+impl Endpoint for Incidents {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!("projects/{}/issues", self.project).into()
+    }
+
+    fn parameters(&self) -> QueryParams<'_> {
+        let mut params = QueryParams::default();
+        params
+            .push("issue_type", "incident")
+            .push("created_after", self.created_after);
+        params
+    }
+}
+
+impl Pageable for Incidents {}
+
+/// The pipelines of a project that were updated since `updated_after`, newest first, of
+/// all refs and statuses unless `ref_` or `status` is given.
+pub struct Pipelines {
+    pub project: ProjectId,
+    pub updated_after: DateTime<Utc>,
+    pub ref_: Option<String>,
+    pub status: Option<&'static str>,
+}
+
+impl Endpoint for Pipelines {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!("projects/{}/pipelines", self.project).into()
+    }
+
+    fn parameters(&self) -> QueryParams<'_> {
+        let mut params = QueryParams::default();
+        params
+            .push("updated_after", self.updated_after)
+            .push_opt("ref", self.ref_.as_deref())
+            .push_opt("status", self.status);
+        params
+    }
+}
+
+impl Pageable for Pipelines {}
+
+/// Validates the CI configuration of a ref and expands its includes.
+pub struct CiLint {
+    pub project: String,
+    pub content_ref: String,
+}
+
+impl Endpoint for CiLint {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!("projects/{}/ci/lint", escaped(&self.project)).into()
+    }
+
+    fn parameters(&self) -> QueryParams<'_> {
+        let mut params = QueryParams::default();
+        params
+            .push("content_ref", self.content_ref.as_str())
+            .push("include_merged_yaml", true);
+        params
+    }
+}
+
+/// The jobs of a pipeline, including the attempts that were retried.
+pub struct PipelineJobs {
+    pub project: ProjectId,
+    pub pipeline: u64,
+}
+
+impl Endpoint for PipelineJobs {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!("projects/{}/pipelines/{}/jobs", self.project, self.pipeline).into()
+    }
+
+    fn parameters(&self) -> QueryParams<'_> {
+        let mut params = QueryParams::default();
+        params.push("include_retried", true);
+        params
+    }
+}
+
+impl Pageable for PipelineJobs {}
+
+/// The trigger jobs of a pipeline, with the downstream pipelines they created.
+pub struct PipelineBridges {
+    pub project: ProjectId,
+    pub pipeline: u64,
+}
+
+impl Endpoint for PipelineBridges {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!(
+            "projects/{}/pipelines/{}/bridges",
+            self.project, self.pipeline
+        )
+        .into()
+    }
+}
+
+impl Pageable for PipelineBridges {}
+
+/// The JUnit test report of a pipeline.
+pub struct TestReport {
+    pub project: ProjectId,
+    pub pipeline: u64,
+}
+
+impl Endpoint for TestReport {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!(
+            "projects/{}/pipelines/{}/test_report",
+            self.project, self.pipeline
+        )
+        .into()
+    }
+}
+
+/// The merge requests of the projects of a group that were opened since `created_after`.
+pub struct GroupMergeRequests {
+    pub group: String,
+    pub created_after: DateTime<Utc>,
+}
+
+impl Endpoint for GroupMergeRequests {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!("groups/{}/merge_requests", escaped(&self.group)).into()
+    }
+
+    fn parameters(&self) -> QueryParams<'_> {
+        let mut params = QueryParams::default();
+        params
+            .push("created_after", self.created_after)
+            .push("scope", "all");
+        params
+    }
+}
+
+impl Pageable for GroupMergeRequests {}
+
+/// The comments and system notes of a merge request.
+pub struct MergeRequestNotes {
+    pub project: ProjectId,
+    pub merge_request: u64,
+}
+
+impl Endpoint for MergeRequestNotes {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!(
+            "projects/{}/merge_requests/{}/notes",
+            self.project, self.merge_request
+        )
+        .into()
+    }
+}
+
+impl Pageable for MergeRequestNotes {}
+
+/// Comments on a merge request.
+pub struct CreateMergeRequestNote {
+    pub project: ProjectId,
+    pub merge_request: u64,
+    pub body: String,
+}
+
+impl Endpoint for CreateMergeRequestNote {
+    fn method(&self) -> Method {
+        Method::POST
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!(
+            "projects/{}/merge_requests/{}/notes",
+            self.project, self.merge_request
+        )
+        .into()
+    }
+
+    fn body(&self) -> Result<Option<(&'static str, Vec<u8>)>, BodyError> {
+        let mut params = FormParams::default();
+        params.push("body", self.body.as_str());
+        params.into_body()
+    }
+}
+
+/// Comments on a commit.
+pub struct CreateCommitComment {
+    pub project: ProjectId,
+    pub sha: String,
+    pub note: String,
+}
+
+impl Endpoint for CreateCommitComment {
+    fn method(&self) -> Method {
+        Method::POST
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!(
+            "projects/{}/repository/commits/{}/comments",
+            self.project, self.sha
+        )
+        .into()
+    }
+
+    fn body(&self) -> Result<Option<(&'static str, Vec<u8>)>, BodyError> {
+        let mut params = FormParams::default();
+        params.push("note", self.note.as_str());
+        params.into_body()
+    }
+}
+
+/// The merge requests that a deployment shipped.
+pub struct DeploymentMergeRequests {
+    pub project: ProjectId,
+    /// The ID of the deployment, not its IID.
+    pub deployment: u64,
+}
+
+impl Endpoint for DeploymentMergeRequests {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!(
+            "projects/{}/deployments/{}/merge_requests",
+            self.project, self.deployment
+        )
+        .into()
+    }
+}
+
+impl Pageable for DeploymentMergeRequests {}
+
+/// The issues of the projects of a group in a milestone.
+pub struct GroupIssues {
+    pub group: String,
+    pub milestone: String,
+}
+
+impl Endpoint for GroupIssues {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!("groups/{}/issues", escaped(&self.group)).into()
+    }
+
+    fn parameters(&self) -> QueryParams<'_> {
+        let mut params = QueryParams::default();
+        params
+            .push("milestone", self.milestone.as_str())
+            .push("scope", "all");
+        params
+    }
+}
+
+impl Pageable for GroupIssues {}
+
+/// The commits on any branch of a project since `since`.
+pub struct ProjectCommits {
+    pub project: ProjectId,
+    pub since: DateTime<Utc>,
+}
+
+impl Endpoint for ProjectCommits {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!("projects/{}/repository/commits", self.project).into()
+    }
+
+    fn parameters(&self) -> QueryParams<'_> {
+        let mut params = QueryParams::default();
+        params.push("since", self.since).push("all", true);
+        params
+    }
+}
+
+impl Pageable for ProjectCommits {}
+
+/// The merge requests of a project that were opened since `created_after`.
+pub struct ProjectMergeRequests {
+    pub project: ProjectId,
+    pub created_after: DateTime<Utc>,
+}
+
+impl Endpoint for ProjectMergeRequests {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!("projects/{}/merge_requests", self.project).into()
+    }
+
+    fn parameters(&self) -> QueryParams<'_> {
+        let mut params = QueryParams::default();
+        params.push("created_after", self.created_after);
+        params
+    }
+}
+
+impl Pageable for ProjectMergeRequests {}
+
+/// The failed jobs of a project, newest first.
+pub struct FailedJobs {
+    pub project: ProjectId,
+}
+
+impl Endpoint for FailedJobs {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!("projects/{}/jobs", self.project).into()
+    }
+
+    fn parameters(&self) -> QueryParams<'_> {
+        let mut params = QueryParams::default();
+        params.push("scope[]", "failed");
+        params
+    }
+}
+
+impl Pageable for FailedJobs {}
+
+/// The commits between two refs of a project.
+pub struct Compare {
+    pub project: ProjectId,
+    pub from: String,
+    pub to: String,
+}
+
+impl Endpoint for Compare {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!("projects/{}/repository/compare", self.project).into()
+    }
+
+    fn parameters(&self) -> QueryParams<'_> {
+        let mut params = QueryParams::default();
+        params
+            .push("from", self.from.as_str())
+            .push("to", self.to.as_str());
+        params
+    }
+}
+
+/// The merge requests that introduced a commit.
+pub struct CommitMergeRequests {
+    pub project: ProjectId,
+    pub sha: String,
+}
+
+impl Endpoint for CommitMergeRequests {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!(
+            "projects/{}/repository/commits/{}/merge_requests",
+            self.project, self.sha
+        )
+        .into()
+    }
+}
+
+/// The deployments to an environment, newest first.
+pub struct EnvironmentDeployments {
+    pub project: ProjectId,
+    pub environment: String,
+}
+
+impl Endpoint for EnvironmentDeployments {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!("projects/{}/deployments", self.project).into()
+    }
+
+    fn parameters(&self) -> QueryParams<'_> {
+        let mut params = QueryParams::default();
+        params
+            .push("environment", self.environment.as_str())
+            .push("order_by", "created_at")
+            .push("sort", "desc");
+        params
+    }
+}
+
+impl Pageable for EnvironmentDeployments {}
+
+/// The CI/CD variables of a project.
+pub struct ProjectVariables {
+    pub project: ProjectId,
+}
+
+impl Endpoint for ProjectVariables {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!("projects/{}/variables", self.project).into()
+    }
+}
+
+impl Pageable for ProjectVariables {}
+
+/// The protected branches of a project.
+pub struct ProtectedBranches {
+    pub project: ProjectId,
+}
+
+impl Endpoint for ProtectedBranches {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!("projects/{}/protected_branches", self.project).into()
+    }
+}
+
+impl Pageable for ProtectedBranches {}
+
+/// Protects the branches matching `name`, allowing the given access levels to push and merge.
+pub struct ProtectBranch {
+    pub project: ProjectId,
+    pub name: String,
+    pub push_access_level: u64,
+    pub merge_access_level: u64,
+}
+
+impl Endpoint for ProtectBranch {
+    fn method(&self) -> Method {
+        Method::POST
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!("projects/{}/protected_branches", self.project).into()
+    }
+
+    fn body(&self) -> Result<Option<(&'static str, Vec<u8>)>, BodyError> {
+        let mut params = FormParams::default();
+        params
+            .push("name", self.name.as_str())
+            .push("push_access_level", self.push_access_level)
+            .push("merge_access_level", self.merge_access_level);
+        params.into_body()
+    }
+}
+
+/// Removes the protection of the branches matching `name`.
+pub struct UnprotectBranch {
+    pub project: ProjectId,
+    pub name: String,
+}
+
+impl Endpoint for UnprotectBranch {
+    fn method(&self) -> Method {
+        Method::DELETE
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!(
+            "projects/{}/protected_branches/{}",
+            self.project,
+            escaped(&self.name)
+        )
+        .into()
+    }
+}
+
+/// The protected environments of a project.
+pub struct ProtectedEnvironments {
+    pub project: ProjectId,
+}
+
+impl Endpoint for ProtectedEnvironments {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!("projects/{}/protected_environments", self.project).into()
+    }
+}
+
+impl Pageable for ProtectedEnvironments {}
+
+/// The deployment tiers that a group protects in all of its projects.
+pub struct GroupProtectedEnvironments {
+    pub group: String,
+}
+
+impl Endpoint for GroupProtectedEnvironments {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!("groups/{}/protected_environments", escaped(&self.group)).into()
+    }
+}
+
+impl Pageable for GroupProtectedEnvironments {}
+
+/// Adds a deployer to the form as one of the `deploy_access_levels`.
+fn push_deployer(params: &mut FormParams, deployer: Deployer) {
+    match deployer {
+        Deployer::AccessLevel(level) => params.push("deploy_access_levels[][access_level]", level),
+        Deployer::User(user) => params.push("deploy_access_levels[][user_id]", user),
+        Deployer::Group(group) => params.push("deploy_access_levels[][group_id]", group),
+    };
+}
+
+/// Protects the environment `name`, so that only the `deployers` may deploy to it.
+pub struct ProtectEnvironment {
+    pub project: ProjectId,
+    pub name: String,
+    pub deployers: Vec<Deployer>,
+    pub required_approvals: Option<u64>,
+}
+
+impl Endpoint for ProtectEnvironment {
+    fn method(&self) -> Method {
+        Method::POST
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!("projects/{}/protected_environments", self.project).into()
+    }
+
+    fn body(&self) -> Result<Option<(&'static str, Vec<u8>)>, BodyError> {
+        let mut params = FormParams::default();
+        params
+            .push("name", self.name.as_str())
+            .push_opt("required_approval_count", self.required_approvals);
+        for deployer in &self.deployers {
+            push_deployer(&mut params, *deployer);
+        }
+        params.into_body()
+    }
+}
+
+/// Changes a protected environment, adding the deployers of `add` and removing the deploy
+/// access levels with the IDs of `remove`.
+pub struct UpdateProtectedEnvironment {
+    pub project: ProjectId,
+    pub name: String,
+    pub add: Vec<Deployer>,
+    pub remove: Vec<u64>,
+    pub required_approvals: Option<u64>,
+}
+
+impl Endpoint for UpdateProtectedEnvironment {
+    fn method(&self) -> Method {
+        Method::PUT
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!(
+            "projects/{}/protected_environments/{}",
+            self.project,
+            escaped(&self.name)
+        )
+        .into()
+    }
+
+    fn body(&self) -> Result<Option<(&'static str, Vec<u8>)>, BodyError> {
+        let mut params = FormParams::default();
+        params.push_opt("required_approval_count", self.required_approvals);
+        for deployer in &self.add {
+            push_deployer(&mut params, *deployer);
+        }
+        for id in &self.remove {
+            params
+                .push("deploy_access_levels[][id]", *id)
+                .push("deploy_access_levels[][_destroy]", true);
+        }
+        params.into_body()
+    }
+}
+
+/// Removes the protection of the environment `name`.
+pub struct UnprotectEnvironment {
+    pub project: ProjectId,
+    pub name: String,
+}
+
+impl Endpoint for UnprotectEnvironment {
+    fn method(&self) -> Method {
+        Method::DELETE
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!(
+            "projects/{}/protected_environments/{}",
+            self.project,
+            escaped(&self.name)
+        )
+        .into()
+    }
+}
+
+/// The group with this path, without its projects.
+pub struct Group {
+    pub group: String,
+}
+
+impl Endpoint for Group {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!("groups/{}", escaped(&self.group)).into()
+    }
+
+    fn parameters(&self) -> QueryParams<'_> {
+        let mut params = QueryParams::default();
+        params.push("with_projects", false);
+        params
+    }
+}
+
+/// The users with exactly this username, at most one.
+pub struct UsersByUsername {
+    pub username: String,
+}
+
+impl Endpoint for UsersByUsername {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        "users".into()
+    }
+
+    fn parameters(&self) -> QueryParams<'_> {
+        let mut params = QueryParams::default();
+        params.push("username", self.username.as_str());
+        params
+    }
+}
+
+/// The webhooks of a project.
+pub struct ProjectHooks {
+    pub project: ProjectId,
+}
+
+impl Endpoint for ProjectHooks {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!("projects/{}/hooks", self.project).into()
+    }
+}
+
+impl Pageable for ProjectHooks {}
+
+/// Adds a webhook that is called for the `events`, e.g. `push` for `push_events`.
+pub struct AddHook {
+    pub project: ProjectId,
+    pub url: String,
+    pub token: Option<String>,
+    pub events: Vec<String>,
+}
+
+impl Endpoint for AddHook {
+    fn method(&self) -> Method {
+        Method::POST
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!("projects/{}/hooks", self.project).into()
+    }
+
+    fn body(&self) -> Result<Option<(&'static str, Vec<u8>)>, BodyError> {
+        let mut params = FormParams::default();
+        params
+            .push("url", self.url.as_str())
+            .push_opt("token", self.token.as_deref());
+        for event in &self.events {
+            params.push(format!("{}_events", event), true);
+        }
+        params.into_body()
+    }
+}
+
+/// Points a webhook to another URL, keeping its events.
+pub struct EditHook {
+    pub project: ProjectId,
+    pub hook: u64,
+    pub url: String,
+}
+
+impl Endpoint for EditHook {
+    fn method(&self) -> Method {
+        Method::PUT
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!("projects/{}/hooks/{}", self.project, self.hook).into()
+    }
+
+    fn body(&self) -> Result<Option<(&'static str, Vec<u8>)>, BodyError> {
+        let mut params = FormParams::default();
+        params.push("url", self.url.as_str());
+        params.into_body()
+    }
+}
+
+/// Removes a webhook.
+pub struct DeleteHook {
+    pub project: ProjectId,
+    pub hook: u64,
+}
+
+impl Endpoint for DeleteHook {
+    fn method(&self) -> Method {
+        Method::DELETE
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!("projects/{}/hooks/{}", self.project, self.hook).into()
+    }
+}
+
+/// The branches of a project.
+pub struct Branches {
+    pub project: ProjectId,
+}
+
+impl Endpoint for Branches {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!("projects/{}/repository/branches", self.project).into()
+    }
+}
+
+impl Pageable for Branches {}
+
+/// Deletes a branch.
+pub struct DeleteBranch {
+    pub project: ProjectId,
+    pub branch: String,
+}
+
+impl Endpoint for DeleteBranch {
+    fn method(&self) -> Method {
+        Method::DELETE
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!(
+            "projects/{}/repository/branches/{}",
+            self.project,
+            escaped(&self.branch)
+        )
+        .into()
+    }
+}
+
+/// The pipeline schedules of a project.
+pub struct PipelineSchedules {
+    pub project: ProjectId,
+}
+
+impl Endpoint for PipelineSchedules {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!("projects/{}/pipeline_schedules", self.project).into()
+    }
+}
+
+impl Pageable for PipelineSchedules {}
+
+/// The deploy tokens, deploy keys, access tokens or trigger tokens of a project, by the
+/// `kind` in their path, e.g. `deploy_tokens`.
+pub struct ProjectCredentials {
+    pub project: ProjectId,
+    pub kind: &'static str,
+}
+
+impl Endpoint for ProjectCredentials {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!("projects/{}/{}", self.project, self.kind).into()
+    }
+}
+
+impl Pageable for ProjectCredentials {}
+
+/// Everyone with access to a group or project, including through parent groups.
+pub struct Members {
+    /// `groups` or `projects`.
+    pub kind: &'static str,
+    pub path: String,
+}
+
+impl Endpoint for Members {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!("{}/{}/members/all", self.kind, escaped(&self.path)).into()
+    }
+}
+
+impl Pageable for Members {}
+
+/// The issues of a project that were updated since `updated_after`.
+pub struct ProjectIssues {
+    pub project: ProjectId,
+    pub updated_after: DateTime<Utc>,
+}
+
+impl Endpoint for ProjectIssues {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!("projects/{}/issues", self.project).into()
+    }
+
+    fn parameters(&self) -> QueryParams<'_> {
+        let mut params = QueryParams::default();
+        params.push("updated_after", self.updated_after);
+        params
+    }
+}
+
+impl Pageable for ProjectIssues {}
+
+/// The projects of a group and its subgroups, or all visible projects if `group` is empty,
+/// narrowed down by GitLab to the ones found by `search` and with `topic`.
+pub struct Projects {
+    pub group: String,
+    pub search: Option<String>,
+    pub topic: Option<String>,
+    /// Only the archived projects with `Some(true)`, none of them with `Some(false)`.
+    pub archived: Option<bool>,
+    /// Also the projects of the subgroups of `group`.
+    pub include_subgroups: bool,
+    pub statistics: bool,
+}
+
+impl Endpoint for Projects {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        if self.group.is_empty() {
+            "projects".into()
+        } else {
+            format!("groups/{}/projects", escaped(&self.group)).into()
+        }
+    }
+
+    fn parameters(&self) -> QueryParams<'_> {
+        let mut params = QueryParams::default();
+        if self.group.is_empty() {
+            // Ordering by id allows GitLab to use keyset pagination, which stays fast for deep
+            // pages
+            params.push("order_by", "id");
+        } else {
+            params.push("include_subgroups", self.include_subgroups);
+        }
+        params
+            .push_opt("search", self.search.as_deref())
+            .push_opt("topic", self.topic.as_deref())
+            .push_opt("archived", self.archived);
+        if self.statistics {
+            params.push("statistics", true);
+        }
+        params
+    }
+}
+
+impl Pageable for Projects {
+    fn use_keyset_pagination(&self) -> bool {
+        // Listing all projects of the instance is ordered by id, which keyset pagination needs
+        self.group.is_empty()
+    }
+}
+
+/// What the search API finds for `search` in the `scope`, e.g. `blobs`, within a group or
+/// everywhere if `group` is empty.
+pub struct Search {
+    pub group: String,
+    pub scope: &'static str,
+    pub search: String,
+}
+
+impl Endpoint for Search {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        if self.group.is_empty() {
+            "search".into()
+        } else {
+            format!("groups/{}/search", escaped(&self.group)).into()
+        }
+    }
+
+    fn parameters(&self) -> QueryParams<'_> {
+        let mut params = QueryParams::default();
+        params
+            .push("scope", self.scope)
+            .push("search", self.search.as_str());
+        params
+    }
+}
+
+impl Pageable for Search {}
+
+/// The backlog and latency of every Sidekiq queue, only for administrators.
+pub struct SidekiqQueueMetrics;
+
+impl Endpoint for SidekiqQueueMetrics {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        "sidekiq/queue_metrics".into()
+    }
+}
+
+/// How many Sidekiq jobs were processed, failed or wait, only for administrators.
+pub struct SidekiqJobStats;
+
+impl Endpoint for SidekiqJobStats {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        "sidekiq/job_stats".into()
+    }
+}
+
+/// The batched background migrations of the main database, only for administrators.
+pub struct BatchedBackgroundMigrations;
+
+impl Endpoint for BatchedBackgroundMigrations {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        "admin/batched_background_migrations".into()
+    }
+}
+
+/// The description template `name` of a project, of the `kind` `issues` or `merge_requests`.
+pub struct Template {
+    pub project: String,
+    pub kind: &'static str,
+    pub name: String,
+}
+
+impl Endpoint for Template {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!(
+            "projects/{}/templates/{}/{}",
+            escaped(&self.project),
+            self.kind,
+            escaped(&self.name)
+        )
+        .into()
+    }
+}
+
+/// Adds time spent on an issue or merge request, `issuable` is `issues` or `merge_requests`.
+pub struct AddSpentTime {
+    pub project: String,
+    pub issuable: &'static str,
+    pub iid: u64,
+    /// E.g. `1h30m`, where a day has 8 hours and a week 5 days.
+    pub duration: String,
+}
+
+impl Endpoint for AddSpentTime {
+    fn method(&self) -> Method {
+        Method::POST
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!(
+            "projects/{}/{}/{}/add_spent_time",
+            escaped(&self.project),
+            self.issuable,
+            self.iid
+        )
+        .into()
+    }
+
+    fn body(&self) -> Result<Option<(&'static str, Vec<u8>)>, BodyError> {
+        let mut params = FormParams::default();
+        params.push("duration", self.duration.as_str());
+        params.into_body()
+    }
+}
+
+/// Sets the time estimate of an issue or merge request, `issuable` is `issues` or
+/// `merge_requests`.
+pub struct SetTimeEstimate {
+    pub project: String,
+    pub issuable: &'static str,
+    pub iid: u64,
+    /// E.g. `8h`, where a day has 8 hours and a week 5 days.
+    pub duration: String,
+}
+
+impl Endpoint for SetTimeEstimate {
+    fn method(&self) -> Method {
+        Method::POST
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!(
+            "projects/{}/{}/{}/time_estimate",
+            escaped(&self.project),
+            self.issuable,
+            self.iid
+        )
+        .into()
+    }
+
+    fn body(&self) -> Result<Option<(&'static str, Vec<u8>)>, BodyError> {
+        let mut params = FormParams::default();
+        params.push("duration", self.duration.as_str());
+        params.into_body()
+    }
+}
+
+/// The pending to-do items of the current user.
+pub struct Todos;
+
+impl Endpoint for Todos {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        "todos".into()
+    }
+}
+
+impl Pageable for Todos {}
+
+/// Marks one to-do item of the current user as done, or all of them if `todo` is `None`.
+pub struct MarkTodoDone {
+    pub todo: Option<u64>,
+}
+
+impl Endpoint for MarkTodoDone {
+    fn method(&self) -> Method {
+        Method::POST
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        match self.todo {
+            Some(todo) => format!("todos/{}/mark_as_done", todo).into(),
+            None => "todos/mark_as_done".into(),
+        }
+    }
+}
+
+/// Creates a personal snippet, or a snippet of `project` if there is one, with a single file.
+pub struct CreateSnippet {
+    pub project: Option<String>,
+    pub title: String,
+    pub file_name: String,
+    pub content: String,
+    pub visibility: &'static str,
+}
+
+impl Endpoint for CreateSnippet {
+    fn method(&self) -> Method {
+        Method::POST
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        match &self.project {
+            Some(project) => format!("projects/{}/snippets", escaped(project)).into(),
+            None => "snippets".into(),
+        }
+    }
+
+    fn body(&self) -> Result<Option<(&'static str, Vec<u8>)>, BodyError> {
+        let mut params = FormParams::default();
+        params
+            .push("title", self.title.as_str())
+            .push("visibility", self.visibility)
+            .push("files[][file_path]", self.file_name.as_str())
+            .push("files[][content]", self.content.as_str());
+        params.into_body()
+    }
+}
@@ -0,0 +1,499 @@
+use futures::stream::{self, FuturesOrdered, StreamExt};
+use gitlab::api::{projects, AsyncQuery};
+use http::StatusCode;
+use itertools::Itertools;
+use regex::Regex;
+use serde::Serialize;
+use std::fmt;
+use std::time::Instant;
+use tokio::sync::Semaphore;
+use tracing::warn;
+
+use crate::client::Client;
+use crate::endpoints::{self, EnvironmentDeployments};
+use crate::error::ApiError;
+use crate::pagination;
+use crate::selector::{self, Selector, TIER};
+use crate::table::EnvironmentRow;
+use crate::timings::{Phase, Timings};
+use crate::types::{Environment, PastDeployment, Project, ProjectId, WebUrl};
+
+/// Narrows the projects that namespace-wide commands query down to the ones with a name, a
+/// topic and the labels of the selector. Archived projects are left out unless
+/// `include_archived` is set.
+#[derive(Clone, Debug, Default)]
+pub struct ProjectFilter {
+    pub name: Option<NameFilter>,
+    pub topic: Option<String>,
+    pub include_archived: bool,
+    /// Leaves out the projects of the subgroups of a namespace.
+    pub exclude_subgroups: bool,
+    /// Matched against the topics of projects, except for the `tier` of environments that the
+    /// commands showing environments match.
+    pub selector: Selector,
+}
+
+impl ProjectFilter {
+    /// Whether neither a name, a topic nor labels of projects are asked for.
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none() && self.topic.is_none() && self.project_selector().is_empty()
+    }
+
+    fn project_selector(&self) -> Selector {
+        self.selector.only(|key| key != TIER)
+    }
+
+    /// Whether the name or the last part of the path of a project passes the name filter and
+    /// its topics the selector. The topic is left to GitLab.
+    pub fn matches(&self, name: &str, path_with_namespace: &str, topics: &[String]) -> bool {
+        let path = path_with_namespace.rsplit('/').next().unwrap_or_default();
+        self.name
+            .as_ref()
+            .is_none_or(|filter| filter.matches(name) || filter.matches(path))
+            && self
+                .project_selector()
+                .matches(&selector::topic_labels(topics))
+    }
+
+    /// Whether an environment row has the tier that the selector asks for.
+    pub fn matches_environment(&self, row: &EnvironmentRow) -> bool {
+        self.selector.only(|key| key == TIER).matches(&row.labels())
+    }
+
+    /// Lists the projects of `namespace` that GitLab finds for the filter.
+    pub fn endpoint(&self, namespace: &str, statistics: bool) -> endpoints::Projects {
+        let search = self.name.as_ref().and_then(|filter| {
+            let (name, search) = filter.server_side();
+            search.or(name).map(String::from)
+        });
+        endpoints::Projects {
+            group: namespace.to_owned(),
+            search,
+            topic: self.topic.clone(),
+            archived: Some(false).filter(|_| !self.include_archived),
+            include_subgroups: !self.exclude_subgroups,
+            statistics,
+        }
+    }
+
+    /// Keeps the cached projects of different filters apart.
+    fn cache_key(&self, namespace: &str) -> String {
+        if self.is_empty() && !self.include_archived && !self.exclude_subgroups {
+            namespace.to_owned()
+        } else {
+            format!("{} {:?}", namespace, self)
+        }
+    }
+}
+
+/// Lists the projects in `namespace`, or all visible projects if it is empty, that pass the
+/// project filter of `gitlab`. The namespace is the full path of a group, e.g.
+/// `platform/payments` for a subgroup.
+pub async fn list_projects(
+    gitlab: &Client,
+    namespace: &str,
+    limit: Option<usize>,
+    on_page: impl Fn(usize),
+) -> Result<Vec<Project>, ApiError> {
+    let filter = gitlab.project_filter();
+    let endpoint = filter.endpoint(namespace, false);
+    let projects: Vec<Project> = pagination::list(gitlab, &endpoint, limit, on_page)
+        .await
+        .map_err(|e| match e {
+            ApiError::Status { status, .. } if status == StatusCode::NOT_FOUND => {
+                ApiError::GroupNotFound(namespace.to_owned())
+            }
+            e => e,
+        })?;
+    Ok(projects
+        .into_iter()
+        .filter(|p| filter.matches(&p.name, &p.path_with_namespace, &p.topics))
+        .collect())
+}
+
+/// Returns the names and IDs of the projects in `namespace`, or of all visible projects if it
+/// is empty, sorted by name. `on_page` is called with the number of projects so far after
+/// every page.
+pub async fn get_projects_for_namespace(
+    gitlab: &Client,
+    namespace: &str,
+    limit: Option<usize>,
+    timings: &Timings,
+    on_page: impl Fn(usize),
+) -> Result<Vec<(String, ProjectId)>, ApiError> {
+    let start = Instant::now();
+    let cache = gitlab.cache().filter(|_| limit.is_none());
+    let key = gitlab.project_filter().cache_key(namespace);
+    let cached = cache.and_then(|cache| {
+        if gitlab.is_offline() {
+            cache.stale_projects(&key)
+        } else {
+            cache.projects(&key)
+        }
+    });
+    if let Some(mut projects) = cached {
+        projects.sort_by(|a, b| a.0.cmp(&b.0));
+        timings.record(Phase::Discovery, start);
+        return Ok(projects);
+    }
+    let projects = list_projects(gitlab, namespace, limit, on_page).await?;
+    let mut result: Vec<(String, ProjectId)> =
+        projects.into_iter().map(|p| (p.name, p.id)).collect();
+    result.sort_by(|a, b| a.0.cmp(&b.0));
+    if let Some(cache) = cache {
+        cache.put_projects(&key, &result);
+    }
+    timings.record(Phase::Discovery, start);
+    Ok(result)
+}
+
+/// Returns the name and ID of the project with the path `project`, e.g. `group/app`.
+pub async fn get_project(gitlab: &Client, project: &str) -> Result<(String, ProjectId), ApiError> {
+    let endpoint = projects::Project::builder()
+        .project(project)
+        .build()
+        .map_err(ApiError::builder)?;
+    let project: Project = endpoint.query_async(gitlab).await?;
+    Ok((project.name, project.id))
+}
+
+/// The page of an environment in the browser.
+pub async fn environment_url(
+    gitlab: &Client,
+    project: ProjectId,
+    environment: u64,
+) -> Result<String, ApiError> {
+    let endpoint = projects::Project::builder()
+        .project(project)
+        .build()
+        .map_err(ApiError::builder)?;
+    let project: WebUrl = endpoint.query_async(gitlab).await?;
+    Ok(format!(
+        "{}/-/environments/{}",
+        project.web_url, environment
+    ))
+}
+
+/// Selects environments by their name: exactly, by a glob like `review/*` or by a regex
+/// between slashes like `/^review-\d+$/`.
+#[derive(Clone, Debug)]
+pub enum NameFilter {
+    Exact(String),
+    Pattern {
+        regex: Regex,
+        /// A part of every matching name that GitLab can search for.
+        search: Option<String>,
+    },
+}
+
+impl NameFilter {
+    pub fn parse(pattern: &str) -> Result<NameFilter, regex::Error> {
+        if let Some(regex) = pattern
+            .strip_prefix('/')
+            .and_then(|p| p.strip_suffix('/'))
+            .filter(|p| !p.is_empty())
+        {
+            return Ok(NameFilter::Pattern {
+                regex: Regex::new(regex)?,
+                search: None,
+            });
+        }
+        let is_wildcard = |c: char| c == '*' || c == '?';
+        if !pattern.contains(is_wildcard) {
+            return Ok(NameFilter::Exact(pattern.to_owned()));
+        }
+        let regex = pattern
+            .split('*')
+            .map(|part| part.split('?').map(regex::escape).join("."))
+            .join(".*");
+        // GitLab only searches for at least three characters
+        let search = pattern
+            .split(is_wildcard)
+            .max_by_key(|literal| literal.len())
+            .filter(|literal| literal.len() >= 3)
+            .map(String::from);
+        Ok(NameFilter::Pattern {
+            regex: Regex::new(&format!("^{}$", regex))?,
+            search,
+        })
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        match self {
+            NameFilter::Exact(exact) => name == exact,
+            NameFilter::Pattern { regex, .. } => regex.is_match(name),
+        }
+    }
+
+    /// The exact name or the part of the names to let GitLab filter by, before `matches`.
+    pub fn server_side(&self) -> (Option<&str>, Option<&str>) {
+        match self {
+            NameFilter::Exact(exact) => (Some(exact), None),
+            NameFilter::Pattern { search, .. } => (None, search.as_deref()),
+        }
+    }
+}
+
+/// Lists the environments of a project that pass `filter`, including the ones that were never
+/// deployed.
+pub async fn list_environments(
+    gitlab: &Client,
+    project: ProjectId,
+    filter: Option<&NameFilter>,
+) -> Result<Vec<Environment>, ApiError> {
+    let mut builder = projects::environments::Environments::builder();
+    builder.project(project);
+    if let Some((name, search)) = filter.map(NameFilter::server_side) {
+        if let Some(name) = name {
+            builder.name(name);
+        }
+        if let Some(search) = search {
+            builder.search(search);
+        }
+    }
+    let endpoint = builder.build().map_err(ApiError::builder)?;
+    let environments: Vec<Environment> = pagination::list(gitlab, &endpoint, None, |_| ()).await?;
+    Ok(environments
+        .into_iter()
+        .filter(|env| filter.is_none_or(|filter| filter.matches(&env.name)))
+        .collect())
+}
+
+/// The last `limit` deployments to the environment `name` of `project`, newest first.
+pub async fn deployment_history(
+    gitlab: &Client,
+    project: ProjectId,
+    name: &str,
+    limit: usize,
+) -> Result<Vec<PastDeployment>, ApiError> {
+    let endpoint = EnvironmentDeployments {
+        project,
+        environment: name.to_owned(),
+    };
+    pagination::list(gitlab, &endpoint, Some(limit), |_| ()).await
+}
+
+/// A project whose environments, or one of them, could not be fetched, so that its rows are
+/// missing instead of the project looking like it has none.
+#[derive(Clone, Debug, Serialize)]
+pub struct FetchError {
+    pub project: String,
+    /// The environment whose details failed, none if listing the environments did.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub environment: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.environment {
+            Some(environment) => write!(
+                f,
+                "{}: could not get the environment {}: {}",
+                self.project, environment, self.message
+            ),
+            None => write!(
+                f,
+                "{}: could not get the environments: {}",
+                self.project, self.message
+            ),
+        }
+    }
+}
+
+/// The rows of one project and what failed while fetching them.
+#[derive(Default)]
+pub struct ProjectRows {
+    pub rows: Vec<EnvironmentRow>,
+    pub errors: Vec<FetchError>,
+}
+
+async fn get_environments_of_project(
+    gitlab: &Client,
+    semaphore: &Semaphore,
+    project_name_and_id: (String, ProjectId),
+    filter: Option<&NameFilter>,
+    timings: &Timings,
+) -> Result<Vec<(String, ProjectId, Environment)>, ApiError> {
+    let (name, id) = project_name_and_id;
+    let _permit = semaphore.acquire().await;
+    let start = Instant::now();
+    let environments = list_environments(gitlab, id, filter).await?;
+    timings.record(Phase::EnvironmentList, start);
+    Ok(environments
+        .into_iter()
+        .map(|e| (name.to_owned(), id, e))
+        .collect())
+}
+
+async fn build_environment_row(
+    gitlab: &Client,
+    semaphore: &Semaphore,
+    project_name: String,
+    project_id: ProjectId,
+    env: Environment,
+    timings: &Timings,
+) -> Result<EnvironmentRow, ApiError> {
+    // Some GitLab versions embed the last deployment in the list already
+    let env: Environment = if env.last_deployment.is_some() {
+        env
+    } else {
+        let _permit = semaphore.acquire().await;
+        let start = Instant::now();
+        let endpoint = projects::environments::Environment::builder()
+            .project(project_id)
+            .environment(env.id)
+            .build()
+            .map_err(ApiError::builder)?;
+        let env: Environment = endpoint.query_async(gitlab).await?;
+        timings.record(Phase::EnvironmentDetail, start);
+        env
+    };
+
+    Ok(EnvironmentRow::new(project_name, project_id, env))
+}
+
+/// How many environments of one project are fetched at the same time by default, so that a
+/// project with many environments does not take all permits of the semaphore.
+pub const PROJECT_CONCURRENCY: usize = 4;
+
+/// Fetches the rows of the deployed environments of one project that pass `filter`, sorted
+/// by environment. At most `project_concurrency` of its environments are fetched at a time.
+pub async fn get_project_rows(
+    gitlab: &Client,
+    semaphore: &Semaphore,
+    project_name_and_id: (String, ProjectId),
+    filter: Option<&NameFilter>,
+    project_concurrency: usize,
+    timings: &Timings,
+) -> ProjectRows {
+    let project = project_name_and_id.0.clone();
+    let envs =
+        match get_environments_of_project(gitlab, semaphore, project_name_and_id, filter, timings)
+            .await
+        {
+            Ok(envs) => envs,
+            Err(e) => {
+                return ProjectRows {
+                    rows: vec![],
+                    errors: vec![FetchError {
+                        project,
+                        environment: None,
+                        message: e.to_string(),
+                    }],
+                }
+            }
+        };
+    let results: Vec<_> = stream::iter(envs)
+        .map(|(name, id, env)| async move {
+            let environment = env.name.clone();
+            build_environment_row(gitlab, semaphore, name.clone(), id, env, timings)
+                .await
+                .map_err(|e| FetchError {
+                    project: name,
+                    environment: Some(environment),
+                    message: e.to_string(),
+                })
+        })
+        .buffered(project_concurrency.max(1))
+        .collect()
+        .await;
+    let mut fetched = ProjectRows::default();
+    for result in results {
+        match result {
+            Ok(row) if row.commit_sha.is_empty() => {}
+            Ok(row) => fetched.rows.push(row),
+            Err(e) => fetched.errors.push(e),
+        }
+    }
+    fetched
+        .rows
+        .sort_by(|a, b| a.environment_name.cmp(&b.environment_name));
+    fetched
+}
+
+/// Fetches the rows of all deployed environments in `namespace`, sorted by project and
+/// environment. What could not be fetched is logged as warnings.
+pub async fn get_namespace_rows(
+    gitlab: &Client,
+    namespace: &str,
+    concurrency: usize,
+) -> Result<Vec<EnvironmentRow>, ApiError> {
+    let timings = Timings::new(false);
+    let projects = get_projects_for_namespace(gitlab, namespace, None, &timings, |_| ()).await?;
+    let semaphore = Semaphore::new(concurrency);
+    let fetched: Vec<ProjectRows> = projects
+        .into_iter()
+        .map(|project| {
+            get_project_rows(
+                gitlab,
+                &semaphore,
+                project,
+                None,
+                PROJECT_CONCURRENCY,
+                &timings,
+            )
+        })
+        .collect::<FuturesOrdered<_>>()
+        .collect()
+        .await;
+    let mut rows = vec![];
+    for project in fetched {
+        for e in &project.errors {
+            warn!("{}", e);
+        }
+        rows.extend(project.rows);
+    }
+    rows.sort_by(|a, b| {
+        (&a.project_name, &a.environment_name).cmp(&(&b.project_name, &b.environment_name))
+    });
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_name_filter() {
+        let exact = NameFilter::parse("production").unwrap();
+        assert!(exact.matches("production"));
+        assert!(!exact.matches("production-eu"));
+        assert_eq!(exact.server_side(), (Some("production"), None));
+
+        let glob = NameFilter::parse("review/*-app?").unwrap();
+        assert!(glob.matches("review/fix-login-app1"));
+        assert!(!glob.matches("review/fix-login-app"));
+        assert!(!glob.matches("staging/review/x-app1"));
+        assert_eq!(glob.server_side(), (None, Some("review/")));
+
+        let regex = NameFilter::parse("/^review-\\d+$/").unwrap();
+        assert!(regex.matches("review-42"));
+        assert!(!regex.matches("review-main"));
+        assert_eq!(regex.server_side(), (None, None));
+
+        assert!(NameFilter::parse("/(/").is_err());
+    }
+
+    #[test]
+    fn test_project_filter() {
+        let filter = ProjectFilter {
+            name: Some(NameFilter::parse("svc-*").unwrap()),
+            topic: Some("backend".to_string()),
+            include_archived: false,
+            exclude_subgroups: true,
+            selector: Selector::parse("team=payments,tier=production").unwrap(),
+        };
+        let topics = ["team=payments".to_string()];
+        assert!(filter.matches("svc-billing", "group/svc-billing", &topics));
+        assert!(filter.matches("Billing", "group/svc-billing", &topics));
+        assert!(!filter.matches("svc-billing", "group/svc-billing", &[]));
+        assert!(!filter.matches("web", "group/web", &topics));
+        let endpoint = filter.endpoint("group", false);
+        assert_eq!(endpoint.search.as_deref(), Some("svc-"));
+        assert_eq!(endpoint.topic.as_deref(), Some("backend"));
+        assert_eq!(endpoint.archived, Some(false));
+        assert!(!endpoint.include_subgroups);
+        assert!(ProjectFilter::default().matches("web", "group/web", &[]));
+    }
+}
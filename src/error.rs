@@ -0,0 +1,259 @@
+use http::StatusCode;
+use std::io;
+use std::path::PathBuf;
+use thiserror::Error;
+
+use crate::client::RestError;
+
+pub const EXIT_FAILURE: i32 = 1;
+pub const EXIT_USAGE: i32 = 2;
+pub const EXIT_AUTH: i32 = 3;
+pub const EXIT_NOT_FOUND: i32 = 4;
+/// `get environments --exit-code` found projects whose environments run different commits.
+pub const EXIT_DRIFT: i32 = 5;
+/// `wait` gave up before the condition was met.
+pub const EXIT_TIMEOUT: i32 = 6;
+/// Ctrl-C stopped the command, as shells report it.
+pub const EXIT_INTERRUPTED: i32 = 130;
+
+fn status_exit_code(status: StatusCode) -> i32 {
+    match status {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => EXIT_AUTH,
+        StatusCode::NOT_FOUND => EXIT_NOT_FOUND,
+        _ => EXIT_FAILURE,
+    }
+}
+
+/// The status of a failed request. GitLab answers with the status at the start of the JSON
+/// message, like `404 Project Not Found`, which is all that is left of it after `query_async`.
+fn request_status(error: &gitlab::api::ApiError<RestError>) -> Option<StatusCode> {
+    match error {
+        gitlab::api::ApiError::GitlabService { status, .. } => Some(*status),
+        gitlab::api::ApiError::Gitlab { msg } => msg
+            .split(' ')
+            .next()
+            .and_then(|code| code.parse().ok())
+            .and_then(|code| StatusCode::from_u16(code).ok()),
+        _ => None,
+    }
+}
+
+/// Problems with our own config or with the configs of other tools we import from.
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Could not find the home directory")]
+    NoHomeDir,
+    #[error("No config found at {0:?}. Run `gitlabctl config init` to create one.")]
+    Missing(PathBuf),
+    #[error("Could not read {path:?}: {source}")]
+    Read { path: PathBuf, source: io::Error },
+    #[error("Could not write the file {path:?}: {source}")]
+    Write { path: PathBuf, source: io::Error },
+    #[error("Could not parse the config: {0}")]
+    Parse(#[from] toml::de::Error),
+    #[error("Could not serialize the config: {0}")]
+    Serialize(#[from] toml::ser::Error),
+    #[error("Could not parse the glab config: {0}")]
+    Glab(#[from] serde_yaml::Error),
+    #[error("Could not parse the baseline {path:?}: {source}")]
+    Baseline {
+        path: PathBuf,
+        source: serde_yaml::Error,
+    },
+    #[error("Could not find a python-gitlab config")]
+    NoPythonGitlab,
+    #[error("Unknown source `{0}`, expected one of {1}")]
+    UnknownSource(String, String),
+    #[error("Unknown context `{0}`")]
+    UnknownContext(String),
+    #[error("No server configured. Run `gitlabctl config init` or select a context.")]
+    NoServer,
+    #[error("An access token is required")]
+    NoAccessToken,
+    #[error("Could not read from the terminal: {0}")]
+    Terminal(io::Error),
+    #[error("Could not connect to {server}: {message}")]
+    Connect { server: String, message: String },
+    #[error("Found {0} problems in the config")]
+    Invalid(usize),
+}
+
+/// Failed requests to GitLab.
+#[derive(Debug, Error)]
+pub enum ApiError {
+    #[error("Could not connect: {0}")]
+    Connect(#[from] gitlab::GitlabError),
+    #[error(transparent)]
+    Request(#[from] gitlab::api::ApiError<RestError>),
+    #[error("Invalid request: {0}")]
+    Builder(String),
+    #[error("Request to {endpoint} failed with {status}")]
+    Status {
+        endpoint: String,
+        status: StatusCode,
+    },
+    #[error("Could not parse {endpoint}: {source}")]
+    Parse {
+        endpoint: String,
+        source: serde_json::Error,
+    },
+    #[error("GraphQL request failed: {0}")]
+    Graphql(String),
+    #[error("Could not find the group {0}")]
+    GroupNotFound(String),
+    #[error("Reached the deadline while getting the {0}")]
+    Deadline(&'static str),
+}
+
+impl ApiError {
+    /// For the errors of the endpoint builders, which all have their own type.
+    pub fn builder(error: impl ToString) -> ApiError {
+        ApiError::Builder(error.to_string())
+    }
+
+    fn exit_code(&self) -> i32 {
+        match self {
+            ApiError::Status { status, .. } => status_exit_code(*status),
+            // GitLab is asked for the current user when connecting, which only fails with a
+            // response if the token is not accepted
+            ApiError::Connect(gitlab::GitlabError::Api { .. }) => EXIT_AUTH,
+            ApiError::Request(e) => request_status(e).map_or(EXIT_FAILURE, status_exit_code),
+            ApiError::GroupNotFound(_) => EXIT_NOT_FOUND,
+            _ => EXIT_FAILURE,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum NotifyError {
+    #[error("No webhook configured, add one to the [notify] section of the config")]
+    NotConfigured,
+    #[error("Could not post to the webhook: {0}")]
+    Post(#[from] reqwest::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum UpdateError {
+    #[error("Could not get the release: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("The latest release has no {0}")]
+    NoAsset(String),
+    #[error("The checksum of the download is {actual}, expected {expected}")]
+    Checksum { expected: String, actual: String },
+    #[error("Could not replace {path:?}: {source}")]
+    Install { path: PathBuf, source: io::Error },
+}
+
+#[derive(Debug, Error)]
+pub enum RenderError {
+    #[error("Could not write the output: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// Everything that can make a command fail.
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    #[error(transparent)]
+    Api(#[from] ApiError),
+    #[error(transparent)]
+    Render(#[from] RenderError),
+    #[error(transparent)]
+    Notify(#[from] NotifyError),
+    #[error(transparent)]
+    Update(#[from] UpdateError),
+    #[error("Could not clear the cache: {0}")]
+    ClearCache(io::Error),
+    #[error("The interactive setup was aborted: {0}")]
+    Aborted(#[from] tokio::task::JoinError),
+    #[error("{0}")]
+    Usage(String),
+    #[error("`git {0}` failed")]
+    Git(String),
+    #[error("Could not open {url} in the browser: {source}")]
+    Browser { url: String, source: io::Error },
+    #[error("Could not serve on {address}: {source}")]
+    Serve { address: String, source: io::Error },
+    #[error("{0} projects run different commits in their environments")]
+    Drift(usize),
+    #[error("Gave up after {timeout} waiting for {condition}")]
+    Timeout { condition: String, timeout: String },
+    #[error("{0}, so it will not meet the condition")]
+    Unmet(String),
+    #[error("Failed for {failed} of {total} projects")]
+    Partial { failed: usize, total: usize },
+    #[error("Interrupted, the results are partial")]
+    Interrupted,
+}
+
+impl Error {
+    /// The exit code of the process, documented in the README.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Api(error) => error.exit_code(),
+            Error::Config(ConfigError::UnknownContext(_))
+            | Error::Notify(NotifyError::NotConfigured)
+            | Error::Usage(_) => EXIT_USAGE,
+            Error::Drift(_) => EXIT_DRIFT,
+            Error::Timeout { .. } => EXIT_TIMEOUT,
+            Error::Interrupted => EXIT_INTERRUPTED,
+            _ => EXIT_FAILURE,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_messages_are_actionable() {
+        let error: Error =
+            ConfigError::Missing(PathBuf::from("/home/me/.config/gitlab.toml")).into();
+        assert_eq!(
+            error.to_string(),
+            "No config found at \"/home/me/.config/gitlab.toml\". Run `gitlabctl config init` to create one."
+        );
+        let error: Error = ApiError::Deadline("projects").into();
+        assert_eq!(
+            error.to_string(),
+            "Reached the deadline while getting the projects"
+        );
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        let status = |status| -> Error {
+            ApiError::Status {
+                endpoint: "projects".to_owned(),
+                status,
+            }
+            .into()
+        };
+        assert_eq!(status(StatusCode::UNAUTHORIZED).exit_code(), EXIT_AUTH);
+        assert_eq!(status(StatusCode::NOT_FOUND).exit_code(), EXIT_NOT_FOUND);
+        assert_eq!(status(StatusCode::BAD_GATEWAY).exit_code(), EXIT_FAILURE);
+        let message = |msg: &str| -> Error {
+            ApiError::Request(gitlab::api::ApiError::Gitlab {
+                msg: msg.to_owned(),
+            })
+            .into()
+        };
+        assert_eq!(message("404 Project Not Found").exit_code(), EXIT_NOT_FOUND);
+        assert_eq!(message("401 Unauthorized").exit_code(), EXIT_AUTH);
+        assert_eq!(message("403 Forbidden").exit_code(), EXIT_AUTH);
+        assert_eq!(
+            message("name has already been taken").exit_code(),
+            EXIT_FAILURE
+        );
+        assert_eq!(Error::Usage(String::new()).exit_code(), EXIT_USAGE);
+        assert_eq!(Error::Drift(2).exit_code(), EXIT_DRIFT);
+        let timeout = Error::Timeout {
+            condition: "sha=abc".to_owned(),
+            timeout: "20m".to_owned(),
+        };
+        assert_eq!(timeout.exit_code(), EXIT_TIMEOUT);
+        assert_eq!(Error::Interrupted.exit_code(), EXIT_INTERRUPTED);
+    }
+}
@@ -0,0 +1,405 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use crate::audit::project_settings;
+use crate::client::Client;
+use crate::endpoints::{ProjectHooks, ProjectVariables, ProtectedBranches};
+use crate::error::ApiError;
+use crate::pagination;
+use crate::types::ProjectId;
+
+/// The settings that are exported, by their name in the projects API.
+pub const SETTINGS: &[&str] = &[
+    "description",
+    "default_branch",
+    "visibility",
+    "merge_method",
+    "squash_option",
+    "only_allow_merge_if_pipeline_succeeds",
+    "only_allow_merge_if_all_discussions_are_resolved",
+    "remove_source_branch_after_merge",
+];
+
+fn all_environments() -> String {
+    "*".to_owned()
+}
+
+/// A CI/CD variable of a project.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Variable {
+    pub key: String,
+    /// Left out of masked variables, which have to be given again when importing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    #[serde(default)]
+    pub protected: bool,
+    #[serde(default)]
+    pub masked: bool,
+    #[serde(default = "all_environments")]
+    pub environment_scope: String,
+}
+
+#[derive(Deserialize)]
+struct AccessLevelOf {
+    access_level: u64,
+}
+
+#[derive(Deserialize)]
+struct ApiProtectedBranch {
+    name: String,
+    push_access_levels: Vec<AccessLevelOf>,
+    merge_access_levels: Vec<AccessLevelOf>,
+}
+
+/// An access level as the Terraform provider names it.
+fn level_name(levels: &[AccessLevelOf]) -> String {
+    match levels.iter().map(|l| l.access_level).max() {
+        None | Some(0) => "no one",
+        Some(30) => "developer",
+        Some(40) => "maintainer",
+        Some(60) => "admin",
+        Some(level) => return level.to_string(),
+    }
+    .to_owned()
+}
+
+/// A protected branch or wildcard, with who may push and merge.
+#[derive(Clone, Debug, Serialize)]
+pub struct ProtectedBranch {
+    pub name: String,
+    pub push_access_level: String,
+    pub merge_access_level: String,
+}
+
+/// A webhook with the events it is called for, e.g. `push`.
+#[derive(Clone, Debug, Serialize)]
+pub struct Hook {
+    #[serde(skip)]
+    pub id: u64,
+    pub url: String,
+    pub events: Vec<String>,
+    pub enable_ssl_verification: bool,
+}
+
+impl Hook {
+    fn from_api(hook: &Value) -> Hook {
+        let events = hook
+            .as_object()
+            .into_iter()
+            .flatten()
+            .filter(|(_, enabled)| enabled.as_bool() == Some(true))
+            .filter_map(|(key, _)| key.strip_suffix("_events").map(String::from))
+            .collect();
+        Hook {
+            id: hook["id"].as_u64().unwrap_or_default(),
+            url: hook["url"].as_str().unwrap_or_default().to_owned(),
+            events,
+            enable_ssl_verification: hook["enable_ssl_verification"].as_bool().unwrap_or(true),
+        }
+    }
+}
+
+/// What is exported of a project.
+#[derive(Clone, Debug, Serialize)]
+pub struct ProjectConfig {
+    #[serde(skip)]
+    pub id: ProjectId,
+    #[serde(skip)]
+    pub path_with_namespace: String,
+    #[serde(skip)]
+    pub name: String,
+    #[serde(skip)]
+    pub path: String,
+    #[serde(skip)]
+    pub namespace_id: u64,
+    /// The values of `SETTINGS`, in the format of the baseline of `audit settings`.
+    pub settings: BTreeMap<String, Value>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub variables: Vec<Variable>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub protected_branches: Vec<ProtectedBranch>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub hooks: Vec<Hook>,
+}
+
+/// Fetches the settings, variables, protected branches and webhooks of `project`.
+pub async fn project_config(
+    gitlab: &Client,
+    project: ProjectId,
+) -> Result<ProjectConfig, ApiError> {
+    let settings = project_settings(gitlab, project).await?;
+    let mut variables: Vec<Variable> =
+        pagination::list(gitlab, &ProjectVariables { project }, None, |_| ()).await?;
+    for variable in &mut variables {
+        if variable.masked {
+            variable.value = None;
+        }
+    }
+    let branches: Vec<ApiProtectedBranch> =
+        pagination::list(gitlab, &ProtectedBranches { project }, None, |_| ()).await?;
+    let hooks: Vec<Value> =
+        pagination::list(gitlab, &ProjectHooks { project }, None, |_| ()).await?;
+    let text = |key: &str| settings[key].as_str().unwrap_or_default().to_owned();
+    Ok(ProjectConfig {
+        id: project,
+        path_with_namespace: text("path_with_namespace"),
+        name: text("name"),
+        path: text("path"),
+        namespace_id: settings["namespace"]["id"].as_u64().unwrap_or_default(),
+        settings: SETTINGS
+            .iter()
+            .filter(|setting| !settings[**setting].is_null())
+            .map(|setting| (setting.to_string(), settings[*setting].clone()))
+            .collect(),
+        variables,
+        protected_branches: branches
+            .iter()
+            .map(|branch| ProtectedBranch {
+                name: branch.name.clone(),
+                push_access_level: level_name(&branch.push_access_levels),
+                merge_access_level: level_name(&branch.merge_access_levels),
+            })
+            .collect(),
+        hooks: hooks.iter().map(Hook::from_api).collect(),
+    })
+}
+
+/// A Terraform resource name, e.g. `group_app` for `group/app`.
+fn identifier(parts: &[&str]) -> String {
+    let name: String = parts
+        .join("_")
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    // Names have to start with a letter or underscore
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{}", name)
+    } else {
+        name
+    }
+}
+
+/// A string literal that Terraform does not interpolate.
+fn hcl_string(value: &str) -> String {
+    serde_json::to_string(value)
+        .unwrap()
+        .replace("${", "$${")
+        .replace("%{", "%%{")
+}
+
+fn hcl_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => hcl_string(s),
+        Value::Array(values) => {
+            let values: Vec<String> = values.iter().map(hcl_value).collect();
+            format!("[{}]", values.join(", "))
+        }
+        other => other.to_string(),
+    }
+}
+
+/// Writes an `import` block and the resource with `attributes`.
+fn resource(
+    out: &mut String,
+    kind: &str,
+    name: &str,
+    import_id: &str,
+    attributes: &[(&str, String)],
+) {
+    out.push_str(&format!(
+        "import {{\n  to = {}.{}\n  id = {}\n}}\n\n",
+        kind,
+        name,
+        hcl_string(import_id)
+    ));
+    out.push_str(&format!("resource \"{}\" \"{}\" {{\n", kind, name));
+    for (attribute, value) in attributes {
+        out.push_str(&format!("  {} = {}\n", attribute, value));
+    }
+    out.push_str("}\n\n");
+}
+
+/// The projects as resources of the Terraform GitLab provider, with `import` blocks that
+/// adopt the existing ones. Masked variables become sensitive input variables.
+pub fn to_hcl(projects: &[ProjectConfig]) -> String {
+    let mut out = String::new();
+    for project in projects {
+        let name = identifier(&[&project.path_with_namespace]);
+        let reference = format!("gitlab_project.{}.id", name);
+        let mut attributes = vec![
+            ("name", hcl_string(&project.name)),
+            ("path", hcl_string(&project.path)),
+            ("namespace_id", project.namespace_id.to_string()),
+        ];
+        for (setting, value) in &project.settings {
+            let attribute = match setting.as_str() {
+                "visibility" => "visibility_level",
+                setting => setting,
+            };
+            attributes.push((attribute, hcl_value(value)));
+        }
+        resource(
+            &mut out,
+            "gitlab_project",
+            &name,
+            &project.path_with_namespace,
+            &attributes,
+        );
+
+        for variable in &project.variables {
+            let mut parts = vec![project.path_with_namespace.as_str(), variable.key.as_str()];
+            if variable.environment_scope != "*" {
+                parts.push(&variable.environment_scope);
+            }
+            let variable_name = identifier(&parts);
+            let value = match &variable.value {
+                Some(value) => hcl_string(value),
+                None => {
+                    out.push_str(&format!(
+                        "variable \"{}\" {{\n  type      = string\n  sensitive = true\n}}\n\n",
+                        variable_name
+                    ));
+                    format!("var.{}", variable_name)
+                }
+            };
+            resource(
+                &mut out,
+                "gitlab_project_variable",
+                &variable_name,
+                &format!(
+                    "{}:{}:{}",
+                    project.id, variable.key, variable.environment_scope
+                ),
+                &[
+                    ("project", reference.clone()),
+                    ("key", hcl_string(&variable.key)),
+                    ("value", value),
+                    ("protected", variable.protected.to_string()),
+                    ("masked", variable.masked.to_string()),
+                    ("environment_scope", hcl_string(&variable.environment_scope)),
+                ],
+            );
+        }
+
+        for branch in &project.protected_branches {
+            resource(
+                &mut out,
+                "gitlab_branch_protection",
+                &identifier(&[&project.path_with_namespace, &branch.name]),
+                &format!("{}:{}", project.id, branch.name),
+                &[
+                    ("project", reference.clone()),
+                    ("branch", hcl_string(&branch.name)),
+                    ("push_access_level", hcl_string(&branch.push_access_level)),
+                    ("merge_access_level", hcl_string(&branch.merge_access_level)),
+                ],
+            );
+        }
+
+        for hook in &project.hooks {
+            let mut attributes = vec![
+                ("project", reference.clone()),
+                ("url", hcl_string(&hook.url)),
+                (
+                    "enable_ssl_verification",
+                    hook.enable_ssl_verification.to_string(),
+                ),
+            ];
+            let events: Vec<String> = hook
+                .events
+                .iter()
+                .map(|event| format!("{}_events", event))
+                .collect();
+            attributes.extend(
+                events
+                    .iter()
+                    .map(|event| (event.as_str(), "true".to_owned())),
+            );
+            resource(
+                &mut out,
+                "gitlab_project_hook",
+                &identifier(&[&project.path_with_namespace, "hook", &hook.id.to_string()]),
+                &format!("{}:{}", project.id, hook.id),
+                &attributes,
+            );
+        }
+    }
+    out.trim_end().to_owned() + "\n"
+}
+
+/// The projects by path, in the format of `-o yaml`.
+pub fn to_yaml(projects: &[ProjectConfig]) -> String {
+    let by_path: BTreeMap<&str, &ProjectConfig> = projects
+        .iter()
+        .map(|project| (project.path_with_namespace.as_str(), project))
+        .collect();
+    serde_yaml::to_string(&by_path).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn project() -> ProjectConfig {
+        ProjectConfig {
+            id: 42,
+            path_with_namespace: "group/my-app".to_string(),
+            name: "My App".to_string(),
+            path: "my-app".to_string(),
+            namespace_id: 7,
+            settings: [("visibility".to_string(), json!("private"))].into(),
+            variables: vec![Variable {
+                key: "TOKEN".to_string(),
+                value: None,
+                protected: true,
+                masked: true,
+                environment_scope: "*".to_string(),
+            }],
+            protected_branches: vec![ProtectedBranch {
+                name: "main".to_string(),
+                push_access_level: "no one".to_string(),
+                merge_access_level: "maintainer".to_string(),
+            }],
+            hooks: vec![Hook::from_api(&json!({
+                "id": 3,
+                "url": "https://ci.example.org/hook",
+                "push_events": true,
+                "tag_push_events": false,
+                "enable_ssl_verification": true,
+            }))],
+        }
+    }
+
+    #[test]
+    fn test_to_hcl() {
+        let hcl = to_hcl(&[project()]);
+        for expected in [
+            "import {\n  to = gitlab_project.group_my_app\n  id = \"group/my-app\"\n}",
+            "  visibility_level = \"private\"\n",
+            "variable \"group_my_app_token\" {",
+            "  value = var.group_my_app_token\n",
+            "  id = \"42:TOKEN:*\"\n",
+            "resource \"gitlab_branch_protection\" \"group_my_app_main\" {",
+            "  push_access_level = \"no one\"\n",
+            "  id = \"42:3\"\n",
+            "  push_events = true\n",
+        ] {
+            assert!(hcl.contains(expected), "{} not in\n{}", expected, hcl);
+        }
+        assert!(!hcl.contains("tag_push_events"));
+    }
+
+    #[test]
+    fn test_hcl_string() {
+        assert_eq!(hcl_string("a \"b\" ${c}"), "\"a \\\"b\\\" $${c}\"");
+        assert_eq!(identifier(&["1group/app.x"]), "_1group_app_x");
+    }
+}
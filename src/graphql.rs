@@ -0,0 +1,421 @@
+use chrono::{DateTime, Utc};
+use gitlab::api::{AsyncClient, AsyncQuery, RestClient};
+use http::header::CONTENT_TYPE;
+use http::{Method, Request};
+use serde::Deserialize;
+use serde_json::json;
+use tokio::time::{self, Instant};
+
+use crate::client::Client;
+use crate::endpoints;
+use crate::environments::{FetchError, NameFilter};
+use crate::error::ApiError;
+use crate::table::EnvironmentRow;
+use crate::types::{Commit, Deployable, DeployablePipeline, Deployment, Environment, User};
+
+/// The first version whose GraphQL API has the last deployment of environments.
+pub const MIN_VERSION: (u32, u32) = (15, 2);
+
+/// GitLab does not hand out more nodes per page.
+const PAGE_SIZE: usize = 100;
+
+const GROUP_ENVIRONMENTS_QUERY: &str = "
+query($fullPath: ID!, $first: Int!, $after: String, $name: String, $search: String, $includeSubgroups: Boolean) {
+  group(fullPath: $fullPath) {
+    projects(includeSubgroups: $includeSubgroups, first: $first, after: $after) {
+      pageInfo { hasNextPage endCursor }
+      nodes {
+        id
+        name
+        archived
+        environments(first: 100, name: $name, search: $search) {
+          nodes {
+            id
+            name
+            tier
+            externalUrl
+            state
+            lastDeployment(status: SUCCESS) {
+              iid
+              createdAt
+              ref
+              triggerer { username }
+              commit { shortId title }
+              job { id pipeline { status } }
+            }
+            latestDeployments: deployments(first: 1, orderBy: { createdAt: DESC }) {
+              nodes { status }
+            }
+          }
+        }
+      }
+    }
+  }
+}";
+
+#[derive(Deserialize)]
+struct VersionInfo {
+    version: String,
+}
+
+#[derive(Deserialize)]
+struct GraphqlResponse {
+    data: Option<Data>,
+    #[serde(default)]
+    errors: Vec<GraphqlError>,
+}
+
+#[derive(Deserialize)]
+struct GraphqlError {
+    message: String,
+    /// Where in the data the error happened, like `["group", "projects", "nodes", 3, ...]`.
+    #[serde(default)]
+    path: Vec<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct Data {
+    group: Option<Group>,
+}
+
+#[derive(Deserialize)]
+struct Group {
+    projects: Connection<GroupProject>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Connection<T> {
+    page_info: Option<PageInfo>,
+    nodes: Vec<T>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PageInfo {
+    has_next_page: bool,
+    end_cursor: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GroupProject {
+    id: String,
+    name: String,
+    #[serde(default)]
+    archived: bool,
+    /// Missing if GraphQL failed to get them, which it reports as an error.
+    environments: Option<Connection<ProjectEnvironment>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProjectEnvironment {
+    id: String,
+    name: String,
+    /// E.g. `PRODUCTION`.
+    tier: Option<String>,
+    external_url: Option<String>,
+    /// E.g. `available`.
+    state: Option<String>,
+    last_deployment: Option<LastDeployment>,
+    /// The newest deployment, which may have failed or still run.
+    latest_deployments: Option<Connection<DeploymentStatus>>,
+}
+
+#[derive(Deserialize)]
+struct DeploymentStatus {
+    /// E.g. `FAILED`.
+    status: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LastDeployment {
+    iid: String,
+    created_at: DateTime<Utc>,
+    #[serde(rename = "ref")]
+    ref_: Option<String>,
+    triggerer: Option<User>,
+    commit: Option<LastDeploymentCommit>,
+    job: Option<Job>,
+}
+
+#[derive(Deserialize)]
+struct Job {
+    id: String,
+    pipeline: Option<JobPipeline>,
+}
+
+#[derive(Deserialize)]
+struct JobPipeline {
+    /// E.g. `SUCCESS`.
+    status: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LastDeploymentCommit {
+    short_id: String,
+    title: Option<String>,
+}
+
+impl From<ProjectEnvironment> for Environment {
+    fn from(env: ProjectEnvironment) -> Environment {
+        // The last deployment is the last successful one, show if a newer one failed or runs
+        let latest_status = env
+            .latest_deployments
+            .and_then(|latest| latest.nodes.into_iter().next())
+            .map(|latest| latest.status.to_lowercase());
+        Environment {
+            id: parse_gid(&env.id),
+            name: env.name,
+            tier: env.tier.map(|tier| tier.to_lowercase()),
+            external_url: env.external_url,
+            state: env.state,
+            last_deployment: env.last_deployment.map(|deployment| Deployment {
+                iid: deployment.iid.parse().unwrap_or_default(),
+                created_at: deployment.created_at,
+                status: Some(latest_status.unwrap_or_else(|| "success".to_owned())),
+                ref_: deployment.ref_,
+                user: deployment.triggerer.unwrap_or(User {
+                    username: String::new(),
+                }),
+                deployable: Deployable {
+                    id: deployment.job.as_ref().map(|job| parse_gid(&job.id)),
+                    commit: Commit {
+                        short_id: deployment.commit.as_ref().map(|c| c.short_id.clone()),
+                        title: deployment.commit.and_then(|commit| commit.title),
+                    },
+                    pipeline: deployment.job.and_then(|job| job.pipeline).map(|pipeline| {
+                        DeployablePipeline {
+                            status: pipeline.status.to_lowercase(),
+                        }
+                    }),
+                },
+            }),
+        }
+    }
+}
+
+/// Parses the numeric ID of a global ID like `gid://gitlab/Project/42`.
+fn parse_gid(gid: &str) -> u64 {
+    gid.rsplit('/')
+        .next()
+        .and_then(|id| id.parse().ok())
+        .unwrap_or_default()
+}
+
+/// Parses the major and minor version from e.g. `15.4.0-ee`.
+pub fn parse_version(version: &str) -> Option<(u32, u32)> {
+    let mut parts = version.split(['.', '-']);
+    Some((parts.next()?.parse().ok()?, parts.next()?.parse().ok()?))
+}
+
+pub async fn is_supported(gitlab: &Client) -> bool {
+    let version: Result<VersionInfo, _> = endpoints::Version.query_async(gitlab).await;
+    version
+        .ok()
+        .and_then(|info| parse_version(&info.version))
+        .is_some_and(|version| version >= MIN_VERSION)
+}
+
+/// The data of the query and the errors that GraphQL reported for parts of it.
+async fn query(
+    gitlab: &Client,
+    variables: serde_json::Value,
+) -> Result<(Data, Vec<GraphqlError>), ApiError> {
+    let url = gitlab.rest_endpoint("../graphql")?;
+    let body = json!({ "query": GROUP_ENVIRONMENTS_QUERY, "variables": variables });
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri(url.as_str())
+        .header(CONTENT_TYPE, "application/json");
+    let rsp = gitlab
+        .rest_async(request, body.to_string().into_bytes())
+        .await?;
+    if !rsp.status().is_success() {
+        return Err(ApiError::Status {
+            endpoint: "graphql".to_owned(),
+            status: rsp.status(),
+        });
+    }
+    let rsp: GraphqlResponse =
+        serde_json::from_slice(rsp.body()).map_err(|source| ApiError::Parse {
+            endpoint: "graphql".to_owned(),
+            source,
+        })?;
+    match rsp.data {
+        Some(data) => Ok((data, rsp.errors)),
+        None => Err(ApiError::Graphql(rsp.errors.first().map_or_else(
+            || "the response has no data".to_owned(),
+            |error| error.message.clone(),
+        ))),
+    }
+}
+
+/// The project of `projects`, and the environment if any, that `error` is about.
+fn fetch_error(group: &str, projects: &[GroupProject], error: GraphqlError) -> FetchError {
+    let index = |i: usize| {
+        error
+            .path
+            .get(i)
+            .and_then(|v| v.as_u64())
+            .map(|i| i as usize)
+    };
+    let project = index(3).and_then(|i| projects.get(i));
+    let environment = project
+        .and_then(|project| project.environments.as_ref())
+        .zip(index(6))
+        .and_then(|(environments, i)| environments.nodes.get(i))
+        .map(|environment| environment.name.clone());
+    FetchError {
+        project: project.map_or_else(|| group.to_owned(), |project| project.name.clone()),
+        environment,
+        message: error.message,
+    }
+}
+
+/// The rows of the projects of a group and what could not be fetched.
+#[derive(Default)]
+pub struct GroupRows {
+    /// The rows of each project.
+    pub groups: Vec<Vec<EnvironmentRow>>,
+    /// The projects and environments that GraphQL reported errors for.
+    pub errors: Vec<FetchError>,
+    /// Whether `deadline` passed before all pages of projects were fetched.
+    pub timed_out: bool,
+}
+
+/// Fetches the deployed environments that pass `filter` of all projects of `group` using one
+/// query per page of projects, grouped by project. Stops with the pages fetched so far at
+/// `deadline`.
+pub async fn get_group_rows(
+    gitlab: &Client,
+    group: &str,
+    limit: Option<usize>,
+    filter: Option<&NameFilter>,
+    deadline: Option<Instant>,
+) -> Result<GroupRows, ApiError> {
+    let (name, search) = filter.map_or((None, None), NameFilter::server_side);
+    let mut fetched = GroupRows::default();
+    let mut projects = 0;
+    let mut after: Option<String> = None;
+    loop {
+        let page_size = gitlab.page_size().unwrap_or(PAGE_SIZE).clamp(1, PAGE_SIZE);
+        let first = limit.map_or(page_size, |limit| (limit - projects).min(page_size));
+        let variables = json!({
+            "fullPath": group,
+            "first": first,
+            "after": after,
+            "name": name,
+            "search": search,
+            "includeSubgroups": !gitlab.project_filter().exclude_subgroups,
+        });
+        let page = match deadline {
+            Some(deadline) => match time::timeout_at(deadline, query(gitlab, variables)).await {
+                Ok(page) => page,
+                Err(_) => {
+                    fetched.timed_out = true;
+                    break;
+                }
+            },
+            None => query(gitlab, variables).await,
+        };
+        let (data, errors) = page?;
+        let connection = data
+            .group
+            .ok_or_else(|| ApiError::GroupNotFound(group.to_owned()))?
+            .projects;
+        fetched.errors.extend(
+            errors
+                .into_iter()
+                .map(|error| fetch_error(group, &connection.nodes, error)),
+        );
+        for project in connection.nodes {
+            projects += 1;
+            if project.archived && !gitlab.project_filter().include_archived {
+                continue;
+            }
+            let name = project.name;
+            let id = parse_gid(&project.id);
+            let rows: Vec<EnvironmentRow> = project
+                .environments
+                .map(|environments| environments.nodes)
+                .unwrap_or_default()
+                .into_iter()
+                .map(move |env| EnvironmentRow::new(name.clone(), id, env.into()))
+                .filter(|row| {
+                    !row.commit_sha.is_empty()
+                        && filter.is_none_or(|filter| filter.matches(&row.environment_name))
+                })
+                .collect();
+            if !rows.is_empty() {
+                fetched.groups.push(rows);
+            }
+        }
+        match connection.page_info {
+            Some(page) if page.has_next_page && limit.is_none_or(|limit| projects < limit) => {
+                after = page.end_cursor
+            }
+            _ => break,
+        }
+    }
+    Ok(fetched)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(parse_version("15.4.0-ee"), Some((15, 4)));
+        assert_eq!(parse_version("9.5.10"), Some((9, 5)));
+        assert_eq!(parse_version("unknown"), None);
+        assert!(parse_version("15.10.1").unwrap() > MIN_VERSION);
+    }
+
+    #[test]
+    fn test_parse_gid() {
+        assert_eq!(parse_gid("gid://gitlab/Project/42"), 42);
+        assert_eq!(parse_gid("gid://gitlab/Ci::Build/1234"), 1234);
+        assert_eq!(parse_gid("nonsense"), 0);
+    }
+
+    #[test]
+    fn test_fetch_error() {
+        let projects: Vec<GroupProject> = serde_json::from_value(json!([
+            {"id": "gid://gitlab/Project/1", "name": "app", "environments": null},
+            {"id": "gid://gitlab/Project/2", "name": "lib", "environments": {
+                "nodes": [{"id": "gid://gitlab/Environment/3", "name": "prod"}]
+            }}
+        ]))
+        .unwrap();
+        let at = |path: serde_json::Value| {
+            let error = GraphqlError {
+                message: "timeout".to_owned(),
+                path: serde_json::from_value(path).unwrap(),
+            };
+            let e = fetch_error("group", &projects, error);
+            (e.project, e.environment)
+        };
+        assert_eq!(
+            at(json!(["group", "projects", "nodes", 0, "environments"])),
+            ("app".to_owned(), None)
+        );
+        assert_eq!(
+            at(json!([
+                "group",
+                "projects",
+                "nodes",
+                1,
+                "environments",
+                "nodes",
+                0,
+                "lastDeployment"
+            ])),
+            ("lib".to_owned(), Some("prod".to_owned()))
+        );
+        assert_eq!(at(json!(["group"])), ("group".to_owned(), None));
+    }
+}
@@ -0,0 +1,218 @@
+use dirs::home_dir;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::{normalize_server, Context};
+use crate::error::ConfigError;
+
+pub const SOURCES: &[&str] = &["glab", "python-gitlab", "netrc"];
+
+#[derive(Deserialize)]
+struct GlabConfig {
+    #[serde(default)]
+    hosts: BTreeMap<String, GlabHost>,
+}
+
+#[derive(Deserialize)]
+struct GlabHost {
+    token: Option<String>,
+    api_host: Option<String>,
+}
+
+fn home_file(path: &str) -> Result<PathBuf, ConfigError> {
+    Ok(home_dir().ok_or(ConfigError::NoHomeDir)?.join(path))
+}
+
+fn read(path: &Path) -> Result<String, ConfigError> {
+    fs::read_to_string(path).map_err(|source| ConfigError::Read {
+        path: path.to_owned(),
+        source,
+    })
+}
+
+fn parse_glab(contents: &str) -> Result<Vec<(String, Context)>, ConfigError> {
+    let config: GlabConfig = serde_yaml::from_str(contents)?;
+    Ok(config
+        .hosts
+        .into_iter()
+        .filter_map(|(host, entry)| {
+            let server = normalize_server(entry.api_host.as_deref().unwrap_or(&host));
+            entry.token.map(|access_token| {
+                let context = Context {
+                    server,
+                    access_token,
+                    namespace: None,
+                };
+                (host, context)
+            })
+        })
+        .collect())
+}
+
+/// A minimal INI reader, enough for the `key = value` sections python-gitlab writes.
+fn parse_ini(contents: &str) -> BTreeMap<String, BTreeMap<String, String>> {
+    let mut sections = BTreeMap::new();
+    let mut current = String::new();
+    for line in contents.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            current = section.trim().to_owned();
+        } else if let Some(pos) = line.find(['=', ':']) {
+            sections
+                .entry(current.clone())
+                .or_insert_with(BTreeMap::new)
+                .insert(
+                    line[..pos].trim().to_owned(),
+                    line[pos + 1..].trim().to_owned(),
+                );
+        }
+    }
+    sections
+}
+
+fn parse_python_gitlab(contents: &str) -> Vec<(String, Context)> {
+    parse_ini(contents)
+        .into_iter()
+        .filter(|(name, _)| name != "global")
+        .filter_map(|(name, section)| {
+            let server = normalize_server(section.get("url")?);
+            let access_token = section.get("private_token")?.to_owned();
+            let context = Context {
+                server,
+                access_token,
+                namespace: None,
+            };
+            Some((name, context))
+        })
+        .collect()
+}
+
+/// Returns the `(machine, password)` pairs of a netrc file.
+fn parse_netrc(contents: &str) -> Vec<(String, String)> {
+    let mut entries = vec![];
+    let mut machine: Option<String> = None;
+    let mut tokens = contents.split_whitespace();
+    while let Some(token) = tokens.next() {
+        match token {
+            "machine" => machine = tokens.next().map(str::to_owned),
+            "default" => machine = None,
+            "password" => {
+                if let (Some(machine), Some(password)) = (&machine, tokens.next()) {
+                    entries.push((machine.to_owned(), password.to_owned()));
+                }
+            }
+            "login" | "account" => {
+                tokens.next();
+            }
+            _ => {}
+        }
+    }
+    entries
+}
+
+/// Reads the contexts from `source`. For netrc, only machines matching `machine` are used,
+/// or otherwise all machines with `gitlab` in their name.
+pub fn import(source: &str, machine: Option<&str>) -> Result<Vec<(String, Context)>, ConfigError> {
+    match source {
+        "glab" => parse_glab(&read(&home_file(".config/glab-cli/config.yml")?)?),
+        "python-gitlab" => {
+            let path = [".python-gitlab.cfg", ".config/python-gitlab.cfg"]
+                .iter()
+                .map(|path| home_file(path))
+                .collect::<Result<Vec<_>, _>>()?
+                .into_iter()
+                .find(|path| path.exists())
+                .ok_or(ConfigError::NoPythonGitlab)?;
+            Ok(parse_python_gitlab(&read(&path)?))
+        }
+        "netrc" => Ok(parse_netrc(&read(&home_file(".netrc")?)?)
+            .into_iter()
+            .filter(|(host, _)| match machine {
+                Some(machine) => host == machine,
+                None => host.contains("gitlab"),
+            })
+            .map(|(host, access_token)| {
+                let context = Context {
+                    server: host.clone(),
+                    access_token,
+                    namespace: None,
+                };
+                (host, context)
+            })
+            .collect()),
+        _ => Err(ConfigError::UnknownSource(
+            source.to_owned(),
+            SOURCES.join(", "),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_glab() {
+        let contents = "
+git_protocol: ssh
+hosts:
+  gitlab.com:
+    token: abc
+    api_protocol: https
+  gitlab.example.org:
+    api_host: api.example.org
+    token: def
+  no-token.org:
+    git_protocol: ssh
+";
+        let contexts = parse_glab(contents).unwrap();
+        assert_eq!(contexts.len(), 2);
+        assert_eq!(contexts[0].0, "gitlab.com");
+        assert_eq!(contexts[0].1.access_token, "abc");
+        assert_eq!(contexts[1].1.server, "api.example.org");
+    }
+
+    #[test]
+    fn test_parse_python_gitlab() {
+        let contents = "
+[global]
+default = work
+timeout = 5
+
+# a comment
+[work]
+url = https://gitlab.example.org/
+private_token = vTbFeqJYCY3sibBP7BZM
+api_version = 4
+
+[oauth]
+url = https://gitlab.com
+oauth_token = nope
+";
+        let contexts = parse_python_gitlab(contents);
+        assert_eq!(contexts.len(), 1);
+        assert_eq!(contexts[0].0, "work");
+        assert_eq!(contexts[0].1.server, "gitlab.example.org");
+        assert_eq!(contexts[0].1.access_token, "vTbFeqJYCY3sibBP7BZM");
+    }
+
+    #[test]
+    fn test_parse_netrc() {
+        let contents = "machine gitlab.com login oauth2 password abc
+machine github.com
+  login me
+  password def
+default login anonymous password guest";
+        assert_eq!(
+            parse_netrc(contents),
+            vec![
+                ("gitlab.com".to_string(), "abc".to_string()),
+                ("github.com".to_string(), "def".to_string())
+            ]
+        );
+    }
+}
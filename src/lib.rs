@@ -0,0 +1,22 @@
+pub mod audit;
+pub mod cache;
+pub mod client;
+pub mod compare;
+pub mod config;
+pub mod dora;
+pub mod endpoints;
+pub mod environments;
+pub mod error;
+pub mod export;
+pub mod git;
+pub mod graphql;
+pub mod import;
+pub mod metrics;
+pub mod notify;
+pub mod pagination;
+pub mod selector;
+pub mod stats;
+pub mod table;
+pub mod test_report;
+pub mod timings;
+pub mod types;
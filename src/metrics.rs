@@ -0,0 +1,140 @@
+use chrono::{DateTime, Utc};
+use std::fmt::Write;
+
+use crate::table::{self, EnvironmentRow};
+
+/// The content type of the Prometheus text format.
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// Escapes a label value as the text format requires.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// The labels of the environment of `row`, with its instance and namespace when several are
+/// queried.
+fn labels(row: &EnvironmentRow) -> String {
+    let mut labels = String::new();
+    if !row.instance.is_empty() {
+        write!(labels, "instance=\"{}\",", escape(&row.instance)).unwrap();
+    }
+    if !row.namespace.is_empty() {
+        write!(labels, "namespace=\"{}\",", escape(&row.namespace)).unwrap();
+    }
+    write!(
+        labels,
+        "project=\"{}\",environment=\"{}\"",
+        escape(&row.project_name),
+        escape(&row.environment_name)
+    )
+    .unwrap();
+    labels
+}
+
+/// Renders the gauges of all `rows` in the Prometheus text format.
+pub fn render(rows: &[EnvironmentRow], now: DateTime<Utc>) -> String {
+    let commits = table::commits_by_project(rows);
+    let mut out = String::new();
+    out.push_str("# HELP gitlab_environment_deployment_age_seconds Seconds since the last deployment to the environment.\n");
+    out.push_str("# TYPE gitlab_environment_deployment_age_seconds gauge\n");
+    for row in rows {
+        if let Some(deployed_at) = row.deployed_at {
+            let age = now.signed_duration_since(deployed_at).num_seconds().max(0);
+            writeln!(
+                out,
+                "gitlab_environment_deployment_age_seconds{{{}}} {}",
+                labels(row),
+                age
+            )
+            .unwrap();
+        }
+    }
+    out.push_str("# HELP gitlab_environment_in_sync Whether all environments of the project run the same commit.\n");
+    out.push_str("# TYPE gitlab_environment_in_sync gauge\n");
+    for row in rows {
+        let in_sync = commits[&row.project_key()].len() == 1;
+        writeln!(
+            out,
+            "gitlab_environment_in_sync{{{}}} {}",
+            labels(row),
+            in_sync as u8
+        )
+        .unwrap();
+    }
+    out.push_str("# HELP gitlab_pipeline_status The status of the pipeline of the last deployment to the environment.\n");
+    out.push_str("# TYPE gitlab_pipeline_status gauge\n");
+    for row in rows.iter().filter(|row| !row.pipeline_status.is_empty()) {
+        writeln!(
+            out,
+            "gitlab_pipeline_status{{{},status=\"{}\"}} 1",
+            labels(row),
+            escape(&row.pipeline_status)
+        )
+        .unwrap();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn row(project: &str, environment: &str, commit: &str) -> EnvironmentRow {
+        EnvironmentRow {
+            instance: String::new(),
+            namespace: String::new(),
+            commit_title: String::new(),
+            project_id: 1,
+            environment_id: 2,
+            job_id: None,
+            project_name: project.to_string(),
+            environment_name: environment.to_string(),
+            tier: String::new(),
+            deployment_by: "1 by bijancn".to_string(),
+            deployment_status: String::new(),
+            ref_: String::new(),
+            commit_sha: commit.to_string(),
+            pipeline_status: String::new(),
+            deployed_at: None,
+            updated: String::new(),
+            external_url: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let now = Utc::now();
+        let rows = vec![
+            EnvironmentRow {
+                deployed_at: Some(now - Duration::seconds(90)),
+                ..row("a", "prod", "1234")
+            },
+            row("a", "review/\"x\"", "5678"),
+            EnvironmentRow {
+                instance: "work".to_string(),
+                namespace: "platform".to_string(),
+                pipeline_status: "failed".to_string(),
+                ..row("b", "prod", "1234")
+            },
+        ];
+        let metrics = render(&rows, now);
+        assert!(metrics.contains(
+            "gitlab_environment_deployment_age_seconds{project=\"a\",environment=\"prod\"} 90\n"
+        ));
+        assert!(!metrics.contains("deployment_age_seconds{instance=\"work\""));
+        assert!(metrics.contains(
+            "gitlab_environment_in_sync{project=\"a\",environment=\"review/\\\"x\\\"\"} 0\n"
+        ));
+        assert!(metrics.contains(
+            "gitlab_environment_in_sync{instance=\"work\",namespace=\"platform\",project=\"b\",environment=\"prod\"} 1\n"
+        ));
+        assert!(metrics.contains(
+            "gitlab_pipeline_status{instance=\"work\",namespace=\"platform\",project=\"b\",environment=\"prod\",status=\"failed\"} 1\n"
+        ));
+        assert!(!metrics.contains("gitlab_pipeline_status{project=\"a\""));
+    }
+}
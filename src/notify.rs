@@ -0,0 +1,119 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use crate::error::NotifyError;
+use crate::table::{EnvironmentRow, ProjectKey};
+
+/// The chat whose incoming webhook is posted to, as they expect different payloads.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Chat {
+    #[default]
+    Slack,
+    Mattermost,
+    Teams,
+}
+
+/// Where `--notify` posts the drifted environments to.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Notify {
+    pub webhook: String,
+    #[serde(default)]
+    pub chat: Chat,
+}
+
+/// Describes the `drifted` projects with the commits of their environments.
+pub fn drift_message(rows: &[EnvironmentRow], drifted: &BTreeSet<ProjectKey>) -> String {
+    let mut message = format!(
+        "{} projects run different commits in their environments:",
+        drifted.len()
+    );
+    for &(instance, namespace, project) in drifted {
+        let environments: Vec<String> = rows
+            .iter()
+            .filter(|row| row.project_key() == (instance, namespace, project))
+            .map(|row| format!("{} {}", row.environment_name, row.commit_sha))
+            .collect();
+        message.push_str("\n- ");
+        if !instance.is_empty() {
+            write!(message, "{} ", instance).unwrap();
+        }
+        if !namespace.is_empty() {
+            write!(message, "{}/", namespace).unwrap();
+        }
+        write!(message, "{}: {}", project, environments.join(", ")).unwrap();
+    }
+    message
+}
+
+fn payload(chat: Chat, text: &str) -> Value {
+    match chat {
+        Chat::Slack | Chat::Mattermost => json!({ "text": text }),
+        Chat::Teams => json!({
+            "@type": "MessageCard",
+            "@context": "https://schema.org/extensions",
+            "summary": "gitlabctl",
+            "text": text,
+        }),
+    }
+}
+
+/// Posts `text` to the configured webhook.
+pub async fn send(notify: &Notify, text: &str) -> Result<(), NotifyError> {
+    reqwest::Client::new()
+        .post(&notify.webhook)
+        .json(&payload(notify.chat, text))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::table::drifted_project_names;
+
+    fn row(project: &str, environment: &str, commit: &str) -> EnvironmentRow {
+        EnvironmentRow {
+            instance: String::new(),
+            namespace: String::new(),
+            commit_title: String::new(),
+            project_id: 1,
+            environment_id: 2,
+            job_id: None,
+            project_name: project.to_string(),
+            environment_name: environment.to_string(),
+            tier: String::new(),
+            deployment_by: String::new(),
+            deployment_status: String::new(),
+            ref_: String::new(),
+            commit_sha: commit.to_string(),
+            pipeline_status: String::new(),
+            deployed_at: None,
+            updated: String::new(),
+            external_url: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_drift_message() {
+        let rows = vec![
+            row("a", "prod", "1234"),
+            row("a", "staging", "5678"),
+            row("b", "prod", "1234"),
+        ];
+        assert_eq!(
+            drift_message(&rows, &drifted_project_names(&rows)),
+            "1 projects run different commits in their environments:\n- a: prod 1234, staging 5678"
+        );
+    }
+
+    #[test]
+    fn test_payload() {
+        assert_eq!(payload(Chat::Slack, "hi"), json!({"text": "hi"}));
+        assert_eq!(payload(Chat::Teams, "hi")["@type"], "MessageCard");
+    }
+}
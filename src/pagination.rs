@@ -0,0 +1,170 @@
+use futures::stream::{self, StreamExt};
+use gitlab::api::{AsyncClient, Endpoint, Pageable, RestClient};
+use http::header::{HeaderMap, LINK};
+use http::{Method, Request};
+use serde::de::DeserializeOwned;
+use url::Url;
+
+use crate::client::Client;
+use crate::error::ApiError;
+
+/// The largest page size GitLab accepts.
+const MAX_PAGE_SIZE: usize = 100;
+/// How many pages are fetched at once when the number of pages is known up front.
+const MAX_PARALLEL_PAGES: usize = 8;
+
+fn header_number(headers: &HeaderMap, name: &str) -> Option<usize> {
+    headers.get(name)?.to_str().ok()?.trim().parse().ok()
+}
+
+/// Returns the `rel="next"` URL of a `Link` header, which GitLab sends for offset as well as
+/// keyset pagination.
+fn next_link(headers: &HeaderMap) -> Option<String> {
+    let link = headers.get(LINK)?.to_str().ok()?;
+    link.split(',').find_map(|part| {
+        let mut pieces = part.split(';');
+        let url = pieces
+            .next()?
+            .trim()
+            .trim_start_matches('<')
+            .trim_end_matches('>');
+        if pieces.any(|p| p.trim() == "rel=\"next\"") {
+            Some(url.to_owned())
+        } else {
+            None
+        }
+    })
+}
+
+/// Returns `url` asking for the given offset `page`, starting at 1.
+fn page_url(url: &Url, page: usize) -> Url {
+    let mut url = url.clone();
+    url.query_pairs_mut().append_pair("page", &page.to_string());
+    url
+}
+
+async fn get_page<E, T>(
+    gitlab: &Client,
+    endpoint: &E,
+    url: String,
+) -> Result<(Vec<T>, HeaderMap), ApiError>
+where
+    E: Endpoint,
+    T: DeserializeOwned,
+{
+    let request = Request::builder().method(Method::GET).uri(url);
+    let rsp = gitlab.rest_async(request, Vec::new()).await?;
+    if !rsp.status().is_success() {
+        return Err(ApiError::Status {
+            endpoint: endpoint.endpoint().into_owned(),
+            status: rsp.status(),
+        });
+    }
+    let page: Vec<T> = serde_json::from_slice(rsp.body()).map_err(|source| ApiError::Parse {
+        endpoint: endpoint.endpoint().into_owned(),
+        source,
+    })?;
+    Ok((page, rsp.headers().clone()))
+}
+
+/// Fetches all pages of `endpoint`, or only as many as needed for `limit` results, calling
+/// `on_page` with the number of results so far after every page.
+///
+/// With offset pagination the first response says how many pages there are, so the remaining
+/// ones are fetched concurrently. Keyset pagination can only follow the `next` links.
+pub async fn list<E, T>(
+    gitlab: &Client,
+    endpoint: &E,
+    limit: Option<usize>,
+    on_page: impl Fn(usize),
+) -> Result<Vec<T>, ApiError>
+where
+    E: Endpoint + Pageable,
+    T: DeserializeOwned,
+{
+    let page_size = gitlab.page_size().unwrap_or(MAX_PAGE_SIZE);
+    let per_page = limit
+        .map_or(page_size, |limit| limit.min(page_size))
+        .clamp(1, MAX_PAGE_SIZE);
+    let mut url = gitlab.rest_endpoint(&endpoint.endpoint())?;
+    endpoint.parameters().add_to_url(&mut url);
+    {
+        let mut pairs = url.query_pairs_mut();
+        pairs.append_pair("per_page", &per_page.to_string());
+        if endpoint.use_keyset_pagination() {
+            pairs.append_pair("pagination", "keyset");
+        }
+    }
+
+    let (mut results, headers) = get_page(gitlab, endpoint, url.to_string()).await?;
+    on_page(results.len());
+    let total_pages = header_number(&headers, "x-total-pages")
+        .filter(|_| !endpoint.use_keyset_pagination())
+        .map(|total| limit.map_or(total, |limit| total.min(limit.div_ceil(per_page))));
+    if let Some(total_pages) = total_pages {
+        let mut pages = stream::iter(2..=total_pages)
+            .map(|page| get_page::<_, T>(gitlab, endpoint, page_url(&url, page).to_string()))
+            .buffered(MAX_PARALLEL_PAGES);
+        while let Some(page) = pages.next().await {
+            results.extend(page?.0);
+            on_page(results.len());
+        }
+    } else {
+        let mut next = next_link(&headers);
+        while let Some(page_url) = next.take() {
+            if limit.is_some_and(|limit| results.len() >= limit) {
+                break;
+            }
+            let (page, headers) = get_page(gitlab, endpoint, page_url).await?;
+            results.extend(page);
+            on_page(results.len());
+            next = next_link(&headers);
+        }
+    }
+    if let Some(limit) = limit {
+        results.truncate(limit);
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::header::HeaderValue;
+
+    #[test]
+    fn test_next_link() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            LINK,
+            HeaderValue::from_static(
+                "<https://gitlab.com/api/v4/projects?page=1>; rel=\"prev\", \
+                 <https://gitlab.com/api/v4/projects?page=3>; rel=\"next\"",
+            ),
+        );
+        assert_eq!(
+            next_link(&headers),
+            Some("https://gitlab.com/api/v4/projects?page=3".to_string())
+        );
+    }
+
+    #[test]
+    fn test_no_next_link_on_last_page() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            LINK,
+            HeaderValue::from_static("<https://gitlab.com/api/v4/projects?page=1>; rel=\"first\""),
+        );
+        assert_eq!(next_link(&headers), None);
+        assert_eq!(next_link(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn test_page_url() {
+        let url = Url::parse("https://gitlab.com/api/v4/groups/1/projects?per_page=100").unwrap();
+        assert_eq!(
+            page_url(&url, 3).as_str(),
+            "https://gitlab.com/api/v4/groups/1/projects?per_page=100&page=3"
+        );
+    }
+}
@@ -0,0 +1,149 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// The label of environments with their deployment tier, e.g. `tier=production`.
+pub const TIER: &str = "tier";
+
+/// One part of a selector like `team=payments`, `tier!=development` or a bare `backend`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Requirement {
+    Equals(String, String),
+    NotEquals(String, String),
+    Exists(String),
+}
+
+impl Requirement {
+    fn key(&self) -> &str {
+        match self {
+            Requirement::Equals(key, _)
+            | Requirement::NotEquals(key, _)
+            | Requirement::Exists(key) => key,
+        }
+    }
+
+    fn matches(&self, labels: &BTreeMap<String, String>) -> bool {
+        match self {
+            Requirement::Equals(key, value) => labels.get(key) == Some(value),
+            Requirement::NotEquals(key, value) => labels.get(key) != Some(value),
+            Requirement::Exists(key) => labels.contains_key(key),
+        }
+    }
+}
+
+/// Selects resources by their labels like `kubectl -l` does, e.g. `team=payments,tier=production`.
+/// All requirements have to match.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Selector(Vec<Requirement>);
+
+#[derive(Debug)]
+pub struct SelectorError(String);
+
+impl fmt::Display for SelectorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "`{}` is not key=value, key!=value or key", self.0)
+    }
+}
+
+impl std::error::Error for SelectorError {}
+
+impl Selector {
+    pub fn parse(selector: &str) -> Result<Selector, SelectorError> {
+        selector
+            .split(',')
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+            .map(|part| {
+                let requirement = if let Some((key, value)) = part.split_once("!=") {
+                    Requirement::NotEquals(key.trim().to_owned(), value.trim().to_owned())
+                } else if let Some((key, value)) = part.split_once('=') {
+                    let value = value.strip_prefix('=').unwrap_or(value);
+                    Requirement::Equals(key.trim().to_owned(), value.trim().to_owned())
+                } else {
+                    Requirement::Exists(part.to_owned())
+                };
+                if requirement.key().is_empty() {
+                    Err(SelectorError(part.to_owned()))
+                } else {
+                    Ok(requirement)
+                }
+            })
+            .collect::<Result<_, _>>()
+            .map(Selector)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The requirements on the keys that `keep` accepts, e.g. to match them against another
+    /// resource.
+    pub fn only(&self, keep: impl Fn(&str) -> bool) -> Selector {
+        Selector(
+            self.0
+                .iter()
+                .filter(|requirement| keep(requirement.key()))
+                .cloned()
+                .collect(),
+        )
+    }
+
+    pub fn matches(&self, labels: &BTreeMap<String, String>) -> bool {
+        self.0.iter().all(|requirement| requirement.matches(labels))
+    }
+}
+
+/// The labels of a project, from topics like `team=payments`, `team:payments` or `backend`.
+pub fn topic_labels(topics: &[String]) -> BTreeMap<String, String> {
+    topics
+        .iter()
+        .map(|topic| match topic.split_once(['=', ':']) {
+            Some((key, value)) => (key.trim().to_owned(), value.trim().to_owned()),
+            None => (topic.trim().to_owned(), String::new()),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            Selector::parse("team=payments, tier!=development,backend").unwrap(),
+            Selector(vec![
+                Requirement::Equals("team".to_string(), "payments".to_string()),
+                Requirement::NotEquals("tier".to_string(), "development".to_string()),
+                Requirement::Exists("backend".to_string()),
+            ])
+        );
+        assert_eq!(
+            Selector::parse("tier==production").unwrap(),
+            Selector(vec![Requirement::Equals(
+                "tier".to_string(),
+                "production".to_string()
+            )])
+        );
+        assert!(Selector::parse("=payments").is_err());
+    }
+
+    #[test]
+    fn test_matches() {
+        let selector = Selector::parse("team=payments,tier=production").unwrap();
+        let mut project = topic_labels(&["team:payments".to_string(), "backend".to_string()]);
+        assert!(!selector.matches(&project));
+        assert!(selector.only(|key| key != "tier").matches(&project));
+        project.insert("tier".to_string(), "production".to_string());
+        assert!(selector.matches(&project));
+        assert!(Selector::parse("backend").unwrap().matches(&project));
+        assert!(!Selector::parse("team!=payments").unwrap().matches(&project));
+        assert!(Selector::default().matches(&labels(&[])));
+    }
+}
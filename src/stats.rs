@@ -0,0 +1,895 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use futures::future::try_join_all;
+use futures::stream::{self, StreamExt, TryStreamExt};
+use gitlab::api::projects::{merge_requests, pipelines};
+use gitlab::api::AsyncQuery;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::client::Client;
+use crate::endpoints::{
+    FailedJobs, GroupIssues, GroupMergeRequests, MergeRequestNotes, PipelineJobs, Pipelines,
+    ProjectCommits, ProjectMergeRequests,
+};
+use crate::error::ApiError;
+use crate::pagination;
+use crate::types::ProjectId;
+
+#[derive(Deserialize)]
+pub struct Pipeline {
+    pub id: u64,
+}
+
+#[derive(Deserialize)]
+pub struct Job {
+    pub name: String,
+    pub status: String,
+}
+
+/// How often a job of a project failed and then passed on retry.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct FlakyJob {
+    pub project: String,
+    pub job: String,
+    /// The pipelines in which the job failed and then passed.
+    pub flaky: usize,
+    /// The pipelines in which the job ran.
+    pub pipelines: usize,
+}
+
+/// The jobs of one pipeline that both failed and succeeded, which as the attempts of a
+/// pipeline share its commit means they failed and then passed on retry.
+fn flaky_in_pipeline(jobs: &[Job]) -> BTreeSet<&str> {
+    let failed: BTreeSet<&str> = jobs
+        .iter()
+        .filter(|job| job.status == "failed")
+        .map(|job| job.name.as_str())
+        .collect();
+    jobs.iter()
+        .filter(|job| job.status == "success" && failed.contains(job.name.as_str()))
+        .map(|job| job.name.as_str())
+        .collect()
+}
+
+/// Ranks the jobs of `project` by how many of its `pipelines` they were flaky in, leaving
+/// out the jobs that never were.
+pub fn flaky_jobs(project: &str, pipelines: &[Vec<Job>]) -> Vec<FlakyJob> {
+    let mut counts: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+    for jobs in pipelines {
+        let names: BTreeSet<&str> = jobs.iter().map(|job| job.name.as_str()).collect();
+        for name in names {
+            counts.entry(name).or_default().1 += 1;
+        }
+        for name in flaky_in_pipeline(jobs) {
+            counts.entry(name).or_default().0 += 1;
+        }
+    }
+    let mut flaky: Vec<FlakyJob> = counts
+        .into_iter()
+        .filter(|(_, (flaky, _))| *flaky > 0)
+        .map(|(job, (flaky, pipelines))| FlakyJob {
+            project: project.to_owned(),
+            job: job.to_owned(),
+            flaky,
+            pipelines,
+        })
+        .collect();
+    flaky.sort_by_key(|f| Reverse(f.flaky));
+    flaky
+}
+
+/// Fetches the jobs of at most `limit` pipelines of `project` updated since `since`.
+pub async fn pipeline_jobs(
+    gitlab: &Client,
+    project: ProjectId,
+    since: DateTime<Utc>,
+    limit: usize,
+) -> Result<Vec<Vec<Job>>, ApiError> {
+    let endpoint = Pipelines {
+        project,
+        updated_after: since,
+        ref_: None,
+        status: None,
+    };
+    let pipelines: Vec<Pipeline> = pagination::list(gitlab, &endpoint, Some(limit), |_| ()).await?;
+    let mut jobs = Vec::with_capacity(pipelines.len());
+    for pipeline in pipelines {
+        let endpoint = PipelineJobs {
+            project,
+            pipeline: pipeline.id,
+        };
+        jobs.push(pagination::list(gitlab, &endpoint, None, |_| ()).await?);
+    }
+    Ok(jobs)
+}
+
+#[derive(Deserialize)]
+struct PipelineDetails {
+    id: u64,
+    #[serde(rename = "ref")]
+    ref_: String,
+    created_at: DateTime<Utc>,
+    coverage: Option<String>,
+}
+
+/// The coverage a pipeline reported.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Coverage {
+    pub pipeline: u64,
+    #[serde(rename = "ref")]
+    pub ref_: String,
+    pub created_at: DateTime<Utc>,
+    /// In percent.
+    pub coverage: f64,
+}
+
+/// The coverage of at most `limit` pipelines of `project` on `ref_` since `since`, oldest
+/// first. Pipelines without coverage are left out.
+pub async fn coverage(
+    gitlab: &Client,
+    project: ProjectId,
+    ref_: Option<&str>,
+    since: DateTime<Utc>,
+    limit: usize,
+) -> Result<Vec<Coverage>, ApiError> {
+    let endpoint = Pipelines {
+        project,
+        updated_after: since,
+        ref_: ref_.map(String::from),
+        status: None,
+    };
+    let ids: Vec<Pipeline> = pagination::list(gitlab, &endpoint, Some(limit), |_| ()).await?;
+    // Only a single pipeline has its coverage
+    let details = try_join_all(ids.iter().map(|pipeline| async move {
+        let endpoint = pipelines::Pipeline::builder()
+            .project(project)
+            .pipeline(pipeline.id)
+            .build()
+            .map_err(ApiError::builder)?;
+        let details: PipelineDetails = endpoint.query_async(gitlab).await?;
+        Ok::<_, ApiError>(details)
+    }))
+    .await?;
+    let mut coverage: Vec<Coverage> = details
+        .into_iter()
+        .filter_map(|details| {
+            Some(Coverage {
+                pipeline: details.id,
+                ref_: details.ref_,
+                created_at: details.created_at,
+                coverage: details.coverage?.parse().ok()?,
+            })
+        })
+        .collect();
+    coverage.sort_by_key(|c| c.created_at);
+    Ok(coverage)
+}
+
+/// Draws `values` as a line of block characters, from the lowest to the highest value.
+pub fn sparkline(values: &[f64]) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    values
+        .iter()
+        .map(|value| {
+            if max > min {
+                BARS[((value - min) / (max - min) * 7.0).round() as usize]
+            } else {
+                BARS[3]
+            }
+        })
+        .collect()
+}
+
+/// The median of `values`, or `None` if there are none.
+pub(crate) fn median(mut values: Vec<i64>) -> Option<i64> {
+    values.sort_unstable();
+    let middle = values.len() / 2;
+    match values.len() {
+        0 => None,
+        n if n % 2 == 0 => Some((values[middle - 1] + values[middle]) / 2),
+        _ => Some(values[middle]),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct User {
+    pub username: String,
+    #[serde(default)]
+    pub name: String,
+}
+
+#[derive(Deserialize)]
+pub struct MergeRequest {
+    pub iid: u64,
+    pub project_id: ProjectId,
+    pub created_at: DateTime<Utc>,
+    pub merged_at: Option<DateTime<Utc>>,
+    pub author: User,
+    #[serde(default)]
+    pub reviewers: Vec<User>,
+}
+
+#[derive(Deserialize)]
+pub struct Note {
+    pub system: bool,
+    pub created_at: DateTime<Utc>,
+    pub author: User,
+}
+
+#[derive(Deserialize)]
+struct Changes {
+    /// The number of changed files, which GitLab caps as `1000+`.
+    changes_count: Option<String>,
+}
+
+/// A merge request with its notes and the number of files it changes.
+pub struct MergeRequestActivity {
+    pub merge_request: MergeRequest,
+    pub notes: Vec<Note>,
+    pub changed_files: Option<usize>,
+}
+
+/// The upper bounds of the changed files of the size buckets, the last one is unbounded.
+const SIZES: [(usize, &str); 4] = [(5, "1-5"), (20, "6-20"), (50, "21-50"), (usize::MAX, "51+")];
+
+/// How fast merge requests are reviewed and merged, and who reviews them.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct MergeRequestStats {
+    pub merge_requests: usize,
+    pub merged: usize,
+    /// The median time from opening to the first comment by someone but the author, in seconds.
+    pub time_to_first_review_seconds: Option<i64>,
+    /// The median time from opening to merging, in seconds.
+    pub time_to_merge_seconds: Option<i64>,
+    /// The number of merge requests by the number of files they change.
+    pub sizes: Vec<(&'static str, usize)>,
+    /// Who commented the most on the merge requests of others, and how many they commented on.
+    pub reviewers: Vec<(String, usize)>,
+}
+
+fn first_review(activity: &MergeRequestActivity) -> Option<DateTime<Utc>> {
+    activity
+        .notes
+        .iter()
+        .filter(|note| {
+            !note.system && note.author.username != activity.merge_request.author.username
+        })
+        .map(|note| note.created_at)
+        .min()
+}
+
+/// Aggregates the `activities`, with the `top` reviewers.
+pub fn merge_request_stats(activities: &[MergeRequestActivity], top: usize) -> MergeRequestStats {
+    let time_to_first_review = activities
+        .iter()
+        .filter_map(|a| Some((first_review(a)? - a.merge_request.created_at).num_seconds()))
+        .collect();
+    let time_to_merge: Vec<i64> = activities
+        .iter()
+        .filter_map(|a| {
+            Some((a.merge_request.merged_at? - a.merge_request.created_at).num_seconds())
+        })
+        .collect();
+    let mut sizes: Vec<(&'static str, usize)> = SIZES.iter().map(|(_, name)| (*name, 0)).collect();
+    for files in activities.iter().filter_map(|a| a.changed_files) {
+        let bucket = SIZES.iter().position(|(max, _)| files <= *max).unwrap();
+        sizes[bucket].1 += 1;
+    }
+    let mut reviewed: HashMap<&str, usize> = HashMap::new();
+    for activity in activities {
+        let reviewers: BTreeSet<&str> = activity
+            .notes
+            .iter()
+            .filter(|note| {
+                !note.system && note.author.username != activity.merge_request.author.username
+            })
+            .map(|note| note.author.username.as_str())
+            .collect();
+        for reviewer in reviewers {
+            *reviewed.entry(reviewer).or_default() += 1;
+        }
+    }
+    let mut reviewers: Vec<(String, usize)> = reviewed
+        .into_iter()
+        .map(|(name, count)| (name.to_owned(), count))
+        .collect();
+    reviewers.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    reviewers.truncate(top);
+    MergeRequestStats {
+        merge_requests: activities.len(),
+        merged: time_to_merge.len(),
+        time_to_first_review_seconds: median(time_to_first_review),
+        time_to_merge_seconds: median(time_to_merge),
+        sizes,
+        reviewers,
+    }
+}
+
+async fn merge_request_activity(
+    gitlab: &Client,
+    merge_request: MergeRequest,
+) -> Result<MergeRequestActivity, ApiError> {
+    let endpoint = MergeRequestNotes {
+        project: merge_request.project_id,
+        merge_request: merge_request.iid,
+    };
+    let notes = pagination::list(gitlab, &endpoint, None, |_| ()).await?;
+    // Only a single merge request has the number of changed files
+    let endpoint = merge_requests::MergeRequest::builder()
+        .project(merge_request.project_id)
+        .merge_request(merge_request.iid)
+        .build()
+        .map_err(ApiError::builder)?;
+    let changes: Changes = endpoint.query_async(gitlab).await?;
+    let changed_files = changes
+        .changes_count
+        .and_then(|count| count.trim_end_matches('+').parse().ok());
+    Ok(MergeRequestActivity {
+        merge_request,
+        notes,
+        changed_files,
+    })
+}
+
+/// Fetches the merge requests of `group` opened since `since` with their notes, `concurrency`
+/// merge requests at a time.
+pub async fn merge_request_activities(
+    gitlab: &Client,
+    group: &str,
+    since: DateTime<Utc>,
+    concurrency: usize,
+) -> Result<Vec<MergeRequestActivity>, ApiError> {
+    let endpoint = GroupMergeRequests {
+        group: group.to_owned(),
+        created_after: since,
+    };
+    let merge_requests: Vec<MergeRequest> =
+        pagination::list(gitlab, &endpoint, None, |_| ()).await?;
+    stream::iter(merge_requests)
+        .map(|merge_request| merge_request_activity(gitlab, merge_request))
+        .buffer_unordered(concurrency)
+        .try_collect()
+        .await
+}
+
+#[derive(Deserialize)]
+pub struct Issue {
+    pub created_at: DateTime<Utc>,
+    pub closed_at: Option<DateTime<Utc>>,
+    pub weight: Option<u64>,
+}
+
+/// The state of the issues of a milestone at the end of a day.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct BurndownDay {
+    pub date: NaiveDate,
+    /// The issues opened until then.
+    pub opened: usize,
+    /// The issues closed until then.
+    pub closed: usize,
+    /// The weight of the issues that were open then.
+    pub remaining_weight: u64,
+}
+
+/// The state of `issues` for every day from when the first one was opened until `today`.
+pub fn burndown(issues: &[Issue], today: NaiveDate) -> Vec<BurndownDay> {
+    let Some(start) = issues.iter().map(|i| i.created_at.date_naive()).min() else {
+        return vec![];
+    };
+    start
+        .iter_days()
+        .take_while(|date| *date <= today)
+        .map(|date| {
+            let opened: Vec<&Issue> = issues
+                .iter()
+                .filter(|i| i.created_at.date_naive() <= date)
+                .collect();
+            let is_closed = |i: &Issue| {
+                i.closed_at
+                    .is_some_and(|closed| closed.date_naive() <= date)
+            };
+            BurndownDay {
+                date,
+                opened: opened.len(),
+                closed: opened.iter().filter(|i| is_closed(i)).count(),
+                remaining_weight: opened
+                    .iter()
+                    .filter(|i| !is_closed(i))
+                    .filter_map(|i| i.weight)
+                    .sum(),
+            }
+        })
+        .collect()
+}
+
+pub async fn milestone_issues(
+    gitlab: &Client,
+    group: &str,
+    milestone: &str,
+) -> Result<Vec<Issue>, ApiError> {
+    let endpoint = GroupIssues {
+        group: group.to_owned(),
+        milestone: milestone.to_owned(),
+    };
+    pagination::list(gitlab, &endpoint, None, |_| ()).await
+}
+
+/// The time tracked on an issue, in seconds.
+#[derive(Deserialize)]
+pub struct TimeStats {
+    pub time_estimate: i64,
+    pub total_time_spent: i64,
+}
+
+#[derive(Deserialize)]
+pub struct TimedIssue {
+    pub assignee: Option<User>,
+    pub time_stats: TimeStats,
+}
+
+/// The time estimated for and spent on the issues of one assignee, empty for the issues that
+/// nobody is assigned to.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct TimeSpent {
+    pub assignee: String,
+    pub issues: usize,
+    /// In seconds.
+    pub estimate: i64,
+    /// In seconds.
+    pub spent: i64,
+}
+
+/// Sums up the tracked time of `issues` by assignee, the most time spent first.
+pub fn time_spent(issues: &[TimedIssue]) -> Vec<TimeSpent> {
+    let mut by_assignee: BTreeMap<&str, TimeSpent> = BTreeMap::new();
+    for issue in issues {
+        let assignee = issue.assignee.as_ref().map_or("", |a| a.username.as_str());
+        let time = by_assignee.entry(assignee).or_insert_with(|| TimeSpent {
+            assignee: assignee.to_owned(),
+            ..TimeSpent::default()
+        });
+        time.issues += 1;
+        time.estimate += issue.time_stats.time_estimate;
+        time.spent += issue.time_stats.total_time_spent;
+    }
+    let mut times: Vec<TimeSpent> = by_assignee.into_values().collect();
+    times.sort_by(|a, b| {
+        b.spent
+            .cmp(&a.spent)
+            .then_with(|| a.assignee.cmp(&b.assignee))
+    });
+    times
+}
+
+/// The issues of a milestone of `group` with their tracked time.
+pub async fn milestone_time(
+    gitlab: &Client,
+    group: &str,
+    milestone: &str,
+) -> Result<Vec<TimedIssue>, ApiError> {
+    let endpoint = GroupIssues {
+        group: group.to_owned(),
+        milestone: milestone.to_owned(),
+    };
+    pagination::list(gitlab, &endpoint, None, |_| ()).await
+}
+
+#[derive(Deserialize)]
+pub struct Commit {
+    pub author_name: String,
+}
+
+/// What a user contributed, by the name they commit with.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct Contributor {
+    pub name: String,
+    pub commits: usize,
+    pub merge_requests: usize,
+    /// The merge requests they were asked to review.
+    pub reviews: usize,
+}
+
+/// Counts the contributions of everyone in `commits` and `merge_requests`, the most commits
+/// first. Users are told apart by their name, as commits have no username.
+pub fn contributors(commits: &[Commit], merge_requests: &[MergeRequest]) -> Vec<Contributor> {
+    fn entry<'a, 'b>(
+        by_name: &'b mut BTreeMap<&'a str, Contributor>,
+        name: &'a str,
+    ) -> &'b mut Contributor {
+        by_name.entry(name).or_insert_with(|| Contributor {
+            name: name.to_owned(),
+            ..Contributor::default()
+        })
+    }
+
+    let mut by_name = BTreeMap::new();
+    for commit in commits {
+        entry(&mut by_name, &commit.author_name).commits += 1;
+    }
+    for merge_request in merge_requests {
+        entry(&mut by_name, &merge_request.author.name).merge_requests += 1;
+        for reviewer in &merge_request.reviewers {
+            entry(&mut by_name, &reviewer.name).reviews += 1;
+        }
+    }
+    let mut contributors: Vec<Contributor> = by_name.into_values().collect();
+    contributors.sort_by_key(|c| Reverse(c.commits));
+    contributors
+}
+
+/// Fetches the commits on any branch and the merge requests of `project` since `since`.
+pub async fn contributions(
+    gitlab: &Client,
+    project: ProjectId,
+    since: DateTime<Utc>,
+) -> Result<(Vec<Commit>, Vec<MergeRequest>), ApiError> {
+    let commits = ProjectCommits { project, since };
+    let merge_requests = ProjectMergeRequests {
+        project,
+        created_after: since,
+    };
+    Ok((
+        pagination::list(gitlab, &commits, None, |_| ()).await?,
+        pagination::list(gitlab, &merge_requests, None, |_| ()).await?,
+    ))
+}
+
+#[derive(Deserialize)]
+pub struct FailedJob {
+    pub name: String,
+    /// Why GitLab says the job failed, e.g. `script_failure` or `runner_system_failure`.
+    pub failure_reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// The failed jobs that share a name and the reason they failed for.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Failure {
+    pub job: String,
+    pub reason: String,
+    pub count: usize,
+    pub projects: BTreeSet<String>,
+    pub last: DateTime<Utc>,
+}
+
+/// Groups the failed jobs of the projects by name and reason, the most frequent first.
+pub fn failures(jobs: &[(String, Vec<FailedJob>)]) -> Vec<Failure> {
+    let mut groups: BTreeMap<(&str, &str), Failure> = BTreeMap::new();
+    for (project, failed) in jobs {
+        for job in failed {
+            let reason = job.failure_reason.as_deref().unwrap_or("unknown");
+            let failure = groups
+                .entry((&job.name, reason))
+                .or_insert_with(|| Failure {
+                    job: job.name.clone(),
+                    reason: reason.to_owned(),
+                    count: 0,
+                    projects: BTreeSet::new(),
+                    last: job.created_at,
+                });
+            failure.count += 1;
+            failure.projects.insert(project.clone());
+            failure.last = failure.last.max(job.created_at);
+        }
+    }
+    let mut failures: Vec<Failure> = groups.into_values().collect();
+    failures.sort_by_key(|f| Reverse(f.count));
+    failures
+}
+
+/// The jobs of `project` that failed since `since`, of the last `limit` failed jobs.
+pub async fn failed_jobs(
+    gitlab: &Client,
+    project: ProjectId,
+    since: DateTime<Utc>,
+    limit: usize,
+) -> Result<Vec<FailedJob>, ApiError> {
+    let endpoint = FailedJobs { project };
+    let jobs: Vec<FailedJob> = pagination::list(gitlab, &endpoint, Some(limit), |_| ()).await?;
+    Ok(jobs
+        .into_iter()
+        .filter(|job| job.created_at >= since)
+        .collect())
+}
+
+/// The storage of a project by what uses it, in bytes.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Storage {
+    #[serde(default)]
+    pub repository_size: u64,
+    #[serde(default)]
+    pub job_artifacts_size: u64,
+    #[serde(default)]
+    pub lfs_objects_size: u64,
+    #[serde(default)]
+    pub packages_size: u64,
+    #[serde(default)]
+    pub container_registry_size: u64,
+    /// Everything that counts against the quota.
+    #[serde(default)]
+    pub storage_size: u64,
+}
+
+#[derive(Deserialize)]
+struct ProjectStatistics {
+    name: String,
+    path_with_namespace: String,
+    #[serde(default)]
+    topics: Vec<String>,
+    /// Missing without at least the reporter role.
+    statistics: Option<Storage>,
+}
+
+/// The storage of the projects of `group` by their path.
+pub async fn group_storage(
+    gitlab: &Client,
+    group: &str,
+) -> Result<Vec<(String, Storage)>, ApiError> {
+    let filter = gitlab.project_filter();
+    let endpoint = filter.endpoint(group, true);
+    let projects: Vec<ProjectStatistics> =
+        pagination::list(gitlab, &endpoint, None, |_| ()).await?;
+    Ok(projects
+        .into_iter()
+        .filter(|p| filter.matches(&p.name, &p.path_with_namespace, &p.topics))
+        .map(|p| (p.path_with_namespace, p.statistics.unwrap_or_default()))
+        .collect())
+}
+
+/// Formats bytes with a binary unit, e.g. `1.5 GiB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(name: &str, status: &str) -> Job {
+        Job {
+            name: name.to_string(),
+            status: status.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_flaky_jobs() {
+        let pipelines = vec![
+            vec![
+                job("test", "failed"),
+                job("test", "success"),
+                job("lint", "success"),
+            ],
+            vec![job("test", "success"), job("lint", "failed")],
+            vec![
+                job("test", "failed"),
+                job("test", "failed"),
+                job("test", "success"),
+                job("build", "failed"),
+                job("build", "success"),
+            ],
+        ];
+        assert_eq!(
+            flaky_jobs("app", &pipelines),
+            vec![
+                FlakyJob {
+                    project: "app".to_string(),
+                    job: "test".to_string(),
+                    flaky: 2,
+                    pipelines: 3,
+                },
+                FlakyJob {
+                    project: "app".to_string(),
+                    job: "build".to_string(),
+                    flaky: 1,
+                    pipelines: 1,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sparkline() {
+        assert_eq!(sparkline(&[80.0, 90.0, 85.0, 100.0]), "▁▅▃█");
+        assert_eq!(sparkline(&[50.0, 50.0]), "▄▄");
+        assert_eq!(sparkline(&[]), "");
+    }
+
+    fn note(author: &str, minutes: i64, created_at: DateTime<Utc>) -> Note {
+        Note {
+            system: false,
+            created_at: created_at + chrono::Duration::minutes(minutes),
+            author: User {
+                username: author.to_string(),
+                name: String::new(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_merge_request_stats() {
+        let created_at = Utc::now();
+        let activity = |notes, merged_after: Option<i64>, changed_files| MergeRequestActivity {
+            merge_request: MergeRequest {
+                iid: 1,
+                project_id: 2,
+                created_at,
+                merged_at: merged_after.map(|m| created_at + chrono::Duration::minutes(m)),
+                author: User {
+                    username: "alice".to_string(),
+                    name: String::new(),
+                },
+                reviewers: vec![],
+            },
+            notes,
+            changed_files,
+        };
+        let activities = vec![
+            activity(
+                vec![
+                    note("alice", 1, created_at),
+                    note("bob", 10, created_at),
+                    note("carol", 20, created_at),
+                ],
+                Some(60),
+                Some(3),
+            ),
+            activity(vec![note("bob", 30, created_at)], None, Some(1000)),
+        ];
+        let stats = merge_request_stats(&activities, 1);
+        assert_eq!(stats.merge_requests, 2);
+        assert_eq!(stats.merged, 1);
+        assert_eq!(stats.time_to_first_review_seconds, Some(20 * 60));
+        assert_eq!(stats.time_to_merge_seconds, Some(60 * 60));
+        assert_eq!(
+            stats.sizes,
+            vec![("1-5", 1), ("6-20", 0), ("21-50", 0), ("51+", 1)]
+        );
+        assert_eq!(stats.reviewers, vec![("bob".to_string(), 2)]);
+    }
+
+    #[test]
+    fn test_burndown() {
+        let day = |d: u32| {
+            NaiveDate::from_ymd_opt(2024, 4, d)
+                .unwrap()
+                .and_hms_opt(12, 0, 0)
+                .unwrap()
+                .and_utc()
+        };
+        let issues = vec![
+            Issue {
+                created_at: day(1),
+                closed_at: Some(day(2)),
+                weight: Some(3),
+            },
+            Issue {
+                created_at: day(2),
+                closed_at: None,
+                weight: Some(5),
+            },
+        ];
+        let days = burndown(&issues, day(3).date_naive());
+        let summary: Vec<(usize, usize, u64)> = days
+            .iter()
+            .map(|d| (d.opened, d.closed, d.remaining_weight))
+            .collect();
+        assert_eq!(summary, vec![(1, 0, 3), (2, 1, 5), (2, 1, 5)]);
+    }
+
+    #[test]
+    fn test_time_spent() {
+        let issue = |assignee: Option<&str>, estimate: i64, spent: i64| TimedIssue {
+            assignee: assignee.map(|username| User {
+                username: username.to_string(),
+                name: String::new(),
+            }),
+            time_stats: TimeStats {
+                time_estimate: estimate,
+                total_time_spent: spent,
+            },
+        };
+        let issues = vec![
+            issue(Some("alice"), 3600, 1800),
+            issue(None, 0, 600),
+            issue(Some("bob"), 7200, 9000),
+            issue(Some("alice"), 0, 3600),
+        ];
+        let times: Vec<(String, usize, i64, i64)> = time_spent(&issues)
+            .into_iter()
+            .map(|t| (t.assignee, t.issues, t.estimate, t.spent))
+            .collect();
+        assert_eq!(
+            times,
+            vec![
+                ("bob".to_string(), 1, 7200, 9000),
+                ("alice".to_string(), 2, 3600, 5400),
+                (String::new(), 1, 0, 600),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_contributors() {
+        let user = |name: &str| User {
+            username: name.to_lowercase(),
+            name: name.to_string(),
+        };
+        let commit = |name: &str| Commit {
+            author_name: name.to_string(),
+        };
+        let commits = vec![commit("Bob"), commit("Alice"), commit("Bob")];
+        let merge_requests = vec![MergeRequest {
+            iid: 1,
+            project_id: 2,
+            created_at: Utc::now(),
+            merged_at: None,
+            author: user("Alice"),
+            reviewers: vec![user("Bob"), user("Carol")],
+        }];
+        let summary: Vec<(String, usize, usize, usize)> = contributors(&commits, &merge_requests)
+            .into_iter()
+            .map(|c| (c.name, c.commits, c.merge_requests, c.reviews))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("Bob".to_string(), 2, 0, 1),
+                ("Alice".to_string(), 1, 1, 0),
+                ("Carol".to_string(), 0, 0, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_failures() {
+        let now = Utc::now();
+        let job = |name: &str, reason: &str| FailedJob {
+            name: name.to_string(),
+            failure_reason: Some(reason.to_string()),
+            created_at: now,
+        };
+        let jobs = vec![
+            (
+                "app".to_string(),
+                vec![
+                    job("build", "runner_system_failure"),
+                    job("test", "script_failure"),
+                ],
+            ),
+            (
+                "api".to_string(),
+                vec![job("build", "runner_system_failure")],
+            ),
+        ];
+        let failures = failures(&jobs);
+        assert_eq!(failures.len(), 2);
+        assert_eq!(failures[0].job, "build");
+        assert_eq!(failures[0].count, 2);
+        assert_eq!(
+            failures[0].projects,
+            BTreeSet::from(["api".to_string(), "app".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(3 * 1024 * 1024 * 1024), "3.0 GiB");
+    }
+}
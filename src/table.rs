@@ -0,0 +1,653 @@
+use chrono::{DateTime, Utc};
+use chrono_humanize::HumanTime;
+use colored::*;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Write};
+use std::time::Duration;
+
+use crate::error::RenderError;
+use crate::selector::TIER;
+use crate::types::{Environment, ProjectId};
+
+#[derive(Clone, Serialize)]
+pub struct EnvironmentRow {
+    /// The context the row was fetched with, only set when querying several.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub instance: String,
+    /// The namespace the row was fetched from, only set when querying several.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub namespace: String,
+    pub project_id: ProjectId,
+    pub environment_id: u64,
+    /// The job of the last deployment.
+    pub job_id: Option<u64>,
+    pub project_name: String,
+    pub environment_name: String,
+    /// The deployment tier, empty if GitLab does not say.
+    pub tier: String,
+    pub deployment_by: String,
+    /// The status of the newest deployment, empty if GitLab does not say.
+    pub deployment_status: String,
+    /// The branch or tag of the last deployment.
+    #[serde(rename = "ref")]
+    pub ref_: String,
+    pub commit_sha: String,
+    /// The title of the deployed commit, empty if GitLab does not say.
+    pub commit_title: String,
+    /// The status of the pipeline that deployed, empty if GitLab does not say.
+    pub pipeline_status: String,
+    pub deployed_at: Option<DateTime<Utc>>,
+    pub updated: String,
+    /// The external URL of the environment, empty if it has none.
+    pub external_url: String,
+}
+
+/// The instance, namespace and name of a project, as projects of other instances or
+/// namespaces can have the same name.
+pub type ProjectKey<'a> = (&'a str, &'a str, &'a str);
+
+impl EnvironmentRow {
+    pub fn project_key(&self) -> ProjectKey<'_> {
+        (&self.instance, &self.namespace, &self.project_name)
+    }
+
+    /// The labels that selectors match the row against.
+    pub fn labels(&self) -> BTreeMap<String, String> {
+        let mut labels = BTreeMap::new();
+        if !self.tier.is_empty() {
+            labels.insert(TIER.to_owned(), self.tier.clone());
+        }
+        labels
+    }
+
+    pub fn new(project_name: String, project_id: ProjectId, env: Environment) -> EnvironmentRow {
+        let last_deployment = env.last_deployment;
+        let job_id = last_deployment.as_ref().and_then(|x| x.deployable.id);
+        let iid: String = last_deployment
+            .as_ref()
+            .map(|deployment| deployment.iid.to_string() + " by " + &deployment.user.username)
+            .unwrap_or_default();
+        let commit: String = last_deployment
+            .as_ref()
+            .and_then(|x| x.deployable.commit.short_id.to_owned())
+            .unwrap_or_default();
+        let commit_title: String = last_deployment
+            .as_ref()
+            .and_then(|x| x.deployable.commit.title.to_owned())
+            .unwrap_or_default();
+        let ref_: String = last_deployment
+            .as_ref()
+            .and_then(|x| x.ref_.clone())
+            .unwrap_or_default();
+        let deployment_status: String = last_deployment
+            .as_ref()
+            .and_then(|x| x.status.clone())
+            .unwrap_or_default();
+        let pipeline_status: String = last_deployment
+            .as_ref()
+            .and_then(|x| x.deployable.pipeline.as_ref())
+            .map(|pipeline| pipeline.status.clone())
+            .unwrap_or_default();
+        let now = Utc::now();
+        let deployed_at = last_deployment.map(|x| x.created_at);
+        let updated: String = deployed_at
+            .map(|at| HumanTime::from(at.signed_duration_since(now)).to_string())
+            .unwrap_or_default();
+        EnvironmentRow {
+            instance: String::new(),
+            namespace: String::new(),
+            project_id,
+            environment_id: env.id,
+            job_id,
+            project_name,
+            environment_name: env.name,
+            tier: env.tier.unwrap_or_default(),
+            deployment_by: iid,
+            deployment_status,
+            ref_,
+            commit_sha: commit,
+            commit_title,
+            pipeline_status,
+            deployed_at,
+            updated,
+            external_url: env.external_url.unwrap_or_default(),
+        }
+    }
+}
+
+pub fn all_the_same(results: &[EnvironmentRow]) -> bool {
+    let mut commits: Vec<String> = results.iter().map(|x| x.commit_sha.clone()).collect();
+    commits.dedup();
+    commits.len() == 1
+}
+
+/// The commits that the environments of every project run.
+pub fn commits_by_project(rows: &[EnvironmentRow]) -> BTreeMap<ProjectKey<'_>, BTreeSet<&str>> {
+    let mut commits: BTreeMap<ProjectKey, BTreeSet<&str>> = BTreeMap::new();
+    for row in rows {
+        commits
+            .entry(row.project_key())
+            .or_default()
+            .insert(&row.commit_sha);
+    }
+    commits
+}
+
+/// The projects whose environments do not all run the same commit.
+pub fn drifted_project_names(rows: &[EnvironmentRow]) -> BTreeSet<ProjectKey<'_>> {
+    commits_by_project(rows)
+        .into_iter()
+        .filter(|(_, commits)| commits.len() > 1)
+        .map(|(project, _)| project)
+        .collect()
+}
+
+/// Counts the projects whose environments do not all run the same commit.
+pub fn drifted_projects(rows: &[EnvironmentRow]) -> usize {
+    drifted_project_names(rows).len()
+}
+
+/// The deployment tiers of GitLab, the most important first.
+pub const TIERS: [&str; 5] = ["production", "staging", "testing", "development", "other"];
+
+/// Groups `rows` by their tier in the order of `TIERS`, followed by the rows without a tier.
+/// Within a tier, the rows are sorted by instance, namespace, project and environment.
+pub fn by_tier(rows: &[EnvironmentRow]) -> Vec<(&str, Vec<EnvironmentRow>)> {
+    let rank = |tier: &str| TIERS.iter().position(|t| *t == tier).unwrap_or(TIERS.len());
+    let mut rows = rows.to_vec();
+    rows.sort_by(|a, b| {
+        (
+            rank(&a.tier),
+            &a.instance,
+            &a.namespace,
+            &a.project_name,
+            &a.environment_name,
+        )
+            .cmp(&(
+                rank(&b.tier),
+                &b.instance,
+                &b.namespace,
+                &b.project_name,
+                &b.environment_name,
+            ))
+    });
+    let mut tiers: Vec<(&str, Vec<EnvironmentRow>)> = vec![];
+    for row in rows {
+        let tier = TIERS.get(rank(&row.tier)).copied().unwrap_or("unknown");
+        match tiers.last_mut() {
+            Some((last, group)) if *last == tier => group.push(row),
+            _ => tiers.push((tier, vec![row])),
+        }
+    }
+    tiers
+}
+
+/// The widths of the columns, which are at least as wide as their headers.
+pub struct Widths {
+    /// Zero hides the column, which is only shown when querying several instances.
+    pub instance: usize,
+    /// Zero hides the column, which is only shown when querying several namespaces.
+    pub namespace: usize,
+    pub project: usize,
+    pub environment: usize,
+    pub deployment: usize,
+    pub state: usize,
+    pub ref_: usize,
+    pub commit: usize,
+    pub status: usize,
+    pub updated: usize,
+    /// Zero hides the TITLE column, which is only shown with `--show-commit-title`.
+    pub title: usize,
+    /// Whether the URL column of `-o wide` is shown. It comes last, so it needs no width.
+    pub url: bool,
+}
+
+/// The width of the TITLE column, longer titles are cut off.
+const TITLE_WIDTH: usize = 40;
+
+/// Cuts `text` off with an ellipsis if it has more than `width` characters.
+fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        text.to_owned()
+    } else {
+        let cut: String = text.chars().take(width.saturating_sub(1)).collect();
+        cut + "…"
+    }
+}
+
+impl Widths {
+    /// Fits all `rows` exactly.
+    pub fn of(rows: &[EnvironmentRow]) -> Widths {
+        let longest = |f: fn(&EnvironmentRow) -> usize| rows.iter().map(f).max().unwrap_or(0);
+        let instance = longest(|x| x.instance.len());
+        let namespace = longest(|x| x.namespace.len());
+        Widths {
+            instance: if instance > 0 { instance.max(8) } else { 0 },
+            namespace: if namespace > 0 { namespace.max(9) } else { 0 },
+            project: longest(|x| x.project_name.len()).max(7),
+            environment: longest(|x| x.environment_name.len()).max(11),
+            deployment: longest(|x| x.deployment_by.len()).max(10),
+            state: longest(|x| x.deployment_status.len()).max(5),
+            ref_: longest(|x| x.ref_.len()).max(3),
+            commit: longest(|x| x.commit_sha.len()).max(6),
+            status: longest(|x| x.pipeline_status.len()).max(6),
+            updated: longest(|x| x.updated.len()).max(7),
+            title: 0,
+            url: false,
+        }
+    }
+
+    /// For printing rows before all of them are known. Only the project names are known up
+    /// front, the other columns get widths that fit typical values.
+    pub fn streaming(projects: &[(String, ProjectId)]) -> Widths {
+        Widths {
+            instance: 0,
+            namespace: 0,
+            project: projects
+                .iter()
+                .map(|(name, _)| name.len())
+                .max()
+                .unwrap_or(0)
+                .max(7),
+            environment: 20,
+            deployment: 20,
+            state: 8,
+            ref_: 12,
+            commit: 8,
+            status: 8,
+            updated: 14,
+            title: 0,
+            url: false,
+        }
+    }
+
+    /// Also shows the URL column if `shown`.
+    pub fn with_url(self, shown: bool) -> Widths {
+        Widths { url: shown, ..self }
+    }
+
+    /// Also shows the TITLE column if `shown`.
+    pub fn with_title(self, shown: bool) -> Widths {
+        Widths {
+            title: if shown { TITLE_WIDTH } else { 0 },
+            ..self
+        }
+    }
+}
+
+pub fn print_header(widths: &Widths) -> Result<(), RenderError> {
+    let mut out = io::stdout().lock();
+    if widths.instance > 0 {
+        write!(out, "{:width$}  ", "INSTANCE", width = widths.instance)?;
+    }
+    if widths.namespace > 0 {
+        write!(out, "{:width$}  ", "NAMESPACE", width = widths.namespace)?;
+    }
+    write!(
+        out,
+        "{:longest_project$}  {:longest_env$}  {:longest_depl$}  {:longest_state$}  {:longest_ref$}  {:longest_commit$}  {:longest_status$}  {:longest_updated$}",
+        "PROJECT",
+        "ENVIRONMENT",
+        "DEPLOYMENT",
+        "STATE",
+        "REF",
+        "COMMIT",
+        "STATUS",
+        "UPDATED",
+        longest_project = widths.project,
+        longest_env = widths.environment,
+        longest_depl = widths.deployment,
+        longest_state = widths.state,
+        longest_ref = widths.ref_,
+        longest_commit = widths.commit,
+        longest_status = widths.status,
+        longest_updated = widths.updated
+    )?;
+    if widths.title > 0 {
+        write!(out, "  {:width$}", "TITLE", width = widths.title)?;
+    }
+    if widths.url {
+        write!(out, "  URL")?;
+    }
+    writeln!(out)?;
+    Ok(())
+}
+
+/// Whether `row` is new or deployed differently than in the `previous` rows.
+pub fn has_changed(row: &EnvironmentRow, previous: &[EnvironmentRow]) -> bool {
+    !previous.iter().any(|p| {
+        p.project_key() == row.project_key()
+            && p.environment_name == row.environment_name
+            && p.deployment_by == row.deployment_by
+            && p.commit_sha == row.commit_sha
+    })
+}
+
+/// The color of a pipeline or deployment status, whatever color the rest of its row has.
+pub fn status_color(status: &str) -> Option<Color> {
+    match status {
+        "success" => Some(Color::Green),
+        "failed" => Some(Color::Red),
+        "running" | "pending" | "created" => Some(Color::Blue),
+        "canceled" | "skipped" => Some(Color::Yellow),
+        _ => None,
+    }
+}
+
+/// The color of the UPDATED cell of a deployment at `deployed_at`: yellow once it is older
+/// than `stale_after` and red once it is older than twice that.
+pub fn staleness(
+    deployed_at: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+    stale_after: Option<Duration>,
+) -> Option<Color> {
+    let age = (now - deployed_at?).to_std().ok()?;
+    let stale_after = stale_after?;
+    if age > stale_after * 2 {
+        Some(Color::Red)
+    } else if age > stale_after {
+        Some(Color::Yellow)
+    } else {
+        None
+    }
+}
+
+fn print_rows(
+    group: &[EnvironmentRow],
+    widths: &Widths,
+    stale_after: Option<Duration>,
+    changed: impl Fn(&EnvironmentRow) -> bool,
+) -> Result<(), RenderError> {
+    let color = if all_the_same(group) {
+        Color::Green
+    } else {
+        Color::Red
+    };
+    let now = Utc::now();
+    let mut out = io::stdout().lock();
+    for r in group {
+        let highlight = changed(r);
+        let paint_with = |cell: &str, color: Color| {
+            let cell = cell.color(color);
+            if highlight {
+                cell.reversed()
+            } else {
+                cell
+            }
+        };
+        let paint = |cell: &str| paint_with(cell, color);
+        let updated_color = staleness(r.deployed_at, now, stale_after).unwrap_or(color);
+        if widths.instance > 0 {
+            write!(
+                out,
+                "{:width$}  ",
+                paint(&r.instance),
+                width = widths.instance
+            )?;
+        }
+        if widths.namespace > 0 {
+            write!(
+                out,
+                "{:width$}  ",
+                paint(&r.namespace),
+                width = widths.namespace
+            )?;
+        }
+        write!(
+            out,
+            "{:longest_project$}  {:longest_env$}  {:longest_depl$}  {:longest_state$}  {:longest_ref$}  {:longest_commit$}  {:longest_status$}  {:longest_updated$}",
+            paint(&r.project_name),
+            paint(&r.environment_name),
+            paint(&r.deployment_by),
+            paint_with(&r.deployment_status, status_color(&r.deployment_status).unwrap_or(color)),
+            paint(&r.ref_),
+            paint(&r.commit_sha),
+            paint_with(&r.pipeline_status, status_color(&r.pipeline_status).unwrap_or(color)),
+            paint_with(&r.updated, updated_color),
+            longest_project = widths.project,
+            longest_env = widths.environment,
+            longest_depl = widths.deployment,
+            longest_state = widths.state,
+            longest_ref = widths.ref_,
+            longest_commit = widths.commit,
+            longest_status = widths.status,
+            longest_updated = widths.updated
+        )?;
+        if widths.title > 0 {
+            let title = truncate(&r.commit_title, widths.title);
+            write!(out, "  {:width$}", paint(&title), width = widths.title)?;
+        }
+        if widths.url {
+            write!(out, "  {}", paint(&r.external_url))?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// Prints the environments of one project, green if they all run the same commit. The
+/// deployments older than `stale_after` are marked in the UPDATED column.
+pub fn print_group(
+    group: &[EnvironmentRow],
+    widths: &Widths,
+    stale_after: Option<Duration>,
+) -> Result<(), RenderError> {
+    print_rows(group, widths, stale_after, |_| false)
+}
+
+/// Like `print_group` but highlights the rows that changed since `previous`.
+pub fn print_changed_group(
+    group: &[EnvironmentRow],
+    widths: &Widths,
+    previous: &[EnvironmentRow],
+    stale_after: Option<Duration>,
+) -> Result<(), RenderError> {
+    print_rows(group, widths, stale_after, |row| has_changed(row, previous))
+}
+
+/// Writes each row as a JSON object on a line of its own.
+fn write_json_lines(out: &mut impl Write, rows: &[EnvironmentRow]) -> io::Result<()> {
+    for row in rows {
+        serde_json::to_writer(&mut *out, row)?;
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// Prints the rows as JSON Lines and flushes them, so that they can be piped on while the
+/// other projects are still fetched.
+pub fn print_json_lines(rows: &[EnvironmentRow]) -> Result<(), RenderError> {
+    let mut stdout = io::stdout().lock();
+    write_json_lines(&mut stdout, rows)?;
+    stdout.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::Itertools;
+
+    fn single_elem_vec() -> Vec<EnvironmentRow> {
+        vec![EnvironmentRow {
+            instance: String::new(),
+            namespace: String::new(),
+            commit_title: String::new(),
+            project_id: 1,
+            environment_id: 1,
+            job_id: None,
+            project_name: "project".to_string(),
+            environment_name: "env".to_string(),
+            tier: String::new(),
+            deployment_by: "deployed by someone".to_string(),
+            deployment_status: String::new(),
+            ref_: String::new(),
+            commit_sha: "asdflkj".to_string(),
+            pipeline_status: String::new(),
+            deployed_at: None,
+            updated: "some time ago".to_string(),
+            external_url: String::new(),
+        }]
+    }
+
+    #[test]
+    fn test_single_elem() {
+        assert!(all_the_same(&single_elem_vec()));
+    }
+
+    #[test]
+    fn test_duplicates() {
+        assert!(all_the_same(
+            &[single_elem_vec(), single_elem_vec()].concat()
+        ));
+    }
+
+    #[test]
+    fn test_differences() {
+        assert!(!all_the_same(
+            &[
+                vec![EnvironmentRow {
+                    commit_sha: "fooo".to_string(),
+                    ..single_elem_vec().first().unwrap().to_owned()
+                }],
+                single_elem_vec()
+            ]
+            .concat()
+        ));
+    }
+
+    #[test]
+    fn test_drifted_projects() {
+        let drifted = EnvironmentRow {
+            project_name: "other".to_string(),
+            commit_sha: "fooo".to_string(),
+            ..single_elem_vec()[0].clone()
+        };
+        let mut rows = [single_elem_vec(), single_elem_vec()].concat();
+        assert_eq!(drifted_projects(&rows), 0);
+        rows.push(drifted.clone());
+        rows.push(EnvironmentRow {
+            commit_sha: "bar".to_string(),
+            ..drifted.clone()
+        });
+        assert_eq!(drifted_projects(&rows), 1);
+        // A project of the same name in another instance is another project
+        rows.push(EnvironmentRow {
+            instance: "elsewhere".to_string(),
+            ..drifted.clone()
+        });
+        assert_eq!(drifted_projects(&rows), 1);
+        // And so is one in another namespace
+        rows.push(EnvironmentRow {
+            namespace: "elsewhere".to_string(),
+            ..drifted
+        });
+        assert_eq!(drifted_projects(&rows), 1);
+    }
+
+    #[test]
+    fn test_has_changed() {
+        let previous = single_elem_vec();
+        let row = previous[0].clone();
+        assert!(!has_changed(&row, &previous));
+        assert!(!has_changed(
+            &EnvironmentRow {
+                updated: "a minute ago".to_string(),
+                ..row.clone()
+            },
+            &previous
+        ));
+        assert!(has_changed(
+            &EnvironmentRow {
+                commit_sha: "fooo".to_string(),
+                ..row.clone()
+            },
+            &previous
+        ));
+        assert!(has_changed(
+            &EnvironmentRow {
+                instance: "other".to_string(),
+                ..row.clone()
+            },
+            &previous
+        ));
+        assert!(has_changed(&row, &[]));
+    }
+
+    #[test]
+    fn test_staleness() {
+        let now = Utc::now();
+        let days = |n| Some(now - chrono::Duration::days(n));
+        let two_weeks = Some(Duration::from_secs(14 * 86400));
+        assert_eq!(staleness(days(3), now, two_weeks), None);
+        assert_eq!(staleness(days(20), now, two_weeks), Some(Color::Yellow));
+        assert_eq!(staleness(days(30), now, two_weeks), Some(Color::Red));
+        assert_eq!(staleness(days(30), now, None), None);
+        assert_eq!(staleness(None, now, two_weeks), None);
+    }
+
+    #[test]
+    fn test_by_tier() {
+        let row = |project: &str, tier: &str| EnvironmentRow {
+            project_name: project.to_string(),
+            tier: tier.to_string(),
+            ..single_elem_vec()[0].clone()
+        };
+        let rows = vec![
+            row("b", "production"),
+            row("a", ""),
+            row("c", "development"),
+            row("a", "production"),
+        ];
+        let tiers: Vec<(&str, String)> = by_tier(&rows)
+            .into_iter()
+            .map(|(tier, rows)| (tier, rows.iter().map(|r| r.project_name.as_str()).join(",")))
+            .collect();
+        assert_eq!(
+            tiers,
+            vec![
+                ("production", "a,b".to_string()),
+                ("development", "c".to_string()),
+                ("unknown", "a".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_widths_fit_headers() {
+        let widths = Widths::of(&single_elem_vec());
+        assert_eq!(widths.project, 7);
+        assert_eq!(widths.environment, 11);
+        assert_eq!(widths.deployment, 19);
+        assert_eq!(widths.state, 5);
+        assert_eq!(widths.ref_, 3);
+        assert_eq!(widths.status, 6);
+        assert_eq!(widths.namespace, 0);
+        let rows = vec![EnvironmentRow {
+            namespace: "ops".to_string(),
+            ..single_elem_vec()[0].clone()
+        }];
+        assert_eq!(Widths::of(&rows).namespace, 9);
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("Fix login", 10), "Fix login");
+        assert_eq!(truncate("Fix the login page", 10), "Fix the l…");
+        assert_eq!(truncate("Überall", 4), "Übe…");
+    }
+
+    #[test]
+    fn test_write_json_lines() {
+        let rows = [single_elem_vec(), single_elem_vec()].concat();
+        let mut out = vec![];
+        write_json_lines(&mut out, &rows).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let row: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(row["environment_name"], "env");
+        assert!(row.get("instance").is_none());
+    }
+}
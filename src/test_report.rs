@@ -0,0 +1,124 @@
+use gitlab::api::AsyncQuery;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+use crate::client::Client;
+use crate::endpoints;
+use crate::error::ApiError;
+use crate::types::ProjectId;
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct TestCase {
+    pub status: String,
+    pub name: String,
+    #[serde(default)]
+    pub classname: String,
+    /// In seconds.
+    #[serde(default)]
+    pub execution_time: f64,
+    pub system_output: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct TestSuite {
+    pub name: String,
+    pub test_cases: Vec<TestCase>,
+}
+
+#[derive(Deserialize)]
+pub struct TestReport {
+    pub total_count: usize,
+    pub failed_count: usize,
+    #[serde(default)]
+    pub error_count: usize,
+    pub test_suites: Vec<TestSuite>,
+}
+
+/// A test case that failed or errored, with the suite it belongs to.
+#[derive(Clone, Serialize)]
+pub struct FailedTest {
+    pub suite: String,
+    #[serde(flatten)]
+    pub case: TestCase,
+}
+
+impl FailedTest {
+    fn key(&self) -> (&str, &str, &str) {
+        (&self.suite, &self.case.classname, &self.case.name)
+    }
+}
+
+impl TestReport {
+    pub fn failed(&self) -> Vec<FailedTest> {
+        self.test_suites
+            .iter()
+            .flat_map(|suite| {
+                suite
+                    .test_cases
+                    .iter()
+                    .filter(|case| case.status == "failed" || case.status == "error")
+                    .map(move |case| FailedTest {
+                        suite: suite.name.clone(),
+                        case: case.clone(),
+                    })
+            })
+            .collect()
+    }
+}
+
+/// The tests that fail in `report` but did not in `base`.
+pub fn newly_failing(report: &TestReport, base: &TestReport) -> Vec<FailedTest> {
+    let base = base.failed();
+    let before: BTreeSet<_> = base.iter().map(FailedTest::key).collect();
+    report
+        .failed()
+        .into_iter()
+        .filter(|test| !before.contains(&test.key()))
+        .collect()
+}
+
+pub async fn get(
+    gitlab: &Client,
+    project: ProjectId,
+    pipeline: u64,
+) -> Result<TestReport, ApiError> {
+    let endpoint = endpoints::TestReport { project, pipeline };
+    Ok(endpoint.query_async(gitlab).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(cases: &[(&str, &str)]) -> TestReport {
+        TestReport {
+            total_count: cases.len(),
+            failed_count: 0,
+            error_count: 0,
+            test_suites: vec![TestSuite {
+                name: "rspec".to_string(),
+                test_cases: cases
+                    .iter()
+                    .map(|(name, status)| TestCase {
+                        status: status.to_string(),
+                        name: name.to_string(),
+                        classname: "spec".to_string(),
+                        execution_time: 0.1,
+                        system_output: None,
+                    })
+                    .collect(),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_newly_failing() {
+        let base = report(&[("a", "failed"), ("b", "success"), ("c", "success")]);
+        let report = report(&[("a", "failed"), ("b", "error"), ("c", "success")]);
+        let names: Vec<String> = newly_failing(&report, &base)
+            .into_iter()
+            .map(|test| test.case.name)
+            .collect();
+        assert_eq!(names, vec!["b"]);
+    }
+}
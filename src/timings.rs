@@ -0,0 +1,120 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Phase {
+    Discovery,
+    Graphql,
+    EnvironmentList,
+    EnvironmentDetail,
+    Render,
+}
+
+impl Phase {
+    fn name(self) -> &'static str {
+        match self {
+            Phase::Discovery => "discovery",
+            Phase::Graphql => "graphql",
+            Phase::EnvironmentList => "env list",
+            Phase::EnvironmentDetail => "env detail",
+            Phase::Render => "render",
+        }
+    }
+}
+
+/// What happened in one phase. Phases run concurrently for many projects, so the time spent
+/// in them adds up to more than the wall-clock time from their first start to their last end.
+#[derive(Clone, Copy, Debug)]
+struct Span {
+    count: usize,
+    busy: Duration,
+    first_start: Instant,
+    last_end: Instant,
+}
+
+impl Span {
+    fn wall(&self) -> Duration {
+        self.last_end.duration_since(self.first_start)
+    }
+}
+
+/// Collects how long each phase of a command takes, if enabled with `--timings`.
+pub struct Timings {
+    enabled: bool,
+    spans: Mutex<BTreeMap<Phase, Span>>,
+}
+
+impl Timings {
+    pub fn new(enabled: bool) -> Timings {
+        Timings {
+            enabled,
+            spans: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Records that `phase` ran from `start` until now.
+    pub fn record(&self, phase: Phase, start: Instant) {
+        if !self.enabled {
+            return;
+        }
+        let end = Instant::now();
+        let mut spans = self.spans.lock().unwrap();
+        let span = spans.entry(phase).or_insert(Span {
+            count: 0,
+            busy: Duration::ZERO,
+            first_start: start,
+            last_end: end,
+        });
+        span.count += 1;
+        span.busy += end.duration_since(start);
+        span.first_start = span.first_start.min(start);
+        span.last_end = span.last_end.max(end);
+    }
+
+    /// Prints the profile to stderr, so that it never mixes with the table.
+    pub fn print(&self, total: Duration) {
+        if !self.enabled {
+            return;
+        }
+        eprintln!();
+        eprintln!(
+            "{:12}  {:>6}  {:>10}  {:>10}",
+            "PHASE", "COUNT", "WALL", "BUSY"
+        );
+        for (phase, span) in self.spans.lock().unwrap().iter() {
+            eprintln!(
+                "{:12}  {:>6}  {:>10.2?}  {:>10.2?}",
+                phase.name(),
+                span.count,
+                span.wall(),
+                span.busy
+            );
+        }
+        eprintln!("{:12}  {:>6}  {:>10.2?}", "total", "", total);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overlapping_spans() {
+        let timings = Timings::new(true);
+        let start = Instant::now();
+        timings.record(Phase::EnvironmentList, start);
+        timings.record(Phase::EnvironmentList, start);
+        let spans = timings.spans.lock().unwrap();
+        let span = spans[&Phase::EnvironmentList];
+        assert_eq!(span.count, 2);
+        assert!(span.busy >= span.wall());
+    }
+
+    #[test]
+    fn test_disabled_records_nothing() {
+        let timings = Timings::new(false);
+        timings.record(Phase::Render, Instant::now());
+        assert!(timings.spans.lock().unwrap().is_empty());
+    }
+}
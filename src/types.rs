@@ -0,0 +1,158 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+pub type ProjectId = u64;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Project {
+    pub id: ProjectId,
+    pub name: String,
+    #[serde(default)]
+    pub path_with_namespace: String,
+    #[serde(default)]
+    pub topics: Vec<String>,
+}
+
+/// Who may deploy to a protected environment.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub enum Deployer {
+    /// Everyone with at least this access level.
+    AccessLevel(u64),
+    User(u64),
+    Group(u64),
+}
+
+/// One of the deployers of a protected environment, as GitLab lists them.
+#[derive(Clone, Debug, Deserialize)]
+pub struct DeployAccessLevel {
+    pub id: u64,
+    pub access_level: Option<u64>,
+    pub user_id: Option<u64>,
+    pub group_id: Option<u64>,
+    /// E.g. `Maintainers` or the name of the user or group.
+    pub access_level_description: String,
+}
+
+impl DeployAccessLevel {
+    pub fn deployer(&self) -> Option<Deployer> {
+        match (self.user_id, self.group_id, self.access_level) {
+            (Some(user), _, _) => Some(Deployer::User(user)),
+            (None, Some(group), _) => Some(Deployer::Group(group)),
+            (None, None, Some(level)) => Some(Deployer::AccessLevel(level)),
+            (None, None, None) => None,
+        }
+    }
+}
+
+/// An environment that only some may deploy to, by name in a project or by tier in a group.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ProtectedEnvironment {
+    pub name: String,
+    #[serde(default)]
+    pub deploy_access_levels: Vec<DeployAccessLevel>,
+    #[serde(default)]
+    pub required_approval_count: u64,
+}
+
+/// Any resource that GitLab shows in the browser.
+#[derive(Clone, Debug, Deserialize)]
+pub struct WebUrl {
+    pub web_url: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct User {
+    pub username: String,
+}
+
+/// The user that the access token belongs to.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CurrentUser {
+    pub username: String,
+    /// Only sent to administrators.
+    #[serde(default)]
+    pub is_admin: bool,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Commit {
+    pub short_id: Option<String>,
+    /// The first line of the commit message.
+    #[serde(default)]
+    pub title: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct DeployablePipeline {
+    /// E.g. `success`, `failed` or `running`.
+    pub status: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Deployable {
+    /// The job that deployed, missing if GitLab does not say.
+    #[serde(default)]
+    pub id: Option<u64>,
+    pub commit: Commit,
+    /// The pipeline of the job, missing if GitLab does not say.
+    #[serde(default)]
+    pub pipeline: Option<DeployablePipeline>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Deployment {
+    pub iid: u64,
+    pub created_at: DateTime<Utc>,
+    /// E.g. `created`, `running`, `success` or `failed`, missing if GitLab does not say.
+    #[serde(default)]
+    pub status: Option<String>,
+    /// The branch or tag that was deployed.
+    #[serde(default, rename = "ref")]
+    pub ref_: Option<String>,
+    pub user: User,
+    pub deployable: Deployable,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct JobPipeline {
+    pub id: u64,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct DeploymentJob {
+    pub id: u64,
+    /// E.g. `manual`, `running` or `success`.
+    pub status: String,
+    /// In seconds, missing while the job runs.
+    pub duration: Option<f64>,
+    pub pipeline: JobPipeline,
+}
+
+/// A deployment as listed in the history of an environment.
+#[derive(Clone, Debug, Deserialize)]
+pub struct PastDeployment {
+    pub id: u64,
+    pub iid: u64,
+    pub created_at: DateTime<Utc>,
+    /// E.g. `success`, `failed` or `running`.
+    pub status: String,
+    pub sha: String,
+    pub user: User,
+    pub deployable: Option<DeploymentJob>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Environment {
+    pub id: u64,
+    pub name: String,
+    /// E.g. `production` or `development`, missing before GitLab 13.10.
+    #[serde(default)]
+    pub tier: Option<String>,
+    /// Where the environment is reachable, if it has a URL.
+    #[serde(default)]
+    pub external_url: Option<String>,
+    /// `available`, `stopping` or `stopped`.
+    #[serde(default)]
+    pub state: Option<String>,
+    pub last_deployment: Option<Deployment>,
+}
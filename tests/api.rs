@@ -0,0 +1,171 @@
+use std::fs;
+
+use gitlabctl::client::{Client, Options};
+use gitlabctl::config::Context;
+use gitlabctl::environments::{get_project_rows, get_projects_for_namespace, PROJECT_CONCURRENCY};
+use gitlabctl::error::EXIT_DRIFT;
+use gitlabctl::table::drifted_projects;
+use gitlabctl::timings::Timings;
+use tokio::process::Command;
+use tokio::sync::Semaphore;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn fixture(name: &str) -> String {
+    fs::read_to_string(format!(
+        "{}/tests/fixtures/{}",
+        env!("CARGO_MANIFEST_DIR"),
+        name
+    ))
+    .unwrap()
+}
+
+async fn mock(server: &MockServer, endpoint: &str, fixture_name: &str) {
+    Mock::given(method("GET"))
+        .and(path(format!("/api/v4/{}", endpoint)))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_raw(fixture(fixture_name), "application/json")
+                .insert_header("etag", format!("W/\"{}\"", fixture_name).as_str()),
+        )
+        .mount(server)
+        .await;
+}
+
+/// A fake GitLab with the group `platform`, whose two projects have some environments.
+async fn fake_gitlab() -> MockServer {
+    let server = MockServer::start().await;
+    mock(&server, "user", "user.json").await;
+    mock(&server, "groups/platform/projects", "projects.json").await;
+    mock(&server, "projects/1/environments", "environments-1.json").await;
+    mock(&server, "projects/1/environments/11", "environment-11.json").await;
+    mock(&server, "projects/2/environments", "environments-2.json").await;
+    Mock::given(method("GET"))
+        .and(path("/api/v4/projects/2/environments/22"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            r#"{"id": 22, "name": "review/never-deployed", "last_deployment": null}"#,
+            "application/json",
+        ))
+        .mount(&server)
+        .await;
+    server
+}
+
+fn context(server: &MockServer) -> Context {
+    Context {
+        server: server.uri(),
+        access_token: "token".to_string(),
+        namespace: Some("platform".to_string()),
+    }
+}
+
+#[tokio::test]
+async fn test_environment_rows_of_group() {
+    let server = fake_gitlab().await;
+    let gitlab = Client::new(&context(&server), Options::default())
+        .await
+        .unwrap();
+    let timings = Timings::new(false);
+    let projects = get_projects_for_namespace(&gitlab, "platform", None, &timings, |_| ())
+        .await
+        .unwrap();
+    assert_eq!(
+        projects,
+        vec![
+            ("my-service-a".to_string(), 1),
+            ("my-service-b".to_string(), 2)
+        ]
+    );
+
+    let semaphore = Semaphore::new(4);
+    let mut rows = vec![];
+    for project in projects {
+        rows.extend(
+            get_project_rows(
+                &gitlab,
+                &semaphore,
+                project,
+                None,
+                PROJECT_CONCURRENCY,
+                &timings,
+            )
+            .await
+            .rows,
+        );
+    }
+    let envs: Vec<_> = rows
+        .iter()
+        .map(|row| (row.environment_name.as_str(), row.commit_sha.as_str()))
+        .collect();
+    // The never deployed environment is left out and prod of my-service-a needed its details
+    assert_eq!(
+        envs,
+        vec![
+            ("prod", "63c3655f"),
+            ("stable", "63c3655f"),
+            ("master", "38588be5"),
+            ("prod", "3c096e4b")
+        ]
+    );
+    assert_eq!(rows[0].deployment_by, "78 by bijancn");
+    assert_eq!(drifted_projects(&rows), 1);
+}
+
+/// A home directory whose config points at `server`.
+fn home_for(server: &MockServer) -> tempfile::TempDir {
+    let home = tempfile::tempdir().unwrap();
+    fs::create_dir_all(home.path().join(".config")).unwrap();
+    fs::write(
+        home.path().join(".config/gitlab.toml"),
+        format!(
+            "server = \"{}\"\naccess_token = \"token\"\nnamespace = \"platform\"\n",
+            server.uri()
+        ),
+    )
+    .unwrap();
+    home
+}
+
+async fn gitlabctl(home: &tempfile::TempDir, args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_gitlabctl"))
+        .args(args)
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .env("XDG_CACHE_HOME", home.path().join(".cache"))
+        .env("CLICOLOR", "0")
+        .output()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_get_environments_command() {
+    let server = fake_gitlab().await;
+    let home = home_for(&server);
+    let output = gitlabctl(
+        &home,
+        &["get", "environments", "--rest", "--no-cache", "--exit-code"],
+    )
+    .await;
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("PROJECT"), "{}", stdout);
+    assert!(stdout.contains("my-service-a"));
+    assert!(stdout.contains("3c096e4b"));
+    assert!(!stdout.contains("never-deployed"));
+    assert_eq!(output.status.code(), Some(EXIT_DRIFT));
+}
+
+#[tokio::test]
+async fn test_offline_answers_from_the_cache() {
+    let server = fake_gitlab().await;
+    let home = home_for(&server);
+    let online = gitlabctl(&home, &["get", "environments", "--rest"]).await;
+    assert!(online.status.success());
+    drop(server);
+
+    let offline = gitlabctl(&home, &["get", "environments", "--offline"]).await;
+    let stdout = String::from_utf8(offline.stdout).unwrap();
+    let stderr = String::from_utf8(offline.stderr).unwrap();
+    assert!(stdout.contains("3c096e4b"), "{}", stdout);
+    assert!(stderr.contains("Offline, data as of"), "{}", stderr);
+}